@@ -0,0 +1,29 @@
+use std::{fs, io};
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+
+use peppi::serde::de;
+
+const REPLAYS: [&str; 4] = ["v0.1", "game", "items", "console_name"];
+
+/// Handlers that ignore every event, to measure the parser alone.
+struct Noop;
+impl de::Handlers for Noop { }
+
+fn parse(c: &mut Criterion) {
+	let mut group = c.benchmark_group("parse");
+	for name in REPLAYS {
+		let bytes = fs::read(format!("tests/data/{}.slp", name)).unwrap();
+		group.throughput(Throughput::Bytes(bytes.len() as u64));
+		group.bench_with_input(BenchmarkId::new("deserialize", name), &bytes, |b, bytes|
+			b.iter(|| de::deserialize(&mut &bytes[..], &mut Noop, None).unwrap()));
+		group.bench_with_input(BenchmarkId::new("game", name), &bytes, |b, bytes|
+			b.iter(|| peppi::game(&mut &bytes[..], None, None).unwrap()));
+		group.bench_with_input(BenchmarkId::new("extract_metadata", name), &bytes, |b, bytes|
+			b.iter(|| peppi::extract_metadata(&mut io::Cursor::new(&bytes[..])).unwrap()));
+	}
+	group.finish();
+}
+
+criterion_group!(benches, parse);
+criterion_main!(benches);
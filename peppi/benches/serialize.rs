@@ -0,0 +1,23 @@
+use std::{fs, io};
+
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+
+use peppi::serde::ser;
+
+fn serialize(c: &mut Criterion) {
+	let bytes = fs::read("tests/data/console_name.slp").unwrap();
+	let game = peppi::game(&mut &bytes[..], None, None).unwrap();
+
+	let mut group = c.benchmark_group("serialize");
+	group.throughput(Throughput::Bytes(bytes.len() as u64));
+	group.bench_function("cursor", |b| b.iter(|| {
+		let mut buf = io::Cursor::new(Vec::new());
+		ser::serialize(&mut buf, &game).unwrap();
+		buf.into_inner()
+	}));
+	group.bench_function("to_vec", |b| b.iter(|| ser::serialize_to_vec(&game).unwrap()));
+	group.finish();
+}
+
+criterion_group!(benches, serialize);
+criterion_main!(benches);
@@ -0,0 +1,59 @@
+//! Communication files for Slippi's playback Dolphin (passed via `-i`).
+
+use std::path::Path;
+
+use serde_json::{json, Value};
+
+use crate::model::game::Game;
+
+/// One clip to play back.
+#[derive(Clone, Copy, Debug)]
+pub struct QueueEntry<'a> {
+	/// where Dolphin can find the replay file
+	pub path: &'a Path,
+	pub game: &'a Game,
+	/// first frame index to play
+	pub start: i32,
+	/// last frame index to play
+	pub end: i32,
+}
+
+/// A comm file making playback Dolphin play `entries` in order, e.g. to show off
+/// combos (see `stats::combo::clip_ranges`):
+///
+/// ```json
+/// {
+///   "mode": "queue",
+///   "replay": "",
+///   "isRealTimeMode": false,
+///   "outputOverlayFiles": true,
+///   "queue": [
+///     {
+///       "path": "C:\\replays\\Game_20190421T195033.slp",
+///       "startFrame": 2290,
+///       "endFrame": 2457,
+///       "gameStartAt": "2019-04-21T19:50:33",
+///       "gameStation": "vgbootcamp"
+///     }
+///   ]
+/// }
+/// ```
+///
+/// `gameStartAt` & `gameStation` (shown by overlays) come from the metadata's `startAt`
+/// & `consoleNick`, and are empty if those are missing.
+pub fn playback_queue(entries: &[QueueEntry]) -> Value {
+	let queue: Vec<Value> = entries.iter().map(|e| json!({
+		"path": e.path.to_string_lossy(),
+		"startFrame": e.start,
+		"endFrame": e.end,
+		"gameStartAt": e.game.get_metadata("startAt").and_then(Value::as_str).unwrap_or(""),
+		"gameStation": e.game.metadata.console.as_deref().unwrap_or(""),
+	})).collect();
+	json!({
+		"mode": "queue",
+		"replay": "",
+		"isRealTimeMode": false,
+		"outputOverlayFiles": true,
+		"queue": queue,
+	})
+}
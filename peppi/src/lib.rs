@@ -25,6 +25,8 @@ pub(crate) mod ubjson {
 	pub(crate) mod ser;
 }
 
+pub mod comm;
+
 pub mod model {
 	#[macro_use] #[doc(hidden)] pub(crate) mod pseudo_bitmask;
 	#[macro_use] #[doc(hidden)] pub(crate) mod pseudo_enum;
@@ -43,6 +45,7 @@ pub mod model {
 		pub mod character;
 		pub mod ground;
 		pub mod item;
+		pub mod special_move;
 		pub mod stage;
 	}
 }
@@ -51,13 +54,41 @@ pub mod serde {
 	pub mod arrow;
 	pub mod collect;
 	pub mod de;
+	pub mod indexed;
+	#[cfg(feature = "npy")]
+	pub mod npy;
 	pub mod ser;
 }
 
+pub mod stats {
+	pub mod aerial;
+	pub mod combo;
+	pub mod conversion;
+	pub mod corpus;
+	pub mod crossup;
+	pub mod death;
+	pub mod di;
+	pub mod grab;
+	pub mod kill;
+	pub mod ledge;
+	pub mod movement;
+	pub mod neutral;
+	pub mod percent;
+	pub mod sdi;
+	pub mod slippi_js;
+	pub mod stale;
+	pub mod stock;
+	pub mod tech;
+	pub mod velocity;
+}
+
+#[cfg(feature = "testing")]
+pub mod test_util;
+
 use std::{
 	error,
 	fmt,
-	io::{self, Read},
+	io::{self, Read, Seek},
 };
 
 #[derive(Debug)]
@@ -100,6 +131,8 @@ impl<R: Read> Read for TrackingReader<R> {
 }
 
 /// Parse a Slippi replay from `r`, passing events to the callbacks in `handlers` as they occur.
+///
+/// Only reads forward, so `r` need not be seekable (e.g. a pipe or stdin).
 pub fn parse<R: Read, H: serde::de::Handlers>(r: &mut R, handlers: &mut H, opts: Option<serde::de::Opts>) -> std::result::Result<(), ParseError> {
 	let mut r = TrackingReader {
 		pos: 0,
@@ -110,6 +143,8 @@ pub fn parse<R: Read, H: serde::de::Handlers>(r: &mut R, handlers: &mut H, opts:
 }
 
 /// Parse a Slippi replay from `r`, returning a `game::Game` object.
+///
+/// Like `parse`, this does not require `r` to be seekable.
 pub fn game<R: Read>(r: &mut R, parse_opts: Option<serde::de::Opts>, collect_opts: Option<serde::collect::Opts>) -> Result<model::game::Game, ParseError> {
 	let mut game_parser = serde::collect::Collector {
 		opts: collect_opts.unwrap_or_default(),
@@ -118,3 +153,22 @@ pub fn game<R: Read>(r: &mut R, parse_opts: Option<serde::de::Opts>, collect_opt
 	parse(r, &mut game_parser, parse_opts)
 		.and_then(|_| game_parser.into_game().map_err(|e| ParseError { error: e, pos: None }))
 }
+
+/// Like `game`, but fails once the replay has more than `max_frames` frames (see
+/// `serde::collect::Opts::max_frames`), for parsing untrusted replays.
+pub fn parse_limited<R: Read>(r: &mut R, max_frames: usize) -> Result<model::game::Game, ParseError> {
+	game(r, None, Some(serde::collect::Opts {
+		max_frames: Some(max_frames),
+		..Default::default()
+	}))
+}
+
+/// Read only the metadata of a Slippi replay from `r`, skipping the game itself.
+///
+/// Much faster than `game` when that's all you need, but requires `r` to be seekable,
+/// and fails for in-progress replays (which don't have metadata yet).
+pub fn extract_metadata<R: Read + Seek>(r: &mut R) -> Result<model::metadata::Metadata, ParseError> {
+	serde::de::metadata_only(r)
+		.and_then(|m| model::metadata::parse(&m))
+		.map_err(|e| ParseError { error: e, pos: r.stream_position().ok() })
+}
@@ -9,8 +9,12 @@ macro_rules! state {
 		$common: ident ( $common_type: ident ),
 		$( $variant: ident ( $variant_type: ident ) => $internal: ident ),* $(,)?
 	}) => {
+		/// Action state, interpreted according to the character.
+		/// States of characters we don't know about are `Unknown`, and
+		/// more variants may be added as characters are.
 		#[derive(Copy, Clone, PartialEq, Eq, serde::Serialize)]
 		#[serde(untagged)]
+		#[non_exhaustive]
 		pub enum $name {
 			$common($common_type),
 			$unknown(u16),
@@ -97,6 +101,14 @@ impl Default for State {
 	}
 }
 
+/// Only common states can be told apart without the character, so others come out as
+/// `Unknown` (see `game::migrate`).
+impl<'de> serde::Deserialize<'de> for State {
+	fn deserialize<D: serde::Deserializer<'de>>(d: D) -> Result<Self, D::Error> {
+		u16::deserialize(d).map(<State as From<u16>>::from)
+	}
+}
+
 impl From<u16> for State {
 	fn from(n: u16) -> State {
 		if n <= MAX_COMMON_STATE {
@@ -90,3 +90,55 @@ pseudo_enum!(Attack: u8 {
 	92 => SUPER_SCOPE_CHARGED,
 	93 => HAMMER,
 });
+
+impl Attack {
+	/// Short, human-friendly name for common attacks (e.g. `"fsmash"`, `"nair"`, `"fthrow"`).
+	/// Item swings & Kirby's copied specials are `None`; use `String::try_from` for their full names.
+	pub fn short_name(self) -> Option<&'static str> {
+		Some(match self {
+			Attack::NON_STALING => "misc",
+			Attack::JAB_1 | Attack::JAB_2 | Attack::JAB_3 => "jab",
+			Attack::RAPID_JABS => "rapid-jabs",
+			Attack::DASH_ATTACK => "dash",
+			Attack::SIDE_TILT => "ftilt",
+			Attack::UP_TILT => "utilt",
+			Attack::DOWN_TILT => "dtilt",
+			Attack::SIDE_SMASH => "fsmash",
+			Attack::UP_SMASH => "usmash",
+			Attack::DOWN_SMASH => "dsmash",
+			Attack::NAIR => "nair",
+			Attack::FAIR => "fair",
+			Attack::BAIR => "bair",
+			Attack::UAIR => "uair",
+			Attack::DAIR => "dair",
+			Attack::NEUTRAL_SPECIAL => "neutral-b",
+			Attack::SIDE_SPECIAL => "side-b",
+			Attack::UP_SPECIAL => "up-b",
+			Attack::DOWN_SPECIAL => "down-b",
+			Attack::GET_UP_ATTACK_FROM_BACK | Attack::GET_UP_ATTACK_FROM_FRONT => "getup",
+			Attack::PUMMEL => "pummel",
+			Attack::FORWARD_THROW => "fthrow",
+			Attack::BACK_THROW => "bthrow",
+			Attack::UP_THROW => "uthrow",
+			Attack::DOWN_THROW => "dthrow",
+			Attack::CARGO_FORWARD_THROW => "cargo-fthrow",
+			Attack::CARGO_BACK_THROW => "cargo-bthrow",
+			Attack::CARGO_UP_THROW => "cargo-uthrow",
+			Attack::CARGO_DOWN_THROW => "cargo-dthrow",
+			Attack::LEDGE_GET_UP_ATTACK_100 | Attack::LEDGE_GET_UP_ATTACK => "edge",
+			_ => return None,
+		})
+	}
+
+	pub fn is_aerial(self) -> bool {
+		(Attack::NAIR.0 ..= Attack::DAIR.0).contains(&self.0)
+	}
+
+	pub fn is_special(self) -> bool {
+		(Attack::NEUTRAL_SPECIAL.0 ..= Attack::KIRBY_HAT_ROY_NEUTRAL_SPECIAL.0).contains(&self.0)
+	}
+
+	pub fn is_throw(self) -> bool {
+		(Attack::FORWARD_THROW.0 ..= Attack::CARGO_DOWN_THROW.0).contains(&self.0)
+	}
+}
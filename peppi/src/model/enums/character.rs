@@ -69,3 +69,96 @@ pseudo_enum!(External: u8 {
 	31 => SANDBAG,
 	32 => POPO,
 });
+
+/// Color of a character's costume (and stock icon), as in the team-color
+/// approximations the game uses. Costume 0 is always `Default`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, serde::Serialize)]
+pub enum CostumeColor {
+	Default,
+	Red,
+	Blue,
+	Green,
+	Yellow,
+	Orange,
+	Pink,
+	Purple,
+	Cyan,
+	White,
+	Black,
+}
+
+impl External {
+	/// Available costumes for this character, in order of their costume index.
+	pub fn costumes(self) -> &'static [CostumeColor] {
+		use CostumeColor::*;
+		match self {
+			External::CAPTAIN_FALCON => &[Default, Black, Red, White, Green, Blue],
+			External::DONKEY_KONG => &[Default, Black, Red, Blue, Green],
+			External::FOX => &[Default, Red, Blue, Green],
+			External::GAME_AND_WATCH => &[Default, Red, Blue, Green],
+			External::KIRBY => &[Default, Yellow, Blue, Red, Green, White],
+			External::BOWSER => &[Default, Red, Blue, Black],
+			External::LINK => &[Default, Red, Blue, Black, White],
+			External::LUIGI => &[Default, White, Blue, Red],
+			External::MARIO => &[Default, Yellow, Black, Blue, Green],
+			External::MARTH => &[Default, Red, Green, Black, White],
+			External::MEWTWO => &[Default, Red, Blue, Green],
+			External::NESS => &[Default, Yellow, Blue, Green],
+			External::PEACH => &[Default, Yellow, White, Blue, Green],
+			External::PIKACHU => &[Default, Red, Blue, Green],
+			External::ICE_CLIMBERS => &[Default, Green, Orange, Red],
+			External::JIGGLYPUFF => &[Default, Red, Blue, Green, Yellow],
+			External::SAMUS => &[Default, Pink, Black, Green, Purple],
+			External::YOSHI => &[Default, Red, Blue, Yellow, Pink, Cyan],
+			External::ZELDA => &[Default, Red, Blue, Green, White],
+			External::SHEIK => &[Default, Red, Blue, Green, White],
+			External::FALCO => &[Default, Red, Blue, Green],
+			External::YOUNG_LINK => &[Default, Red, Blue, White, Black],
+			External::DR_MARIO => &[Default, Red, Blue, Green, Black],
+			External::ROY => &[Default, Red, Blue, Green, Yellow],
+			External::PICHU => &[Default, Red, Blue, Green],
+			External::GANONDORF => &[Default, Red, Blue, Green, Purple],
+			_ => &[Default],
+		}
+	}
+
+	/// In-game character this character starts the match as (Popo for the Ice Climbers),
+	/// or `None` for an unknown character.
+	pub fn internal(self) -> Option<Internal> {
+		Some(match self {
+			External::CAPTAIN_FALCON => Internal::CAPTAIN_FALCON,
+			External::DONKEY_KONG => Internal::DONKEY_KONG,
+			External::FOX => Internal::FOX,
+			External::GAME_AND_WATCH => Internal::GAME_AND_WATCH,
+			External::KIRBY => Internal::KIRBY,
+			External::BOWSER => Internal::BOWSER,
+			External::LINK => Internal::LINK,
+			External::LUIGI => Internal::LUIGI,
+			External::MARIO => Internal::MARIO,
+			External::MARTH => Internal::MARTH,
+			External::MEWTWO => Internal::MEWTWO,
+			External::NESS => Internal::NESS,
+			External::PEACH => Internal::PEACH,
+			External::PIKACHU => Internal::PIKACHU,
+			External::ICE_CLIMBERS | External::POPO => Internal::POPO,
+			External::JIGGLYPUFF => Internal::JIGGLYPUFF,
+			External::SAMUS => Internal::SAMUS,
+			External::YOSHI => Internal::YOSHI,
+			External::ZELDA => Internal::ZELDA,
+			External::SHEIK => Internal::SHEIK,
+			External::FALCO => Internal::FALCO,
+			External::YOUNG_LINK => Internal::YOUNG_LINK,
+			External::DR_MARIO => Internal::DR_MARIO,
+			External::ROY => Internal::ROY,
+			External::PICHU => Internal::PICHU,
+			External::GANONDORF => Internal::GANONDORF,
+			External::MASTER_HAND => Internal::MASTER_HAND,
+			External::WIRE_FRAME_MALE => Internal::WIRE_FRAME_MALE,
+			External::WIRE_FRAME_FEMALE => Internal::WIRE_FRAME_FEMALE,
+			External::GIGA_BOWSER => Internal::GIGA_BOWSER,
+			External::CRAZY_HAND => Internal::CRAZY_HAND,
+			External::SANDBAG => Internal::SANDBAG,
+			_ => return None,
+		})
+	}
+}
@@ -1,3 +1,5 @@
+use super::stage::Stage;
+
 pseudo_enum!(Type: u16 {
 	000 => CAPSULE,
 	001 => BOX,
@@ -232,5 +234,20 @@ pseudo_enum!(Type: u16 {
 	236 => BIRDO_EGG,
 });
 
+impl Type {
+	/// Stages on which this (stage-specific) item can appear, or `None` for regular items.
+	pub fn stages(self) -> Option<&'static [Stage]> {
+		match self {
+			Type::TINGLE => Some(&[Stage::GREAT_BAY]),
+			Type::APPLE | Type::HEALING_APPLE => Some(&[Stage::GREEN_GREENS]),
+			Type::TOOL => Some(&[Stage::FLAT_ZONE]),
+			Type::BIRDO | Type::BIRDO_EGG => Some(&[Stage::MUSHROOM_KINGDOM_II]),
+			Type::ARWING_LASER => Some(&[Stage::CORNERIA, Stage::VENOM]),
+			Type::GREAT_FOX_LASER => Some(&[Stage::CORNERIA]),
+			_ => None,
+		}
+	}
+}
+
 pseudo_enum!(State: u8 {
 });
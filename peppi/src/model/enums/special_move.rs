@@ -0,0 +1,149 @@
+use serde::Serialize;
+
+use super::{
+	action_state::{
+		CaptainFalcon, Falco, Fox, Jigglypuff, Marth, Nana, Peach, Pikachu, Popo, Samus,
+		Sheik, State,
+	},
+	character::Internal,
+};
+
+/// A character's special (B) move, named after its action states. Only some characters'
+/// moves are covered so far, and more variants may be added as others are.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize)]
+#[non_exhaustive]
+pub enum SpecialMove {
+	// Fox & Falco
+	Blaster,
+	Illusion,
+	Phantasm,
+	FireFox,
+	FireBird,
+	/// down-B ("shine")
+	Reflector,
+	// Marth
+	ShieldBreaker,
+	DancingBlade,
+	DolphinSlash,
+	Counter,
+	// Sheik
+	NeedleStorm,
+	Chain,
+	Vanish,
+	Transform,
+	// Jigglypuff
+	Rollout,
+	Pound,
+	Sing,
+	Rest,
+	// Captain Falcon
+	FalconPunch,
+	RaptorBoost,
+	FalconDive,
+	FalconKick,
+	// Peach
+	Vegetable,
+	Bomber,
+	Parasol,
+	Toad,
+	// Ice Climbers
+	IceShot,
+	SquallHammer,
+	Belay,
+	Blizzard,
+	// Samus
+	ChargeShot,
+	Missile,
+	ScrewAttack,
+	Bomb,
+	// Pikachu
+	ThunderJolt,
+	SkullBash,
+	QuickAttack,
+	Thunder,
+}
+
+/// Finds the move whose inclusive range of state ids `table` contains `id`.
+fn lookup(id: u16, table: &[(u16, u16, SpecialMove)]) -> Option<SpecialMove> {
+	table.iter().find(|(first, last, _)| (*first ..= *last).contains(&id)).map(|(_, _, m)| *m)
+}
+
+impl SpecialMove {
+	/// The special move that action state `state` belongs to, for `character`.
+	/// `None` for common states, non-special states (e.g. Jigglypuff's extra jumps),
+	/// & characters we don't cover yet.
+	pub fn from_state(state: u16, character: Internal) -> Option<SpecialMove> {
+		use SpecialMove::*;
+		match State::from(state, character) {
+			State::Fox(s) => lookup(s.0, &[
+				(Fox::BLASTER_GROUND_STARTUP.0, Fox::BLASTER_AIR_END.0, Blaster),
+				(Fox::ILLUSION_GROUND_STARTUP.0, Fox::ILLUSION_AIR_END.0, Illusion),
+				(Fox::FIRE_FOX_GROUND_STARTUP.0, Fox::FIRE_FOX_BOUNCE_END.0, FireFox),
+				(Fox::REFLECTOR_GROUND_STARTUP.0, Fox::REFLECTOR_AIR_CHANGE_DIRECTION.0, Reflector),
+			]),
+			State::Falco(s) => lookup(s.0, &[
+				(Falco::BLASTER_GROUND_STARTUP.0, Falco::BLASTER_AIR_END.0, Blaster),
+				(Falco::PHANTASM_GROUND_STARTUP.0, Falco::PHANTASM_AIR_END.0, Phantasm),
+				(Falco::FIRE_BIRD_GROUND_STARTUP.0, Falco::FIRE_BIRD_BOUNCE_END.0, FireBird),
+				(Falco::REFLECTOR_GROUND_STARTUP.0, Falco::REFLECTOR_AIR_CHANGE_DIRECTION.0, Reflector),
+			]),
+			State::Marth(s) => lookup(s.0, &[
+				(Marth::SHIELD_BREAKER_GROUND_START_CHARGE.0, Marth::SHIELD_BREAKER_AIR_FULLY_CHARGED.0, ShieldBreaker),
+				(Marth::DANCING_BLADE_1_GROUND.0, Marth::DANCING_BLADE_4_DOWN_AIR.0, DancingBlade),
+				(Marth::DOLPHIN_SLASH_GROUND.0, Marth::DOLPHIN_SLASH_AIR.0, DolphinSlash),
+				(Marth::COUNTER_GROUND.0, Marth::COUNTER_AIR_HIT.0, Counter),
+			]),
+			State::Sheik(s) => lookup(s.0, &[
+				(Sheik::NEEDLE_STORM_GROUND_START_CHARGE.0, Sheik::NEEDLE_STORM_AIR_FIRE.0, NeedleStorm),
+				(Sheik::CHAIN_GROUND_STARTUP.0, Sheik::CHAIN_AIR_END.0, Chain),
+				(Sheik::VANISH_GROUND_STARTUP.0, Sheik::VANISH_AIR_REAPPEAR.0, Vanish),
+				(Sheik::TRANSFORM_GROUND.0, Sheik::TRANSFORM_AIR_ENDING.0, Transform),
+			]),
+			State::Jigglypuff(s) => lookup(s.0, &[
+				(Jigglypuff::ROLLOUT_GROUND_START_CHARGE_RIGHT.0, Jigglypuff::ROLLOUT_HIT.0, Rollout),
+				(Jigglypuff::POUND_GROUND.0, Jigglypuff::POUND_AIR.0, Pound),
+				(Jigglypuff::SING_GROUND_LEFT.0, Jigglypuff::SING_AIR_RIGHT.0, Sing),
+				(Jigglypuff::REST_GROUND_LEFT.0, Jigglypuff::REST_AIR_RIGHT.0, Rest),
+			]),
+			State::CaptainFalcon(s) => lookup(s.0, &[
+				(CaptainFalcon::FALCON_PUNCH_GROUND.0, CaptainFalcon::FALCON_PUNCH_AIR.0, FalconPunch),
+				(CaptainFalcon::RAPTOR_BOOST_GROUND.0, CaptainFalcon::RAPTOR_BOOST_AIR_HIT.0, RaptorBoost),
+				(CaptainFalcon::FALCON_DIVE_GROUND.0, CaptainFalcon::FALCON_DIVE_ENDING.0, FalconDive),
+				(CaptainFalcon::FALCON_KICK_GROUND.0, CaptainFalcon::FALCON_KICK_HIT_WALL.0, FalconKick),
+			]),
+			State::Peach(s) => lookup(s.0, &[
+				(Peach::VEGETABLE_GROUND.0, Peach::VEGETABLE_AIR.0, Vegetable),
+				(Peach::BOMBER_GROUND_STARTUP.0, Peach::BOMBER_AIR.0, Bomber),
+				(Peach::PARASOL_GROUND_START.0, Peach::PARASOL_AIR_START.0, Parasol),
+				(Peach::TOAD_GROUND.0, Peach::TOAD_AIR_ATTACK.0, Toad),
+				(Peach::PARASOL_OPENING.0, Peach::PARASOL_OPEN.0, Parasol),
+			]),
+			State::Popo(s) => lookup(s.0, &[
+				(Popo::ICE_SHOT_GROUND.0, Popo::ICE_SHOT_AIR.0, IceShot),
+				(Popo::SQUALL_HAMMER_GROUND_SOLO.0, Popo::SQUALL_HAMMER_AIR_TOGETHER.0, SquallHammer),
+				(Popo::BELAY_GROUND_STARTUP.0, Popo::BELAY_AIR_FAILED_CATAPULTING_END.0, Belay),
+				(Popo::BLIZZARD_GROUND.0, Popo::BLIZZARD_AIR.0, Blizzard),
+			]),
+			State::Nana(s) => lookup(s.0, &[
+				(Nana::ICE_SHOT_GROUND.0, Nana::ICE_SHOT_AIR.0, IceShot),
+				(Nana::BLIZZARD_GROUND.0, Nana::BLIZZARD_AIR.0, Blizzard),
+				(Nana::SQUALL_HAMMER_GROUND_TOGETHER.0, Nana::SQUALL_HAMMER_AIR_TOGETHER.0, SquallHammer),
+				(Nana::BELAY_CATAPULT_STARTUP.0, Nana::BELAY_CATAPULTING.0, Belay),
+			]),
+			State::Samus(s) => lookup(s.0, &[
+				(Samus::BOMB_JUMP_GROUND.0, Samus::BOMB_JUMP_AIR.0, Bomb),
+				(Samus::CHARGE_SHOT_GROUND_START.0, Samus::CHARGE_SHOT_AIR_FIRE.0, ChargeShot),
+				(Samus::MISSILE_GROUND.0, Samus::MISSILE_SMASH_AIR.0, Missile),
+				(Samus::SCREW_ATTACK_GROUND.0, Samus::SCREW_ATTACK_AIR.0, ScrewAttack),
+				(Samus::BOMB_END_GROUND.0, Samus::BOMB_AIR.0, Bomb),
+			]),
+			State::Pikachu(s) => lookup(s.0, &[
+				(Pikachu::THUNDER_JOLT_GROUND.0, Pikachu::THUNDER_JOLT_AIR.0, ThunderJolt),
+				(Pikachu::SKULL_BASH_GROUND_STARTUP.0, Pikachu::SKULL_BASH_AIR_TAKEOFF.0, SkullBash),
+				(Pikachu::QUICK_ATTACK_GROUND_STARTUP.0, Pikachu::QUICK_ATTACK_AIR_END.0, QuickAttack),
+				(Pikachu::THUNDER_GROUND_STARTUP.0, Pikachu::THUNDER_AIR_END.0, Thunder),
+			]),
+			_ => None,
+		}
+	}
+}
@@ -30,3 +30,42 @@ pseudo_enum!(Stage: u16 {
 	31 => BATTLEFIELD,
 	32 => FINAL_DESTINATION,
 });
+
+/// Boundaries beyond which characters are KO'd.
+#[derive(Clone, Copy, Debug, PartialEq, serde::Serialize)]
+pub struct BlastZones {
+	pub left: f32,
+	pub right: f32,
+	pub top: f32,
+	pub bottom: f32,
+}
+
+impl Stage {
+	/// Blast zones for tournament-legal stages.
+	pub fn blast_zones(self) -> Option<BlastZones> {
+		let (left, right, top, bottom) = match self {
+			Stage::FOUNTAIN_OF_DREAMS => (-198.75, 198.75, 202.5, -146.25),
+			Stage::POKEMON_STADIUM => (-230.0, 230.0, 180.0, -111.0),
+			Stage::YOSHIS_STORY => (-175.7, 173.6, 168.0, -91.0),
+			Stage::DREAM_LAND_N64 => (-255.0, 255.0, 250.0, -123.0),
+			Stage::BATTLEFIELD => (-224.0, 224.0, 200.0, -108.8),
+			Stage::FINAL_DESTINATION => (-246.0, 246.0, 188.0, -140.0),
+			_ => return None,
+		};
+		Some(BlastZones { left, right, top, bottom })
+	}
+
+	/// Horizontal distance from the center of the stage to either ledge (at ground level),
+	/// for tournament-legal stages.
+	pub fn ledge_x(self) -> Option<f32> {
+		match self {
+			Stage::FOUNTAIN_OF_DREAMS => Some(63.35),
+			Stage::POKEMON_STADIUM => Some(87.75),
+			Stage::YOSHIS_STORY => Some(56.0),
+			Stage::DREAM_LAND_N64 => Some(77.27),
+			Stage::BATTLEFIELD => Some(68.4),
+			Stage::FINAL_DESTINATION => Some(85.57),
+			_ => None,
+		}
+	}
+}
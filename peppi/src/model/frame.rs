@@ -1,4 +1,6 @@
 use serde::{
+	Deserialize,
+	Deserializer,
 	Serialize,
 	ser::SerializeStruct,
 };
@@ -6,7 +8,7 @@ use serde::{
 use crate::{
 	model::{
 		buttons,
-		enums::{action_state, attack, character, ground},
+		enums::{action_state, attack, character, ground, special_move},
 		item,
 		primitives::{Direction, Port, Position, Velocity},
 		triggers,
@@ -15,15 +17,22 @@ use crate::{
 
 use peppi_derive::Arrow;
 
+/// Deserializes a field that's present (even as `null`) as `Some`, so that `Option<Option<_>>`
+/// fields skipped when `None` round-trip. Use with `#[serde(default)]`.
+pub(crate) fn deserialize_some<'de, D, T>(d: D) -> Result<Option<T>, D::Error>
+where D: Deserializer<'de>, T: Deserialize<'de> {
+	T::deserialize(d).map(Some)
+}
+
 /// Controller button state.
-#[derive(Clone, Copy, Debug, PartialEq, Serialize, Arrow)]
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize, Arrow)]
 pub struct Buttons {
 	pub logical: buttons::Logical,
 	pub physical: buttons::Physical,
 }
 
 /// Controller trigger state.
-#[derive(Clone, Copy, Debug, PartialEq, Serialize, Arrow)]
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize, Arrow)]
 pub struct Triggers {
 	pub logical: triggers::Logical,
 	pub physical: triggers::Physical,
@@ -51,7 +60,7 @@ pseudo_enum!(HurtboxState: u8 {
 });
 
 /// Start-of-frame data.
-#[derive(Clone, Copy, Debug, PartialEq, Serialize, Arrow)]
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize, Arrow)]
 pub struct Start {
 	pub random_seed: u32,
 	/// Scene frame counter. Starts at 0 when game starts. Continues to count frames
@@ -61,7 +70,7 @@ pub struct Start {
 }
 
 /// End-of-frame data.
-#[derive(Clone, Copy, Debug, PartialEq, Serialize, Arrow)]
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize, Arrow)]
 pub struct End {
 	#[serde(skip_serializing_if = "Option::is_none")]
 	#[slippi(version = "3.7")] pub latest_finalized_frame: Option<i32>,
@@ -70,7 +79,7 @@ pub struct End {
 /// The game tracks two different "velocities" per character, autogenous (self-induced)
 /// and knockback. These are added to obtain an effective velocity, which may be further
 /// modified by other factors like obstacles.
-#[derive(Clone, Copy, Debug, PartialEq, Serialize, Arrow)]
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize, Arrow)]
 pub struct Velocities {
 	/// self-induced velocity
 	pub autogenous: Velocity,
@@ -82,7 +91,7 @@ pub struct Velocities {
 	pub autogenous_x: AutogenousXVelocity,
 }
 
-#[derive(Clone, Copy, Debug, PartialEq, Serialize, Arrow)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Deserialize, Serialize, Arrow)]
 pub struct AutogenousXVelocity {
 	pub air: f32,
 	pub ground: f32,
@@ -91,7 +100,7 @@ pub struct AutogenousXVelocity {
 /// Pre-frame update data, required to reconstruct a replay.
 ///
 /// Collected right before controller inputs are processed.
-#[derive(Clone, Copy, Debug, PartialEq, Serialize, Arrow)]
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize, Arrow)]
 pub struct Pre {
 	pub position: Position,
 	pub direction: Direction,
@@ -103,26 +112,202 @@ pub struct Pre {
 	pub state: action_state::State,
 	#[serde(skip_serializing_if = "Option::is_none")]
 	#[slippi(version = "1.2")] pub raw_analog_x: Option<u8>,
+	/// damage percent before this frame's update, i.e. the previous frame's `Post::damage`
+	/// (so it doesn't include damage taken on this frame). If unset, serialization fills it
+	/// in from the `Post::damage` of the frame with the previous index (its replayed copy,
+	/// after a rollback), or this frame's for the first frame.
 	#[serde(skip_serializing_if = "Option::is_none")]
 	#[slippi(version = "1.4")] pub damage: Option<f32>,
 }
 
+/// Which side of the screen "forward" points away from, as in fighting-game notation.
+/// On 1P side forward is right; on 2P side it's left.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+pub enum Side { P1, P2 }
+
+/// Smallest non-zero stick coordinate: Melee zeroes anything within 22/80 of neutral.
+pub(crate) const STICK_DEADZONE: f32 = 0.2875;
+
+/// Which of the eight directions a stick is held in, or neither.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+pub enum StickRegion { DeadZone, N, NE, E, SE, S, SW, W, NW }
+
+impl StickRegion {
+	/// Region for (processed) stick coordinates.
+	pub fn from_position(p: Position) -> StickRegion {
+		use StickRegion::*;
+		let axis = |v: f32| match v {
+			v if v >= STICK_DEADZONE => 1,
+			v if v <= -STICK_DEADZONE => -1,
+			_ => 0,
+		};
+		match (axis(p.x), axis(p.y)) {
+			(0, 0) => DeadZone,
+			(0, 1) => N,
+			(1, 1) => NE,
+			(1, 0) => E,
+			(1, _) => SE,
+			(0, _) => S,
+			(_, -1) => SW,
+			(_, 0) => W,
+			_ => NW,
+		}
+	}
+
+	/// Horizontal & vertical components of this direction (each -1, 0 or 1).
+	pub fn axes(self) -> (i8, i8) {
+		use StickRegion::*;
+		match self {
+			DeadZone => (0, 0),
+			N => (0, 1),
+			NE => (1, 1),
+			E => (1, 0),
+			SE => (1, -1),
+			S => (0, -1),
+			SW => (-1, -1),
+			W => (-1, 0),
+			NW => (-1, 1),
+		}
+	}
+}
+
+/// How far a stick is pushed along one axis, by the thresholds Melee checks. Processed
+/// stick coordinates are multiples of 1/80.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+pub enum StickZone {
+	/// within 22/80 of center, so read as neutral
+	DeadZone,
+	/// past the dead zone but short of `Smash`, e.g. for walking or tilts
+	Tilt,
+	/// at least 64/80 horizontally or 53/80 vertically: far enough to dash, smash or
+	/// tap-jump (the game also requires getting there within a few frames, which a
+	/// single frame can't show)
+	Smash,
+}
+
+impl StickZone {
+	fn from_axis(v: f32, smash: f32) -> StickZone {
+		match (v.abs() * 80.0).round() {
+			units if units >= smash => StickZone::Smash,
+			units if units > 22.0 => StickZone::Tilt,
+			_ => StickZone::DeadZone,
+		}
+	}
+
+	/// Zone of a horizontal (processed) stick coordinate.
+	pub fn from_x(x: f32) -> StickZone {
+		StickZone::from_axis(x, 64.0)
+	}
+
+	/// Zone of a vertical (processed) stick coordinate.
+	pub fn from_y(y: f32) -> StickZone {
+		StickZone::from_axis(y, 53.0)
+	}
+}
+
+/// Just the inputs needed to draw a controller (e.g. for an input overlay).
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+pub struct ControllerState {
+	pub joystick: Position,
+	pub cstick: Position,
+	/// physical L trigger
+	pub l: f32,
+	/// physical R trigger
+	pub r: f32,
+	pub buttons: buttons::Physical,
+}
+
+impl Pre {
+	/// The (processed) sticks, physical triggers & physical buttons.
+	pub fn controller_state(&self) -> ControllerState {
+		ControllerState {
+			joystick: self.joystick,
+			cstick: self.cstick,
+			l: self.triggers.physical.l,
+			r: self.triggers.physical.r,
+			buttons: self.buttons.physical,
+		}
+	}
+
+	/// Direction the joystick is held in. See `stick_zones` for how far.
+	pub fn stick_region(&self) -> StickRegion {
+		StickRegion::from_position(self.joystick)
+	}
+
+	/// How far the joystick is pushed horizontally & vertically, e.g. for telling tilts
+	/// from smashes. See `StickZone`.
+	pub fn stick_zones(&self) -> (StickZone, StickZone) {
+		(StickZone::from_x(self.joystick.x), StickZone::from_y(self.joystick.y))
+	}
+
+	/// Distance of the (processed) joystick from center, from 0 to 1.
+	pub fn joystick_magnitude(&self) -> f32 {
+		self.joystick.x.hypot(self.joystick.y)
+	}
+
+	/// Direction of the (processed) joystick in degrees, counterclockwise from
+	/// right & in `[0, 360)`, or `None` in the dead zone (see `StickRegion`).
+	pub fn joystick_angle_deg(&self) -> Option<f32> {
+		if self.stick_region() == StickRegion::DeadZone {
+			return None;
+		}
+		let angle = self.joystick.y.atan2(self.joystick.x).to_degrees();
+		Some(match angle < 0.0 {
+			true => angle + 360.0,
+			_ => angle,
+		})
+	}
+
+	/// Numpad notation for the joystick, followed by any pressed buttons
+	/// (e.g. `5`, `2[A]`, `6[BZ]`). Down-forward is `3`, up-back is `7`, etc.
+	pub fn notation(&self, side: Side) -> String {
+		let (x, y) = self.stick_region().axes();
+		let x = match side {
+			Side::P1 => x,
+			Side::P2 => -x,
+		};
+		let digit = 5 + x + 3 * y;
+
+		let pressed: String = [
+			(buttons::Logical::A, 'A'),
+			(buttons::Logical::B, 'B'),
+			(buttons::Logical::X, 'X'),
+			(buttons::Logical::Y, 'Y'),
+			(buttons::Logical::Z, 'Z'),
+			(buttons::Logical::L, 'L'),
+			(buttons::Logical::R, 'R'),
+		].iter()
+			.filter(|(b, _)| (self.buttons.logical & *b).0 != 0)
+			.map(|(_, c)| c)
+			.collect();
+
+		match pressed.is_empty() {
+			true => digit.to_string(),
+			_ => format!("{}[{}]", digit, pressed),
+		}
+	}
+}
+
 /// Post-frame update data, for computing stats etc.
 ///
 /// Collected at the end of collision detection, the last consideration of the game engine.
-#[derive(Clone, Copy, Debug, PartialEq, Serialize, Arrow)]
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize, Arrow)]
 pub struct Post {
-	/// in-game character (can only change for Zelda/Sheik)
+	/// in-game character (can only change for Zelda/Sheik, see `Game::transformations`)
 	pub character: character::Internal,
 	/// action state (very useful for stats)
 	pub state: action_state::State,
 	pub position: Position,
 	pub direction: Direction,
-	/// damage percent
+	/// damage percent after this frame's update (see also `Pre::damage`)
 	pub damage: f32,
 	/// shield size
 	pub shield: f32,
 	pub last_attack_landed: Option<attack::Attack>,
+	/// the game's count of consecutive hits landed by this character (including each hit of a
+	/// multi-hit move). It goes back to 1 on the first hit of a new string, and may be reset
+	/// (to 0 or 1) before that once the victim escapes, so it doesn't line up with peppi's own
+	/// combo detection (see `stats::combo::Opts`). Shared between all opponents.
 	pub combo_count: u8,
 	pub last_hit_by: Option<Port>,
 	/// stocks remaining
@@ -144,6 +329,7 @@ pub struct Post {
 	#[slippi(version = "2.0")] pub jumps: Option<u8>,
 	/// true = successful L-Cancel
 	#[serde(skip_serializing_if = "Option::is_none")]
+	#[serde(default, deserialize_with = "deserialize_some")]
 	#[slippi(version = "2.0")] pub l_cancel: Option<Option<bool>>,
 	#[serde(skip_serializing_if = "Option::is_none")]
 	#[slippi(version = "2.1")] pub hurtbox_state: Option<HurtboxState>,
@@ -157,15 +343,69 @@ pub struct Post {
 	#[slippi(version = "3.11")] pub animation_index: Option<u32>,
 }
 
+impl Post {
+	/// In one of the `DEAD_*` action states (KO'd, and not yet back on the respawn platform).
+	pub fn is_dead(&self) -> bool {
+		matches!(self.state, action_state::State::Common(s)
+			if s.0 <= action_state::Common::DEAD_UP_FALL_HIT_CAMERA_ICE.0)
+	}
+
+	/// On the respawn platform ("halo"), either descending or waiting to drop off.
+	pub fn is_respawn(&self) -> bool {
+		use action_state::Common;
+		matches!(self.state, action_state::State::Common(Common::REBIRTH | Common::REBIRTH_WAIT))
+	}
+
+	/// In hitstun (or tumbling) after being hit, in one of the `DAMAGE_*` action states.
+	pub fn is_damaged(&self) -> bool {
+		use action_state::Common;
+		matches!(self.state, action_state::State::Common(s) if s == Common::DAMAGE_FALL
+			|| (Common::DAMAGE_HI_1.0 ..= Common::DAMAGE_FLY_ROLL.0).contains(&s.0)
+			|| s == Common::DAMAGE_SCREW
+			|| s == Common::DAMAGE_SCREW_AIR)
+	}
+
+	/// Held or being thrown, including by command grabs (e.g. Bowser's side-B)
+	/// and similar binds (e.g. Jigglypuff's Sing, or being buried).
+	pub fn is_grabbed(&self) -> bool {
+		use action_state::Common;
+		matches!(self.state, action_state::State::Common(s)
+			if (Common::CAPTURE_PULLED_HI.0 ..= Common::CAPTURE_FOOT.0).contains(&s.0)
+			|| (Common::THROWN_F.0 ..= Common::THROWN_LW_WOMEN.0).contains(&s.0)
+			|| ((Common::SHOULDERED_WAIT.0 ..= Common::THROWN_MEWTWO_AIR.0).contains(&s.0)
+				&& s != Common::BARREL_WAIT))
+	}
+
+	/// Lying on the ground after missing a tech, or getting up from there.
+	pub fn is_downed(&self) -> bool {
+		use action_state::Common;
+		matches!(self.state, action_state::State::Common(s)
+			if (Common::DOWN_BOUND_U.0 ..= Common::DOWN_SPOT_D.0).contains(&s.0))
+	}
+
+	/// The special move this character is using, reading the action state as `character`'s
+	/// (normally `self.character`). See `SpecialMove::from_state`.
+	pub fn special_move(&self, character: character::Internal) -> Option<special_move::SpecialMove> {
+		special_move::SpecialMove::from_state(self.state.into(), character)
+	}
+
+	/// Teching on the ground, a wall, or the ceiling.
+	pub fn is_teching(&self) -> bool {
+		use action_state::Common;
+		matches!(self.state, action_state::State::Common(s)
+			if (Common::PASSIVE.0 ..= Common::PASSIVE_CEIL.0).contains(&s.0))
+	}
+}
+
 /// Frame data for a single character. Includes both pre-frame and post-frame data.
-#[derive(Clone, Copy, Debug, PartialEq, Serialize, Arrow)]
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize, Arrow)]
 pub struct Data {
 	pub pre: Pre,
 	pub post: Post,
 }
 
 /// Frame data for a single port/player. Can include two characters’ frame data (ICs).
-#[derive(Clone, Debug, PartialEq, Serialize, Arrow)]
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize, Arrow)]
 pub struct PortData {
 	/// Frame data for the controlled character.
 	pub leader: Data,
@@ -194,6 +434,46 @@ pub struct Frame<const N: usize> {
 	#[slippi(version = "3.0")] pub items: Option<Vec<item::Item>>,
 }
 
+/// Like `Frame`, but with the number of ports only known at runtime.
+/// Used to build `peppi::model::game::Frames` (see `Frames::from_port_count`).
+#[derive(Clone, Debug, Default, PartialEq, Deserialize)]
+pub struct DynFrame {
+	pub index: i32,
+	pub ports: Vec<PortData>,
+	pub start: Option<Start>,
+	pub end: Option<End>,
+	pub items: Option<Vec<item::Item>>,
+}
+
+impl<const N: usize> TryFrom<DynFrame> for Frame<N> {
+	type Error = std::io::Error;
+
+	fn try_from(f: DynFrame) -> std::io::Result<Self> {
+		let DynFrame { index, ports, start, end, items } = f;
+		Ok(Frame {
+			index: index,
+			ports: ports.try_into().map_err(|p: Vec<_>|
+				err!("frame {}: expected {} ports, but got {}", index, N, p.len()))?,
+			start: start,
+			end: end,
+			items: items,
+		})
+	}
+}
+
+impl<const N: usize> From<Frame<N>> for DynFrame {
+	fn from(f: Frame<N>) -> Self {
+		let Frame { index, ports, start, end, items } = f;
+		DynFrame {
+			index: index,
+			ports: ports.into(),
+			start: start,
+			end: end,
+			items: items,
+		}
+	}
+}
+
 // workaround for Serde not supporting const generics
 impl<const N: usize> Serialize for Frame<N> {
 	fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error> where S: serde::Serializer {
@@ -1,13 +1,18 @@
-use std::fmt::{self, Debug};
+use std::{
+	collections::{HashMap, HashSet},
+	fmt::{self, Debug, Display, Write},
+	time::Duration,
+};
 
+use chrono::{DateTime, SecondsFormat, SubsecRound, Utc};
 use serde::{Deserialize, Serialize};
 
 use crate::{
 	model::{
-		enums::{character, stage},
+		enums::{action_state, character, item, stage},
 		frame,
 		metadata,
-		primitives::Port,
+		primitives::{Port, PortMask, Position},
 		slippi,
 	},
 };
@@ -18,6 +23,27 @@ pub const MAX_PLAYERS: usize = 6;
 /// Frame indexes start at -123, and reach 0 at "Go!".
 pub const FIRST_FRAME_INDEX: i32 = -123;
 
+/// Per-port fields of the Game Start payload, as (port 0's offset, stride), in the
+/// order they were added (see `Game::remap_ports`).
+const PORT_FIELDS: [(usize, usize); 6] = [
+	(100, 36), // player block
+	(320, 8), // UCF (v1.0)
+	(352, 16), // name tag (v1.3)
+	(420, 31), // netplay name (v3.9)
+	(544, 10), // netplay code (v3.9)
+	(584, 29), // Slippi UID (v3.11)
+];
+
+/// Version of the layout of `Game`'s Serde serialization (& `Game::write_full_json`),
+/// written as `schema_version`, for reading stored games back with `migrate`:
+///
+/// 1. no `schema_version` (peppi before versioning)
+/// 2. adds `schema_version`
+pub const SCHEMA_VERSION: u32 = 2;
+
+/// Custom metadata key for `Game::video_offset`.
+const VIDEO_OFFSET_KEY: &str = "videoOffset";
+
 /// We can parse files with higher versions than this, but we won't expose all information.
 /// When converting a replay with a higher version number to another format like Arrow,
 /// the conversion will be lossy.
@@ -90,7 +116,7 @@ pub struct Player {
 	pub stocks: u8,
 	pub costume: u8,
 	pub team: Option<Team>,
-	/// handicap level; affects `offense_ratio` & `defense_ratio`
+	/// handicap level (`NORMAL_HANDICAP` = none); see `PlayerSettings::handicap`
 	pub handicap: u8,
 	/// miscellaneous flags (metal, stamina mode, etc)
 	pub bitfield: u8,
@@ -112,12 +138,46 @@ pub struct Player {
 	pub netplay: Option<Netplay>,
 }
 
+impl Player {
+	/// Color of this player's costume, or `None` if `costume` is out of range for the character.
+	pub fn costume_color(&self) -> Option<character::CostumeColor> {
+		self.character.costumes().get(self.costume as usize).copied()
+	}
+}
+
 #[derive(Clone, Copy, Debug, Default, PartialEq, Deserialize, Serialize)]
 pub struct Scene {
 	pub minor: u8,
 	pub major: u8,
 }
 
+impl Scene {
+	/// Whether this is a Slippi Online game (of any kind: ranked, unranked, direct, or teams).
+	pub fn is_online(&self) -> bool {
+		self.major == 8
+	}
+}
+
+/// Identifies a netplay set, and this game's place in it.
+#[derive(Clone, Debug, Default, PartialEq, Deserialize, Serialize)]
+pub struct Match {
+	/// e.g. `mode.ranked-2023-06-25T21:21:08.85-0`
+	pub id: String,
+	/// game number within the set, starting at 1
+	pub game: u32,
+	/// tiebreaker number within the set, or 0 if this isn't a tiebreaker
+	pub tiebreaker: u32,
+}
+
+/// Ranked context for a Slippi Online game. Replays don't record the players' ranks.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct RankedInfo {
+	pub is_ranked: bool,
+	pub match_id: String,
+	pub game_number: u32,
+	pub tiebreaker_number: u32,
+}
+
 /// Information used to initialize the game such as the game mode, settings, characters & stage.
 #[derive(Clone, Debug, Default, PartialEq, Deserialize, Serialize)]
 pub struct Start {
@@ -150,6 +210,190 @@ pub struct Start {
 	/// (added: v3.12)
 	#[serde(skip_serializing_if = "Option::is_none")]
 	pub language: Option<Language>,
+	/// (added: v3.14)
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub r#match: Option<Match>,
+}
+
+pseudo_enum!(TimerType: u8 {
+	0 => NONE,
+	2 => DECREASING,
+	3 => INCREASING,
+});
+
+impl Start {
+	/// Whether the in-game clock counts down from `timer`, up, or isn't shown at all.
+	pub fn timer_type(&self) -> TimerType {
+		TimerType(self.bitfield[0] & 0b11)
+	}
+}
+
+/// A time in frames, displayed like Melee's in-game timer (`MM:SS.cc`).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+pub struct GameClock(pub u32);
+
+impl GameClock {
+	pub fn minutes(self) -> u32 {
+		self.0 / 3600
+	}
+
+	pub fn seconds(self) -> u32 {
+		self.0 / 60 % 60
+	}
+
+	/// Hundredths of a second, rounded down from the frame within the second.
+	pub fn centiseconds(self) -> u32 {
+		self.0 % 60 * 100 / 60
+	}
+}
+
+impl Display for GameClock {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "{:02}:{:02}.{:02}", self.minutes(), self.seconds(), self.centiseconds())
+	}
+}
+
+/// Handicap level of a player without a handicap.
+pub const NORMAL_HANDICAP: u8 = 9;
+
+/// Per-player knockback & size modifiers. All 1.0 in a standard match.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+pub struct PlayerSettings {
+	pub port: Port,
+	/// handicap level, from 1 to 9 (normal). The game converts this into `offense_ratio`
+	/// & `defense_ratio` when the match starts (if handicaps are on), and the replay records
+	/// the resulting ratios, so changing one doesn't update the other
+	pub handicap: u8,
+	pub offense_ratio: f32,
+	pub defense_ratio: f32,
+	pub model_scale: f32,
+}
+
+impl PlayerSettings {
+	/// Unmodified settings for `port`.
+	pub fn standard(port: Port) -> Self {
+		PlayerSettings {
+			port: port,
+			handicap: NORMAL_HANDICAP,
+			offense_ratio: 1.0,
+			defense_ratio: 1.0,
+			model_scale: 1.0,
+		}
+	}
+}
+
+pseudo_enum!(ItemFrequency: i8 {
+	-1 => OFF,
+	0 => VERY_LOW,
+	1 => LOW,
+	2 => MEDIUM,
+	3 => HIGH,
+	4 => VERY_HIGH,
+});
+
+/// Damage & handicap modifiers and other rules for a match, as a view onto `Start`.
+/// Apply changes with `Start::set_match_settings` before serializing.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct MatchSettings {
+	pub damage_ratio: f32,
+	pub players: Vec<PlayerSettings>,
+	pub item_spawn_frequency: ItemFrequency,
+	/// points awarded for a self-destruct, from -2 to +1
+	pub self_destruct_score: i8,
+	/// items allowed to spawn (those switched on in the item switch menu)
+	pub items: HashSet<item::Type>,
+}
+
+/// Number of spawnable item types (`CAPSULE` through `POKE_BALL`). Each has a bit in
+/// `Start::item_spawn_bitfield`, numbered by item ID from the low bit of the last byte;
+/// the first byte's five high bits are unused.
+pub const NUM_SPAWNABLE_ITEMS: u16 = 35;
+
+fn item_bit(t: item::Type) -> (usize, u8) {
+	(4 - t.0 as usize / 8, 1 << (t.0 % 8))
+}
+
+fn spawnable_items(bitfield: &[u8; 5]) -> HashSet<item::Type> {
+	(0 .. NUM_SPAWNABLE_ITEMS).map(item::Type).filter(|&t| {
+		let (byte, mask) = item_bit(t);
+		bitfield[byte] & mask != 0
+	}).collect()
+}
+
+impl Default for MatchSettings {
+	fn default() -> Self {
+		MatchSettings {
+			damage_ratio: 1.0,
+			players: vec![],
+			item_spawn_frequency: ItemFrequency::OFF,
+			self_destruct_score: -1,
+			items: (0 .. NUM_SPAWNABLE_ITEMS).map(item::Type).collect(),
+		}
+	}
+}
+
+impl MatchSettings {
+	/// True if no damage or handicap modifier differs from its default of 1.0,
+	/// and no player's handicap level differs from normal.
+	pub fn is_standard(&self) -> bool {
+		self.damage_ratio == 1.0 &&
+			self.players.iter().all(|p| *p == PlayerSettings::standard(p.port))
+	}
+}
+
+impl Start {
+	pub fn match_settings(&self) -> MatchSettings {
+		MatchSettings {
+			damage_ratio: self.damage_ratio,
+			players: self.players.iter().map(|p| PlayerSettings {
+				port: p.port,
+				handicap: p.handicap,
+				offense_ratio: p.offense_ratio,
+				defense_ratio: p.defense_ratio,
+				model_scale: p.model_scale,
+			}).collect(),
+			item_spawn_frequency: ItemFrequency(self.item_spawn_frequency),
+			self_destruct_score: self.self_destruct_score,
+			items: spawnable_items(&self.item_spawn_bitfield),
+		}
+	}
+
+	/// Writes `settings` back to the corresponding `Start` fields.
+	/// Entries for ports without a player are ignored.
+	/// Fails (without changing anything) if a rule is out of range.
+	pub fn set_match_settings(&mut self, settings: &MatchSettings) -> std::io::Result<()> {
+		if !(ItemFrequency::OFF.0 ..= ItemFrequency::VERY_HIGH.0).contains(&settings.item_spawn_frequency.0) {
+			return Err(err!("invalid item spawn frequency: {}", settings.item_spawn_frequency.0));
+		}
+		if !(-2 ..= 1).contains(&settings.self_destruct_score) {
+			return Err(err!("invalid self-destruct score: {}", settings.self_destruct_score));
+		}
+		if let Some(t) = settings.items.iter().find(|t| t.0 >= NUM_SPAWNABLE_ITEMS) {
+			return Err(err!("not a spawnable item: {}", t.0));
+		}
+		// leave the raw bitfield (including its unused bits) alone unless the items changed
+		if settings.items != spawnable_items(&self.item_spawn_bitfield) {
+			for t in (0 .. NUM_SPAWNABLE_ITEMS).map(item::Type) {
+				let (byte, mask) = item_bit(t);
+				match settings.items.contains(&t) {
+					true => self.item_spawn_bitfield[byte] |= mask,
+					false => self.item_spawn_bitfield[byte] &= !mask,
+				}
+			}
+		}
+		self.damage_ratio = settings.damage_ratio;
+		self.item_spawn_frequency = settings.item_spawn_frequency.0;
+		self.self_destruct_score = settings.self_destruct_score;
+		for ps in &settings.players {
+			if let Some(p) = self.players.iter_mut().find(|p| p.port == ps.port) {
+				p.handicap = ps.handicap;
+				p.offense_ratio = ps.offense_ratio;
+				p.defense_ratio = ps.defense_ratio;
+				p.model_scale = ps.model_scale;
+			}
+		}
+		Ok(())
+	}
 }
 
 pseudo_enum!(EndMethod: u8 {
@@ -165,8 +409,9 @@ pseudo_enum!(EndMethod: u8 {
 pub struct End {
 	/// how the game ended
 	pub method: EndMethod,
-	/// player who LRAS'd, if any (added: v2.0)
+	/// player who LRAS'd, if any (added: v2.0). Serialized as "nobody" if unset.
 	#[serde(skip_serializing_if = "Option::is_none")]
+	#[serde(default, deserialize_with = "frame::deserialize_some")]
 	pub lras_initiator: Option<Option<Port>>,
 }
 
@@ -183,6 +428,22 @@ pub enum Frames {
 }
 
 impl Frames {
+	/// Builds the `Frames` variant for `port_count` ports.
+	/// Fails unless every frame has exactly `port_count` entries in `ports`.
+	pub fn from_port_count(port_count: usize, frames: Vec<frame::DynFrame>) -> std::io::Result<Frames> {
+		fn convert<const N: usize>(frames: Vec<frame::DynFrame>) -> std::io::Result<Vec<frame::Frame<N>>> {
+			frames.into_iter().map(frame::Frame::try_from).collect()
+		}
+
+		Ok(match port_count {
+			1 => Self::P1(convert(frames)?),
+			2 => Self::P2(convert(frames)?),
+			3 => Self::P3(convert(frames)?),
+			4 => Self::P4(convert(frames)?),
+			n => return Err(err!("unsupported number of ports: {}", n)),
+		})
+	}
+
 	pub fn len(&self) -> usize {
 		match self {
 			Self::P1(frames) => frames.len(),
@@ -195,6 +456,209 @@ impl Frames {
 	pub fn is_empty(&self) -> bool {
 		self.len() == 0
 	}
+
+	/// Index of the `n`th frame (see `frame::Frame::index`).
+	pub fn index(&self, n: usize) -> i32 {
+		match self {
+			Self::P1(frames) => frames[n].index,
+			Self::P2(frames) => frames[n].index,
+			Self::P3(frames) => frames[n].index,
+			Self::P4(frames) => frames[n].index,
+		}
+	}
+
+	/// Data for the `port_idx`th port on the `n`th frame.
+	/// Ports are indexed in the same order as `Start::players`.
+	pub fn port_data(&self, n: usize, port_idx: usize) -> &frame::PortData {
+		match self {
+			Self::P1(frames) => &frames[n].ports[port_idx],
+			Self::P2(frames) => &frames[n].ports[port_idx],
+			Self::P3(frames) => &frames[n].ports[port_idx],
+			Self::P4(frames) => &frames[n].ports[port_idx],
+		}
+	}
+
+	/// Whether each frame is the last occurrence of its index, i.e. wasn't later
+	/// rolled back. Always true unless rollbacks were collected
+	/// (see `peppi::serde::collect::Opts`).
+	pub fn finalized(&self) -> Vec<bool> {
+		let mut finalized = vec![false; self.len()];
+		let mut min_later = i32::MAX;
+		for n in (0 .. self.len()).rev() {
+			let index = self.index(n);
+			finalized[n] = index < min_later;
+			min_later = min_later.min(index);
+		}
+		finalized
+	}
+
+	/// Start-of-frame data for the `n`th frame (added: v2.2).
+	pub fn start(&self, n: usize) -> Option<&frame::Start> {
+		match self {
+			Self::P1(frames) => frames[n].start.as_ref(),
+			Self::P2(frames) => frames[n].start.as_ref(),
+			Self::P3(frames) => frames[n].start.as_ref(),
+			Self::P4(frames) => frames[n].start.as_ref(),
+		}
+	}
+
+	/// Items on the `n`th frame (added: v3.0).
+	pub fn items(&self, n: usize) -> Option<&Vec<crate::model::item::Item>> {
+		match self {
+			Self::P1(frames) => frames[n].items.as_ref(),
+			Self::P2(frames) => frames[n].items.as_ref(),
+			Self::P3(frames) => frames[n].items.as_ref(),
+			Self::P4(frames) => frames[n].items.as_ref(),
+		}
+	}
+
+	/// End-of-frame data for the `n`th frame (added: v3.0).
+	pub fn end(&self, n: usize) -> Option<&frame::End> {
+		match self {
+			Self::P1(frames) => frames[n].end.as_ref(),
+			Self::P2(frames) => frames[n].end.as_ref(),
+			Self::P3(frames) => frames[n].end.as_ref(),
+			Self::P4(frames) => frames[n].end.as_ref(),
+		}
+	}
+}
+
+/// Options for iterating over a single port's frames.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct PortFramesOpts {
+	/// Yield a single item per frame for Ice Climbers, with Nana's data available via
+	/// `PortFrame::follower`. Otherwise Popo and Nana are yielded as separate items.
+	pub merge_followers: bool,
+	/// Skip frames that were later rolled back (see `Frames::finalized`).
+	pub finalized_only: bool,
+}
+
+/// Thresholds for `Game::is_meaningful`.
+#[derive(Clone, Copy, Debug)]
+pub struct MeaningfulOpts {
+	/// minimum number of frames after "Go!" (default: 1800, i.e. 30 seconds)
+	pub min_frames: i32,
+	/// require every player to have either taken damage or given some input after "Go!"
+	/// (default: true)
+	pub require_activity: bool,
+}
+
+impl Default for MeaningfulOpts {
+	fn default() -> Self {
+		MeaningfulOpts {
+			min_frames: 1800,
+			require_activity: true,
+		}
+	}
+}
+
+/// A single character's frame data, as yielded by `PortFrames`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PortFrame<'a> {
+	/// Frame index (see `frame::Frame::index`).
+	pub index: i32,
+	/// Frame data for the character this item represents.
+	pub data: &'a frame::Data,
+	/// Whether `data` belongs to the follower (Nana).
+	pub is_follower: bool,
+	/// Follower data for this frame, if any (only when merging followers).
+	pub follower: Option<&'a frame::Data>,
+}
+
+/// One port's leader data on one frame, as returned by `Game::rows`.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+pub struct FrameRow {
+	/// Frame index (see `frame::Frame::index`).
+	pub index: i32,
+	pub port: Port,
+	pub data: frame::Data,
+}
+
+/// Iterator over a single port's frames. See `Game::port_frames`.
+pub struct PortFrames<'a> {
+	frames: &'a Frames,
+	port_idx: usize,
+	opts: PortFramesOpts,
+	next: usize,
+	pending_follower: Option<PortFrame<'a>>,
+	finalized: Option<Vec<bool>>,
+}
+
+impl<'a> Iterator for PortFrames<'a> {
+	type Item = PortFrame<'a>;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		if let Some(follower) = self.pending_follower.take() {
+			return Some(follower);
+		}
+
+		if let Some(finalized) = &self.finalized {
+			while self.next < finalized.len() && !finalized[self.next] {
+				self.next += 1;
+			}
+		}
+
+		if self.next >= self.frames.len() {
+			return None;
+		}
+
+		let index = self.frames.index(self.next);
+		let port_data = self.frames.port_data(self.next, self.port_idx);
+		self.next += 1;
+
+		let follower = port_data.follower.as_deref();
+		match self.opts.merge_followers {
+			true => Some(PortFrame {
+				index: index,
+				data: &port_data.leader,
+				is_follower: false,
+				follower: follower,
+			}),
+			_ => {
+				self.pending_follower = follower.map(|f| PortFrame {
+					index: index,
+					data: f,
+					is_follower: true,
+					follower: None,
+				});
+				Some(PortFrame {
+					index: index,
+					data: &port_data.leader,
+					is_follower: false,
+					follower: None,
+				})
+			},
+		}
+	}
+}
+
+/// A potential inconsistency in a game, as found by `Game::validate`.
+///
+/// These don't prevent serialization, but the resulting replay may not play back correctly.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Warning {
+	/// A stage-specific item appears on some other stage (e.g. after editing `Start::stage`).
+	StageItem { index: i32, id: u32, r#type: item::Type },
+	/// The first frame replayed after a rollback has a different random seed than the last
+	/// time it was simulated, even though rollbacks restore the game state (RNG included).
+	/// Only detectable when rollbacks are collected (see `peppi::serde::collect::Opts`).
+	RollbackSeed { index: i32, expected: u32, actual: u32 },
+	/// A player's stock count on the first frame differs from their starting stocks
+	/// in the start block (e.g. in a hand-built replay).
+	StartStocks { port: Port, expected: u8, actual: u8 },
+}
+
+impl Display for Warning {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Self::StageItem { index, id, r#type } =>
+				write!(f, "frame {}: item {} ({:?}) is specific to another stage", index, id, r#type),
+			Self::RollbackSeed { index, expected, actual } =>
+				write!(f, "frame {}: replayed with random seed {:#x} instead of {:#x}", index, actual, expected),
+			Self::StartStocks { port, expected, actual } =>
+				write!(f, "{}: starts with {} stocks, but {} on the first frame", port, expected, actual),
+		}
+	}
 }
 
 /// Binary blob of Gecko codes in use.
@@ -206,20 +670,116 @@ pub struct GeckoCodes {
 	pub actual_size: u16,
 }
 
+/// Callbacks for walking a game's frames with `Game::accept`, e.g. to compute custom
+/// stats. Each frame's callbacks are made in Slippi's event order: `on_frame_start`,
+/// then `on_pre` for each port (leader before follower), then `on_item` for each item,
+/// then `on_post` for each port, then `on_frame_end`. All methods do nothing by
+/// default, and an error from any of them stops the walk.
+pub trait FrameVisitor {
+	/// Start of the frame with index `index`; `start` is absent before v2.2.
+	fn on_frame_start(&mut self, _index: i32, _start: Option<&frame::Start>) -> std::io::Result<()> { Ok(()) }
+	/// Pre-frame update for one character.
+	fn on_pre(&mut self, _index: i32, _port: Port, _is_follower: bool, _pre: &frame::Pre) -> std::io::Result<()> { Ok(()) }
+	/// Post-frame update for one character.
+	fn on_post(&mut self, _index: i32, _port: Port, _is_follower: bool, _post: &frame::Post) -> std::io::Result<()> { Ok(()) }
+	/// One item's update (added: v3.0).
+	fn on_item(&mut self, _index: i32, _item: &crate::model::item::Item) -> std::io::Result<()> { Ok(()) }
+	/// End of the frame; `end` is absent before v3.0.
+	fn on_frame_end(&mut self, _index: i32, _end: Option<&frame::End>) -> std::io::Result<()> { Ok(()) }
+}
+
 /// Replay data for a single game of Melee.
 ///
 /// See https://github.com/project-slippi/slippi-wiki/blob/master/SPEC.md.
-#[derive(PartialEq, Serialize)]
+///
+/// Serializes (with Serde) as an object with `schema_version` (see `SCHEMA_VERSION`),
+/// `start`, `end`, `frames`, `metadata` (raw) & `truncated` (if set).
+#[derive(PartialEq)]
 pub struct Game {
 	pub start: Start,
 	pub end: End,
 	pub frames: Frames,
+	pub metadata: metadata::Metadata,
+	pub metadata_raw: serde_json::Map<String, serde_json::Value>,
+	#[doc(hidden)]
+	pub gecko_codes: Option<GeckoCodes>,
+	/// The replay ended prematurely (e.g. the recording crashed). `end` is a placeholder
+	/// unless the Game End event made it, and any incomplete final frame was dropped.
+	pub truncated: bool,
+}
+
+impl Serialize for Game {
+	fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error> where S: serde::Serializer {
+		use serde::ser::SerializeStruct;
+		let mut state = serializer.serialize_struct("Game", 6)?;
+		state.serialize_field("schema_version", &SCHEMA_VERSION)?;
+		state.serialize_field("start", &self.start)?;
+		state.serialize_field("end", &self.end)?;
+		state.serialize_field("frames", &self.frames)?;
+		state.serialize_field("metadata", &self.metadata_raw)?;
+		if self.truncated {
+			state.serialize_field("truncated", &self.truncated)?;
+		}
+		state.end()
+	}
+}
+
+/// Reorders each frame's port data so the `n`th port's comes from the `order[n]`th, &
+/// renames the ports it refers to (see `Game::remap_ports`).
+fn remap_frame_ports<const N: usize>(frames: &mut [frame::Frame<N>], order: &[usize], remap: impl Fn(Port) -> Port) {
+	for f in frames {
+		let ports = f.ports.clone();
+		for (port, idx) in f.ports.iter_mut().zip(order) {
+			*port = ports[*idx].clone();
+			for data in std::iter::once(&mut port.leader).chain(port.follower.as_deref_mut()) {
+				data.post.last_hit_by = data.post.last_hit_by.map(&remap);
+			}
+		}
+		for item in f.items.iter_mut().flatten() {
+			if let Some(Some(owner)) = &mut item.owner {
+				*owner = remap(*owner);
+			}
+		}
+	}
+}
+
+/// Everything about a game except its frame data (see `Game::header`).
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct GameHeader {
+	pub start: Start,
+	pub end: End,
 	#[serde(skip)]
 	pub metadata: metadata::Metadata,
 	#[serde(rename = "metadata")]
 	pub metadata_raw: serde_json::Map<String, serde_json::Value>,
-	#[serde(skip)] #[doc(hidden)]
-	pub gecko_codes: Option<GeckoCodes>,
+	/// number of frames, not counting ones that were rolled back
+	pub duration: usize,
+}
+
+/// Serializes only the finalized frames (see `Frames::finalized`).
+struct FinalizedFrames<'a>(&'a Frames);
+
+impl Serialize for FinalizedFrames<'_> {
+	fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error> where S: serde::Serializer {
+		let finalized = self.0.finalized();
+		let finalized = |n: &usize| finalized[*n];
+		match self.0 {
+			Frames::P1(f) => serializer.collect_seq((0 .. f.len()).filter(finalized).map(|n| &f[n])),
+			Frames::P2(f) => serializer.collect_seq((0 .. f.len()).filter(finalized).map(|n| &f[n])),
+			Frames::P3(f) => serializer.collect_seq((0 .. f.len()).filter(finalized).map(|n| &f[n])),
+			Frames::P4(f) => serializer.collect_seq((0 .. f.len()).filter(finalized).map(|n| &f[n])),
+		}
+	}
+}
+
+/// See `Game::write_full_json`.
+#[derive(Serialize)]
+struct FullJson<'a> {
+	schema_version: u32,
+	start: &'a Start,
+	end: &'a End,
+	metadata: &'a serde_json::Map<String, serde_json::Value>,
+	frames: FinalizedFrames<'a>,
 }
 
 impl Debug for Game {
@@ -232,3 +792,915 @@ impl Debug for Game {
 			.finish()
 	}
 }
+
+impl Game {
+	/// Copies everything but the frame data, for lightweight indexing of many games.
+	pub fn header(&self) -> GameHeader {
+		GameHeader {
+			start: self.start.clone(),
+			end: self.end.clone(),
+			metadata: self.metadata.clone(),
+			metadata_raw: self.metadata_raw.clone(),
+			duration: self.logical_frame_count(),
+		}
+	}
+
+	/// Writes the whole game to `w` as one JSON document, streaming it rather than
+	/// building it in memory. The document is an object with keys:
+	///
+	/// * `schema_version`: see `SCHEMA_VERSION`
+	/// * `start` & `end`: the Game Start & Game End blocks (as `Start` & `End`)
+	/// * `metadata`: the raw metadata, as in the replay
+	/// * `frames`: every finalized frame in order (see `Frames::finalized`), each an object
+	///   with `index`, `start` (v2.2+), `end` (v3.0+), `ports` & `items` (v3.0+). `ports`
+	///   follows `Start::players`, each port an object with `leader` (and `follower` for
+	///   Nana) holding `pre` & `post` frame data.
+	///
+	/// Fields missing from the replay's version are omitted, and pseudo-enums are written
+	/// as numbers unless `SERIALIZATION_CONFIG.enum_names` is set.
+	pub fn write_full_json<W: std::io::Write>(&self, w: W) -> std::io::Result<()> {
+		serde_json::to_writer(w, &FullJson {
+			schema_version: SCHEMA_VERSION,
+			start: &self.start,
+			end: &self.end,
+			metadata: &self.metadata_raw,
+			frames: FinalizedFrames(&self.frames),
+		})?;
+		Ok(())
+	}
+
+	/// Writes the frame data of the player in `port` (leader only, finalized frames) to
+	/// `dir` as NumPy arrays, one `.npy` file each. See `peppi::serde::npy::write`.
+	#[cfg(feature = "npy")]
+	pub fn write_npy<P: AsRef<std::path::Path>>(&self, dir: P, port: Port) -> std::io::Result<()> {
+		crate::serde::npy::write(dir.as_ref(), self, port)
+	}
+
+	/// Number of frames stored, i.e. `frames.len()`. Includes frames that were later
+	/// rolled back, if rollbacks were collected (see `peppi::serde::collect::Opts`), so
+	/// the same index can appear more than once.
+	pub fn raw_frame_count(&self) -> usize {
+		self.frames.len()
+	}
+
+	/// Number of distinct frames that actually happened, not counting rolled-back ones
+	/// (see `Frames::finalized`). This is the game's length as the players saw it.
+	pub fn logical_frame_count(&self) -> usize {
+		self.frames.finalized().into_iter().filter(|f| *f).count()
+	}
+
+	/// Whether any frames were rolled back, i.e. `raw_frame_count` & `logical_frame_count`
+	/// differ. Always false unless rollbacks were collected.
+	pub fn has_rollback(&self) -> bool {
+		self.raw_frame_count() != self.logical_frame_count()
+	}
+
+	/// Value of the top-level metadata key `key`, if present.
+	pub fn get_metadata(&self, key: &str) -> Option<&serde_json::Value> {
+		self.metadata_raw.get(key)
+	}
+
+	/// Sets the top-level metadata key `key` to `value`, keeping its position if it
+	/// already exists (new keys go last), and updates `metadata` to match. Fails without
+	/// changing anything if the result isn't valid metadata (e.g. a non-string `startAt`).
+	/// The new value is written out when the game is serialized.
+	pub fn set_metadata(&mut self, key: &str, value: serde_json::Value) -> std::io::Result<()> {
+		let mut raw = self.metadata_raw.clone();
+		raw.insert(key.to_string(), value);
+		self.metadata = metadata::parse(&raw)?;
+		self.metadata_raw = raw;
+		Ok(())
+	}
+
+	/// Sets the metadata's start time to `start_at` (to the second, as Slippi records it),
+	/// and its last frame to match the frame data, so the two agree again after the frames
+	/// were edited (e.g. cut or joined). Viewers use these for absolute timestamps.
+	pub fn retime(&mut self, start_at: DateTime<Utc>) {
+		let start_at = start_at.trunc_subsecs(0);
+		self.metadata_raw.insert("startAt".to_string(),
+			start_at.to_rfc3339_opts(SecondsFormat::Secs, true).into());
+		self.metadata.date = Some(start_at);
+
+		if !self.frames.is_empty() {
+			let last = self.frames.index(self.frames.len() - 1);
+			self.metadata_raw.insert("lastFrame".to_string(), last.into());
+			self.metadata.duration = Some((last - FIRST_FRAME_INDEX + 1) as usize);
+		}
+	}
+
+	/// Position in an external video (in frames) of this game's first frame, as set by
+	/// `set_video_offset`. Stored under the custom metadata key `videoOffset`.
+	pub fn video_offset(&self) -> Option<i32> {
+		self.get_metadata(VIDEO_OFFSET_KEY)
+			.and_then(|v| v.as_i64())
+			.and_then(|v| i32::try_from(v).ok())
+	}
+
+	/// Records that this game's first frame (index `FIRST_FRAME_INDEX`) appears at video
+	/// frame `frames`, for syncing with a recording. Negative if the video starts mid-game.
+	pub fn set_video_offset(&mut self, frames: i32) {
+		self.metadata_raw.insert(VIDEO_OFFSET_KEY.to_string(), frames.into());
+	}
+
+	/// Video frame on which frame index `frame` appears, assuming the video runs at the
+	/// game's 60fps, or `None` if no video offset is set.
+	pub fn video_frame(&self, frame: i32) -> Option<i32> {
+		self.video_offset().map(|offset| offset + frame - FIRST_FRAME_INDEX)
+	}
+
+	/// Ranked context for online games, or `None` for offline games and
+	/// replays that predate match IDs (v3.14).
+	pub fn ranked_info(&self) -> Option<RankedInfo> {
+		if !self.start.scene.is_some_and(|s| s.is_online()) {
+			return None;
+		}
+		self.start.r#match.as_ref().map(|m| RankedInfo {
+			is_ranked: m.id.starts_with("mode.ranked"),
+			match_id: m.id.clone(),
+			game_number: m.game,
+			tiebreaker_number: m.tiebreaker,
+		})
+	}
+
+	pub fn is_ranked(&self) -> bool {
+		self.ranked_info().is_some_and(|r| r.is_ranked)
+	}
+
+	/// Stable hash of the game's content: the start block, finalized frames & end block.
+	/// Ignores metadata (start time, console name, etc.), so re-uploads of the same
+	/// replay hash the same. Fails if `start` can't be serialized.
+	pub fn content_hash(&self) -> std::io::Result<u64> {
+		crate::serde::ser::content_hash(self)
+	}
+
+	/// Exact number of bytes `serde::ser::serialize` will write for this game, computed
+	/// from its payload sizes & frame/item counts without serializing the frames (e.g. for
+	/// pre-allocating buffers or checking upload quotas). Fails if the metadata can't be
+	/// encoded as UBJSON.
+	pub fn unparse_size(&self) -> std::io::Result<usize> {
+		Ok(crate::serde::ser::serialized_size(self)?)
+	}
+
+	pub fn stage(&self) -> stage::Stage {
+		self.start.stage
+	}
+
+	/// Port of the player with the given netplay connect code (v3.9+), if any.
+	/// The tag is matched case-insensitively, and `#` matches the full-width `＃`
+	/// that Melee stores codes with, so e.g. "abcd#123" finds "ABCD＃123".
+	pub fn port_of_code(&self, code: &str) -> Option<Port> {
+		fn normalize(code: &str) -> String {
+			code.replace('＃', "#").to_ascii_uppercase()
+		}
+		let code = normalize(code);
+		if code.is_empty() {
+			return None;
+		}
+		self.start.players.iter()
+			.find(|p| p.netplay.as_ref().is_some_and(|n| normalize(&n.code) == code))
+			.map(|p| p.port)
+	}
+
+	/// Starting character for each occupied port, in port order.
+	pub fn characters(&self) -> Vec<(Port, character::External)> {
+		self.start.players.iter().map(|p| (p.port, p.character)).collect()
+	}
+
+	/// Character chosen by the player in `port`. Unlike the in-game character on each frame
+	/// (`frame::Post::character`), this doesn't change when Zelda & Sheik transform.
+	pub fn external_character(&self, port: Port) -> Option<character::External> {
+		self.start.players.iter().find(|p| p.port == port).map(|p| p.character)
+	}
+
+	/// Frame indexes on which the player in `port` (leader only) transformed between
+	/// Zelda & Sheik, with the in-game character they became. Rolled-back frames are skipped.
+	pub fn transformations(&self, port: Port) -> Vec<(i32, character::Internal)> {
+		let opts = Some(PortFramesOpts { merge_followers: true, finalized_only: true });
+		let mut transformations = Vec::new();
+		let mut prev = None;
+		for f in self.port_frames(port, opts).into_iter().flatten() {
+			let character = f.data.post.character;
+			if prev.is_some_and(|prev| prev != character) {
+				transformations.push((f.index, character));
+			}
+			prev = Some(character);
+		}
+		transformations
+	}
+
+	/// Ports on each of the two teams (ordered by team color), or `None` unless this
+	/// is a teams game with exactly two teams.
+	pub fn teams(&self) -> Option<[Vec<Port>; 2]> {
+		if !self.start.is_teams {
+			return None;
+		}
+		let mut colors: Vec<TeamColor> = self.start.players.iter()
+			.map(|p| p.team.map(|t| t.color))
+			.collect::<Option<_>>()?;
+		colors.sort_by_key(|c| c.0);
+		colors.dedup();
+		match colors[..] {
+			[a, b] => Some([a, b].map(|color| self.start.players.iter()
+				.filter(|p| p.team.map(|t| t.color) == Some(color))
+				.map(|p| p.port)
+				.collect())),
+			_ => None,
+		}
+	}
+
+	/// Ports of the winning player, plus their teammates in a teams game. Empty if there's
+	/// no winner (e.g. an unresolved game, or a no-contest without an LRAS).
+	///
+	/// After an LRAS, everyone but the quitter (and their team) wins. Otherwise the winners
+	/// have the most stocks left on the last frame; after a timeout, ties are broken by
+	/// lowest percent.
+	pub fn winners(&self) -> Vec<Port> {
+		let team = |port: Port| self.start.players.iter()
+			.find(|p| p.port == port)
+			.and_then(|p| p.team.map(|t| t.color));
+		let teammates = |a: Port, b: Port| a == b || (self.start.is_teams && team(a).is_some() && team(a) == team(b));
+
+		if let Some(Some(quitter)) = self.end.lras_initiator {
+			return self.start.players.iter()
+				.map(|p| p.port)
+				.filter(|p| !teammates(*p, quitter))
+				.collect();
+		}
+		if self.frames.is_empty() || !matches!(self.end.method, EndMethod::TIME | EndMethod::GAME | EndMethod::RESOLVED) {
+			return vec![];
+		}
+
+		let last = self.frames.len() - 1;
+		let timeout = self.end.method == EndMethod::TIME;
+		let standing = |idx: usize| {
+			let post = &self.frames.port_data(last, idx).leader.post;
+			(post.stocks, if timeout { -post.damage } else { 0.0 })
+		};
+		let best = (0 .. self.start.players.len())
+			.map(standing)
+			.max_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+		let leaders: Vec<Port> = (0 .. self.start.players.len())
+			.filter(|idx| Some(standing(*idx)) == best)
+			.map(|idx| self.start.players[idx].port)
+			.collect();
+		self.start.players.iter()
+			.map(|p| p.port)
+			.filter(|p| leaders.iter().any(|l| teammates(*p, *l)))
+			.collect()
+	}
+
+	/// A short human-readable summary: stage, date, length & final in-game clock, each
+	/// player (character, costume color, name tag & connect code, and stocks & percent at
+	/// the end), and the result. Meant for printing, so the exact format may change.
+	pub fn report(&self) -> String {
+		fn name<T: TryInto<String>>(value: T, raw: impl Display) -> String {
+			value.try_into().unwrap_or_else(|_| format!("unknown ({})", raw))
+		}
+
+		let mut r = String::new();
+		let frames = self.logical_frame_count();
+		writeln!(r, "{} (Slippi v{})", name(self.stage(), self.stage().0), self.start.slippi.version).unwrap();
+		if let Some(date) = self.metadata.date {
+			write!(r, "{}, ", date.format("%Y-%m-%d %H:%M:%S UTC")).unwrap();
+		}
+		write!(r, "{} ({} frames)", GameClock(frames as u32), frames).unwrap();
+		if let Some(clock) = self.frames.len().checked_sub(1).and_then(|n| self.clock_at(self.frames.index(n))) {
+			write!(r, ", clock: {}", clock).unwrap();
+		}
+		writeln!(r).unwrap();
+
+		let winners = self.winners();
+		for (idx, p) in self.start.players.iter().enumerate() {
+			write!(r, "{}: {}", p.port, name(p.character, p.character.0)).unwrap();
+			if let Some(color) = p.costume_color() {
+				write!(r, " ({:?})", color).unwrap();
+			}
+			if let Some(tag) = p.name_tag.as_deref().filter(|t| !t.is_empty()) {
+				write!(r, " [{}]", tag).unwrap();
+			}
+			if let Some(netplay) = p.netplay.as_ref().filter(|n| !n.code.is_empty()) {
+				write!(r, " {} ({})", netplay.name, netplay.code.replace('＃', "#")).unwrap();
+			}
+			if !self.frames.is_empty() {
+				let post = &self.frames.port_data(self.frames.len() - 1, idx).leader.post;
+				write!(r, ": {} stocks, {:.0}%", post.stocks, post.damage).unwrap();
+			}
+			if winners.contains(&p.port) {
+				write!(r, " (winner)").unwrap();
+			}
+			writeln!(r).unwrap();
+		}
+
+		write!(r, "Result: {}", name(self.end.method, self.end.method.0)).unwrap();
+		if let Some(Some(quitter)) = self.end.lras_initiator {
+			write!(r, " (LRAS by {})", quitter).unwrap();
+		}
+		r
+	}
+
+	/// Time left on the in-game clock at frame index `frame`, or `None` unless this
+	/// is a timed match. The clock starts at "Go!" (frame 0) and stops at zero.
+	pub fn time_remaining_at(&self, frame: i32) -> Option<Duration> {
+		match self.start.timer_type() {
+			TimerType::DECREASING => {
+				let elapsed = frame.max(0) as u64;
+				let remaining = (self.start.timer as u64 * 60).saturating_sub(elapsed);
+				Some(Duration::from_secs(remaining) / 60)
+			},
+			_ => None,
+		}
+	}
+
+	/// What the in-game clock shows at frame index `frame`: the time left for timed
+	/// matches, the time elapsed for ones whose clock counts up, or `None` if the clock
+	/// is hidden. The clock starts at "Go!" (frame 0), and stops at zero when counting down.
+	pub fn clock_at(&self, frame: i32) -> Option<GameClock> {
+		let elapsed = frame.max(0) as u32;
+		match self.start.timer_type() {
+			TimerType::DECREASING => Some(GameClock((self.start.timer * 60).saturating_sub(elapsed))),
+			TimerType::INCREASING => Some(GameClock(elapsed)),
+			_ => None,
+		}
+	}
+
+	/// Cuts the game off after the last frame on which the in-game clock shows `remaining`
+	/// (see `time_remaining_at`), as if the recording had stopped there. The end of the game
+	/// becomes `UNRESOLVED`, and the metadata's last frame & per-character frame counts are
+	/// updated to match. Fails (without changing anything) unless this is a timed match that
+	/// was still going when the clock reached `remaining`.
+	pub fn truncate_at_time(&mut self, remaining: Duration) -> std::io::Result<()> {
+		if self.start.timer_type() != TimerType::DECREASING {
+			return Err(err!("can't truncate untimed match"));
+		}
+		// round to the nearest frame, so `time_remaining_at` values round-trip
+		let remaining_frames = (remaining.as_nanos() * 60 + 500_000_000) / 1_000_000_000;
+		let last = i64::try_from(remaining_frames).ok()
+			.map(|r| self.start.timer as i64 * 60 - r)
+			.filter(|last| *last >= 0)
+			.ok_or_else(|| err!("timer starts at {}s, less than {:?}", self.start.timer, remaining))?;
+		let len = (0 .. self.frames.len()).rev()
+			.find(|n| self.frames.index(*n) as i64 == last)
+			.ok_or_else(|| err!("game ended before reaching {:?} remaining", remaining))? + 1;
+
+		let mut metadata_raw = self.metadata_raw.clone();
+		metadata_raw.insert("lastFrame".to_string(), last.into());
+		if let Some(serde_json::Value::Object(players)) = metadata_raw.get_mut("players") {
+			let finalized = self.frames.finalized();
+			for (idx, player) in self.start.players.iter().enumerate() {
+				let characters = players.get_mut(&(player.port as u8).to_string())
+					.and_then(|p| p.get_mut("characters"))
+					.and_then(|c| c.as_object_mut());
+				if let Some(characters) = characters {
+					*characters = self.character_counts(idx, len, &finalized);
+				}
+			}
+		}
+		self.metadata = metadata::parse(&metadata_raw)?;
+		self.metadata_raw = metadata_raw;
+
+		match &mut self.frames {
+			Frames::P1(f) => f.truncate(len),
+			Frames::P2(f) => f.truncate(len),
+			Frames::P3(f) => f.truncate(len),
+			Frames::P4(f) => f.truncate(len),
+		}
+		self.end = End {
+			method: EndMethod::UNRESOLVED,
+			lras_initiator: self.end.lras_initiator.map(|_| None),
+		};
+		Ok(())
+	}
+
+	/// Index of the first frame on which any player acts, i.e. leaves the sequence every
+	/// character goes through before being given control: the entry animation, falling &
+	/// landing onto the stage, then standing in place. Players get control during the
+	/// Ready countdown, before "Go!" (frame 0), so this is usually negative. Going by
+	/// leaders' finalized frames; if no one ever acts, this is the index after the last
+	/// frame (or `FIRST_FRAME_INDEX` if there are none).
+	pub fn go_frame(&self) -> i32 {
+		use action_state::{Common, State};
+		let opts = Some(PortFramesOpts { merge_followers: true, finalized_only: true });
+		let end = match self.frames.len() {
+			0 => FIRST_FRAME_INDEX,
+			n => self.frames.index(n - 1) + 1,
+		};
+		self.start.players.iter()
+			.filter_map(|p| self.port_frames(p.port, opts)?
+				.find(|f| !matches!(f.data.post.state, State::Common(
+					Common::ENTRY | Common::ENTRY_START | Common::ENTRY_END
+					| Common::FALL | Common::LANDING | Common::WAIT)))
+				.map(|f| f.index))
+			.min()
+			.unwrap_or(end)
+	}
+
+	/// Removes the frames before `go_frame`, so that playback starts when the players
+	/// start acting. Frame indexes are left as-is (so Slippi's "Go!" is still frame 0),
+	/// & so is the metadata. Since Slippi replays must start at `FIRST_FRAME_INDEX`, the
+	/// trimmed game is for in-memory use: `serde::ser::serialize` refuses to write it.
+	pub fn trim_to_action(&mut self) {
+		let go = self.go_frame();
+		match &mut self.frames {
+			Frames::P1(f) => f.retain(|f| f.index >= go),
+			Frames::P2(f) => f.retain(|f| f.index >= go),
+			Frames::P3(f) => f.retain(|f| f.index >= go),
+			Frames::P4(f) => f.retain(|f| f.index >= go),
+		}
+	}
+
+	/// Moves the player in each port `p` to port `mapping[p as usize]`, or leaves them
+	/// where they are if that's `None`. Updates everything that refers to ports: the start
+	/// block (including `raw_bytes`, so unmapped fields move too), the order of players &
+	/// of each frame's port data (which are by port), `Post::last_hit_by`, item owners,
+	/// the LRAS initiator & the metadata's `players`. Fails without changing anything if
+	/// two players would end up in the same port.
+	pub fn remap_ports(&mut self, mapping: [Option<Port>; NUM_PORTS]) -> std::io::Result<()> {
+		let remap = |p: Port| mapping[p as usize].unwrap_or(p);
+		let ports: Vec<_> = self.start.players.iter().map(|p| remap(p.port)).collect();
+		for (n, p) in ports.iter().enumerate() {
+			if ports[.. n].contains(p) {
+				return Err(err!("multiple players mapped to port {:?}", p));
+			}
+		}
+
+		let mut metadata_raw = self.metadata_raw.clone();
+		if let Some(serde_json::Value::Object(players)) = metadata_raw.get_mut("players") {
+			let mut remapped: Vec<_> = std::mem::take(players).into_iter().map(|(k, v)| {
+				let port = k.parse::<u8>().ok().and_then(|p| Port::try_from(p).ok());
+				(port.map_or(k, |p| (remap(p) as u8).to_string()), v)
+			}).collect();
+			remapped.sort_by_key(|(k, _)| k.parse::<usize>().unwrap_or(0));
+			*players = remapped.into_iter().collect();
+		}
+		self.metadata = metadata::parse(&metadata_raw)?;
+		self.metadata_raw = metadata_raw;
+
+		let raw = self.start.raw_bytes.clone();
+		for (player, to) in self.start.players.iter().zip(&ports) {
+			for (offset, stride) in PORT_FIELDS.into_iter().filter(|(o, s)| raw.len() >= o + s * NUM_PORTS) {
+				let (from, to) = (offset + stride * player.port as usize, offset + stride * *to as usize);
+				self.start.raw_bytes[to .. to + stride].copy_from_slice(&raw[from .. from + stride]);
+			}
+		}
+
+		let mut order: Vec<_> = (0 .. ports.len()).collect();
+		order.sort_by_key(|idx| ports[*idx] as u8);
+		let players = std::mem::take(&mut self.start.players);
+		self.start.players = order.iter()
+			.map(|idx| Player { port: ports[*idx], ..players[*idx].clone() })
+			.collect();
+
+		match &mut self.frames {
+			Frames::P1(f) => remap_frame_ports(f, &order, remap),
+			Frames::P2(f) => remap_frame_ports(f, &order, remap),
+			Frames::P3(f) => remap_frame_ports(f, &order, remap),
+			Frames::P4(f) => remap_frame_ports(f, &order, remap),
+		}
+		if let Some(Some(p)) = &mut self.end.lras_initiator {
+			*p = remap(*p);
+		}
+		Ok(())
+	}
+
+	/// Metadata `characters` map for the `port_idx`th port: the number of finalized frames
+	/// among the first `len` that each (internal) character was on, counting Nana too.
+	fn character_counts(&self, port_idx: usize, len: usize, finalized: &[bool]) -> serde_json::Map<String, serde_json::Value> {
+		let mut counts = serde_json::Map::new();
+		for n in (0 .. len).filter(|n| finalized[*n]) {
+			let data = self.frames.port_data(n, port_idx);
+			for d in std::iter::once(&data.leader).chain(data.follower.as_deref()) {
+				let count = counts.entry(d.post.character.0.to_string()).or_insert(0.into());
+				*count = (count.as_u64().unwrap_or(0) + 1).into();
+			}
+		}
+		counts
+	}
+
+	/// Recomputes the metadata that's derived from frame data, as Slippi records it: the
+	/// last frame and each player's `characters` (see `metadata::Player::characters`).
+	/// Players missing from the metadata are added, with `names` taken from the start
+	/// block for netplay games (v3.9+); other keys are kept. Updates `metadata` to match.
+	pub fn rebuild_metadata(&mut self) -> std::io::Result<()> {
+		let mut metadata_raw = self.metadata_raw.clone();
+		if !self.frames.is_empty() {
+			metadata_raw.insert("lastFrame".to_string(), self.frames.index(self.frames.len() - 1).into());
+		}
+
+		let old_players = match metadata_raw.get("players") {
+			Some(serde_json::Value::Object(players)) => players.clone(),
+			_ => serde_json::Map::new(),
+		};
+		let finalized = self.frames.finalized();
+		let mut players = serde_json::Map::new();
+		for (idx, player) in self.start.players.iter().enumerate() {
+			let key = (player.port as u8).to_string();
+			let mut p = match old_players.get(&key) {
+				Some(serde_json::Value::Object(p)) => p.clone(),
+				_ => serde_json::Map::new(),
+			};
+			p.insert("characters".to_string(),
+				self.character_counts(idx, self.frames.len(), &finalized).into());
+			if let Some(netplay) = player.netplay.as_ref().filter(|n| !n.code.is_empty()) {
+				p.entry("names").or_insert_with(|| serde_json::json!({
+					"netplay": netplay.name,
+					"code": netplay.code,
+				}));
+			}
+			players.insert(key, p.into());
+		}
+		metadata_raw.insert("players".to_string(), players.into());
+
+		self.metadata = metadata::parse(&metadata_raw)?;
+		self.metadata_raw = metadata_raw;
+		Ok(())
+	}
+
+	/// Controller inputs of the player in `port` on each finalized frame, starting at
+	/// `FIRST_FRAME_INDEX`, or empty if there's no such player. See `frame::ControllerState`.
+	pub fn controller_frames(&self, port: Port) -> Vec<frame::ControllerState> {
+		let opts = Some(PortFramesOpts { merge_followers: true, finalized_only: true });
+		self.port_frames(port, opts).into_iter().flatten()
+			.map(|f| f.data.pre.controller_state())
+			.collect()
+	}
+
+	/// Calls `visitor`'s methods for every frame, in the order of `frames` (so including
+	/// any rolled-back frames that were collected). See `FrameVisitor`.
+	pub fn accept(&self, visitor: &mut impl FrameVisitor) -> std::io::Result<()> {
+		for n in 0 .. self.frames.len() {
+			let index = self.frames.index(n);
+			visitor.on_frame_start(index, self.frames.start(n))?;
+			for (port_idx, player) in self.start.players.iter().enumerate() {
+				let data = self.frames.port_data(n, port_idx);
+				visitor.on_pre(index, player.port, false, &data.leader.pre)?;
+				if let Some(f) = &data.follower {
+					visitor.on_pre(index, player.port, true, &f.pre)?;
+				}
+			}
+			for item in self.frames.items(n).into_iter().flatten() {
+				visitor.on_item(index, item)?;
+			}
+			for (port_idx, player) in self.start.players.iter().enumerate() {
+				let data = self.frames.port_data(n, port_idx);
+				visitor.on_post(index, player.port, false, &data.leader.post)?;
+				if let Some(f) = &data.follower {
+					visitor.on_post(index, player.port, true, &f.post)?;
+				}
+			}
+			visitor.on_frame_end(index, self.frames.end(n))?;
+		}
+		Ok(())
+	}
+
+	/// Ports with a player in them (see `Start::players`).
+	pub fn active_ports(&self) -> PortMask {
+		self.start.players.iter().fold(PortMask::default(), |mask, p| mask | p.port.into())
+	}
+
+	/// Leader data for every port on every finalized frame, flattened into one row per port
+	/// per frame (ordered by frame, then port) regardless of the number of ports. Handy for
+	/// loading into tabular tools. Followers (Nana) are left out.
+	pub fn rows(&self) -> Vec<FrameRow> {
+		let finalized = self.frames.finalized();
+		(0 .. self.frames.len()).filter(|n| finalized[*n]).flat_map(|n|
+			self.start.players.iter().enumerate().map(move |(idx, p)| FrameRow {
+				index: self.frames.index(n),
+				port: p.port,
+				data: self.frames.port_data(n, idx).leader,
+			})
+		).collect()
+	}
+
+	/// 2D histogram of the positions of the player in `port` (leader only) over all
+	/// finalized frames, as `grid[y][x]` with `bins_y` rows (bottom to top) of `bins_x`
+	/// cells (left to right). The grid spans the stage's blast zones (see
+	/// `Stage::blast_zones`), and positions outside them count towards the nearest edge
+	/// cell, so every frame is counted once. Empty for unknown stages or an empty port.
+	pub fn position_heatmap(&self, port: Port, bins_x: usize, bins_y: usize) -> Vec<Vec<u32>> {
+		let zones = match self.start.stage.blast_zones() {
+			Some(zones) => zones,
+			None => return vec![],
+		};
+		let opts = Some(PortFramesOpts { finalized_only: true, ..Default::default() });
+		let frames = match self.port_frames(port, opts) {
+			Some(frames) => frames,
+			None => return vec![],
+		};
+		let bin = |v: f32, min: f32, max: f32, bins: usize| {
+			let cell = ((v - min) / (max - min) * bins as f32).floor();
+			(cell.max(0.0) as usize).min(bins.saturating_sub(1))
+		};
+
+		let mut grid = vec![vec![0; bins_x]; bins_y];
+		if bins_x == 0 || bins_y == 0 {
+			return grid;
+		}
+		for f in frames.filter(|f| !f.is_follower) {
+			let p = f.data.post.position;
+			let x = bin(p.x, zones.left, zones.right, bins_x);
+			let y = bin(p.y, zones.bottom, zones.top, bins_y);
+			grid[y][x] += 1;
+		}
+		grid
+	}
+
+	/// Position of `port` within `start.players` (and thus within each frame's `ports`).
+	pub fn port_index(&self, port: Port) -> Option<usize> {
+		self.start.players.iter().position(|p| p.port == port)
+	}
+
+	/// Iterates over the frames of a single port, or returns `None` if no player is in `port`.
+	pub fn port_frames(&self, port: Port, opts: Option<PortFramesOpts>) -> Option<PortFrames<'_>> {
+		let opts = opts.unwrap_or_default();
+		self.port_index(port).map(|port_idx| PortFrames {
+			frames: &self.frames,
+			port_idx: port_idx,
+			opts: opts,
+			next: 0,
+			pending_follower: None,
+			finalized: opts.finalized_only.then(|| self.frames.finalized()),
+		})
+	}
+
+	/// Index of the first frame on which the player in `port` has either stick
+	/// off-center or any button pressed, or `None` if they never do (or the port
+	/// is empty). Useful for trimming idle frames before the players start acting. Only
+	/// the leader's finalized frames count, since Nana's inputs are the game's own.
+	pub fn first_input_frame(&self, port: Port) -> Option<i32> {
+		let opts = Some(PortFramesOpts { merge_followers: true, finalized_only: true });
+		self.port_frames(port, opts)?
+			.find(|f| {
+				let pre = &f.data.pre;
+				pre.joystick != Position::default()
+					|| pre.cstick != Position::default()
+					|| pre.buttons.physical.0 != 0
+			})
+			.map(|f| f.index)
+	}
+
+	/// False for throwaway games, such as handwarmers that were quit right away or where
+	/// someone never played: those shorter than `min_frames`, or (if `require_activity`)
+	/// where a player neither took damage nor touched their controller after "Go!"
+	/// (going by leaders' finalized frames, as for `first_input_frame`). Useful for
+	/// filtering datasets in bulk. See `MeaningfulOpts` for the defaults.
+	pub fn is_meaningful(&self, opts: Option<MeaningfulOpts>) -> bool {
+		let opts = opts.unwrap_or_default();
+		let frames_opts = Some(PortFramesOpts { merge_followers: true, finalized_only: true });
+		let frames = match self.frames.len() {
+			0 => 0,
+			n => self.frames.index(n - 1) + 1,
+		};
+		if frames < opts.min_frames {
+			return false;
+		}
+		!opts.require_activity || self.start.players.iter().all(|p|
+			self.port_frames(p.port, frames_opts).into_iter().flatten().any(|f| {
+				let pre = &f.data.pre;
+				f.data.post.damage > 0.0 || (f.index >= 0 && (pre.joystick != Position::default()
+					|| pre.cstick != Position::default()
+					|| pre.buttons.physical.0 != 0))
+			}))
+	}
+
+	/// The action-state timeline of the player in `port` (leader only), run-length
+	/// encoded as `(state, first frame index, last frame index)`. Rolled-back frames
+	/// are skipped, so each run covers a contiguous range of indexes. Empty if the
+	/// port is empty.
+	pub fn state_runs(&self, port: Port) -> Vec<(action_state::State, i32, i32)> {
+		let opts = Some(PortFramesOpts { merge_followers: true, finalized_only: true });
+		let mut runs: Vec<(action_state::State, i32, i32)> = Vec::new();
+		for f in self.port_frames(port, opts).into_iter().flatten() {
+			let state = f.data.post.state;
+			match runs.last_mut() {
+				Some((s, _, end)) if *s == state => *end = f.index,
+				_ => runs.push((state, f.index, f.index)),
+			}
+		}
+		runs
+	}
+
+	/// Consecutive `(previous, current)` frame data of the player in `port` (leader
+	/// only), for frame-to-frame deltas. Rolled-back frames are skipped, so a game with
+	/// `n` finalized frames yields `n - 1` pairs. Empty if the port is empty.
+	pub fn frame_pairs(&self, port: Port) -> impl Iterator<Item = (&frame::Data, &frame::Data)> + '_ {
+		let opts = Some(PortFramesOpts { merge_followers: true, finalized_only: true });
+		let frames = || self.port_frames(port, opts).into_iter().flatten().map(|f| f.data);
+		frames().zip(frames().skip(1))
+	}
+
+	/// `(own, opponent's)` frame data on each finalized frame for the player in `port`
+	/// in a 1v1 game (leaders only). Fails if there aren't exactly two players, or
+	/// `port` is empty.
+	pub fn versus_frames(&self, port: Port) -> std::io::Result<impl Iterator<Item = (&frame::Data, &frame::Data)> + '_> {
+		if self.start.players.len() != 2 {
+			return Err(err!("not a 1v1 game: {} players", self.start.players.len()));
+		}
+		let opponent = self.start.players.iter()
+			.map(|p| p.port)
+			.find(|p| *p != port)
+			.filter(|_| self.port_index(port).is_some())
+			.ok_or_else(|| err!("no player in port {}", port))?;
+		let opts = Some(PortFramesOpts { merge_followers: true, finalized_only: true });
+		let frames = |port| self.port_frames(port, opts).into_iter().flatten().map(|f| f.data);
+		Ok(frames(port).zip(frames(opponent)))
+	}
+
+	/// Positions in `frames` of `n` finalized frames spaced as evenly as possible,
+	/// including the first and last, for previews of long games. Returns every
+	/// finalized frame if there are no more than `n`. Use with `Frames::index` &
+	/// `Frames::port_data`.
+	pub fn sample_frames(&self, n: usize) -> Vec<usize> {
+		let finalized: Vec<usize> = self.frames.finalized().into_iter()
+			.enumerate()
+			.filter_map(|(i, f)| f.then_some(i))
+			.collect();
+		let len = finalized.len();
+		match n {
+			_ if len <= n => finalized,
+			1 => vec![finalized[0]],
+			_ => (0 .. n).map(|i| finalized[i * (len - 1) / (n - 1)]).collect(),
+		}
+	}
+
+	/// Blanks identifying information (name tags, netplay names, connect codes & Slippi UIDs),
+	/// in both the start block and the metadata. Gameplay data is left untouched.
+	pub fn anonymize(&mut self) {
+		for p in &mut self.start.players {
+			if let Some(name_tag) = &mut p.name_tag {
+				name_tag.clear();
+			}
+			if let Some(netplay) = &mut p.netplay {
+				netplay.name.clear();
+				netplay.code.clear();
+				if let Some(suid) = &mut netplay.suid {
+					suid.clear();
+				}
+			}
+		}
+
+		for p in self.metadata.players.iter_mut().flatten() {
+			if let Some(netplay) = &mut p.netplay {
+				netplay.name.clear();
+				netplay.code.clear();
+			}
+		}
+
+		if let Some(serde_json::Value::Object(players)) = self.metadata_raw.get_mut("players") {
+			for p in players.values_mut() {
+				if let Some(serde_json::Value::Object(names)) = p.get_mut("names") {
+					for name in names.values_mut() {
+						if name.is_string() {
+							*name = serde_json::Value::String(String::new());
+						}
+					}
+				}
+			}
+		}
+	}
+
+	/// Checks for inconsistencies between the game's fields. See `Warning`.
+	pub fn validate(&self) -> Vec<Warning> {
+		let mut warnings = Vec::new();
+		if !self.frames.is_empty() {
+			for (idx, player) in self.start.players.iter().enumerate() {
+				let actual = self.frames.port_data(0, idx).leader.post.stocks;
+				if actual != player.stocks {
+					warnings.push(Warning::StartStocks {
+						port: player.port,
+						expected: player.stocks,
+						actual: actual,
+					});
+				}
+			}
+		}
+
+		let mut seen = HashSet::new();
+		for n in 0 .. self.frames.len() {
+			for item in self.frames.items(n).into_iter().flatten() {
+				if let Some(stages) = item.r#type.stages() {
+					if !stages.contains(&self.start.stage) && seen.insert(item.id) {
+						warnings.push(Warning::StageItem {
+							index: self.frames.index(n),
+							id: item.id,
+							r#type: item.r#type,
+						});
+					}
+				}
+			}
+		}
+
+		let mut last_seeds = HashMap::new();
+		for n in 0 .. self.frames.len() {
+			let index = self.frames.index(n);
+			if let Some(start) = self.frames.start(n) {
+				let is_rollback = n > 0 && index <= self.frames.index(n - 1);
+				match last_seeds.insert(index, start.random_seed) {
+					Some(expected) if is_rollback && expected != start.random_seed =>
+						warnings.push(Warning::RollbackSeed {
+							index: index,
+							expected: expected,
+							actual: start.random_seed,
+						}),
+					_ => (),
+				}
+			}
+		}
+
+		warnings
+	}
+}
+
+fn finalized_eq<const N: usize>(a: &[frame::Frame<N>], a_fin: &[bool], b: &[frame::Frame<N>], b_fin: &[bool]) -> bool {
+	let a = a.iter().zip(a_fin).filter(|(_, f)| **f).map(|(f, _)| f);
+	let b = b.iter().zip(b_fin).filter(|(_, f)| **f).map(|(f, _)| f);
+	a.eq(b)
+}
+
+/// Whether `a` and `b` are the same game, going by the start block and finalized
+/// frames only. Metadata (e.g. `startAt`) may differ.
+pub fn is_same_game(a: &Game, b: &Game) -> bool {
+	if a.start != b.start || a.end != b.end {
+		return false;
+	}
+	let (a_fin, b_fin) = (a.frames.finalized(), b.frames.finalized());
+	match (&a.frames, &b.frames) {
+		(Frames::P1(a), Frames::P1(b)) => finalized_eq(a, &a_fin, b, &b_fin),
+		(Frames::P2(a), Frames::P2(b)) => finalized_eq(a, &a_fin, b, &b_fin),
+		(Frames::P3(a), Frames::P3(b)) => finalized_eq(a, &a_fin, b, &b_fin),
+		(Frames::P4(a), Frames::P4(b)) => finalized_eq(a, &a_fin, b, &b_fin),
+		_ => false,
+	}
+}
+
+/// Rebuilds a `Game` from its Serde serialization as JSON (or `Game::write_full_json`'s),
+/// written by this or an earlier version of peppi (see `SCHEMA_VERSION`). Pseudo-enums
+/// must have been written as numbers, i.e. without `SERIALIZATION_CONFIG.enum_names`.
+///
+/// Some things aren't serialized, so they can't be recovered: the raw Game Start bytes,
+/// Gecko codes, & whichever of the air & ground autogenous x velocities wasn't in use.
+/// So the result is good for analysis, but won't serialize back to a `.slp` faithfully.
+pub fn migrate(value: serde_json::Value) -> std::io::Result<Game> {
+	let mut value = match value {
+		serde_json::Value::Object(v) => v,
+		v => return Err(err!("expected object, but got: {:?}", v)),
+	};
+	// versions 1 & 2 differ only in `schema_version`, so there's nothing to convert yet
+	match value.remove("schema_version") {
+		None => (),
+		Some(v) if v.as_u64().is_some_and(|v| (2 ..= SCHEMA_VERSION as u64).contains(&v)) => (),
+		Some(v) => return Err(err!("unsupported schema version: {} (max: {})", v, SCHEMA_VERSION)),
+	}
+	let mut take = |key: &str| value.remove(key).ok_or_else(|| err!("missing key: {}", key));
+	let parse_err = |key: &'static str| move |e: serde_json::Error| err!("{}: {}", key, e);
+	let start = Start::deserialize(take("start")?).map_err(parse_err("start"))?;
+	let end = End::deserialize(take("end")?).map_err(parse_err("end"))?;
+	let metadata_raw = serde_json::Map::deserialize(take("metadata")?).map_err(parse_err("metadata"))?;
+	let mut frames = Vec::<frame::DynFrame>::deserialize(take("frames")?).map_err(parse_err("frames"))?;
+	let truncated = value.remove("truncated").is_some_and(|t| t == true);
+
+	for f in &mut frames {
+		for p in &mut f.ports {
+			for data in std::iter::once(&mut p.leader).chain(p.follower.as_deref_mut()) {
+				// character-specific states need the character to decode
+				let character = data.post.character;
+				data.pre.state = action_state::State::from(data.pre.state.into(), character);
+				data.post.state = action_state::State::from(data.post.state.into(), character);
+				if let Some(v) = &mut data.post.velocities {
+					match data.post.airborne {
+						Some(true) => v.autogenous_x.air = v.autogenous.x,
+						_ => v.autogenous_x.ground = v.autogenous.x,
+					}
+				}
+			}
+		}
+	}
+
+	Ok(Game {
+		frames: Frames::from_port_count(start.players.len(), frames)?,
+		start: start,
+		end: end,
+		metadata: metadata::parse(&metadata_raw)?,
+		metadata_raw: metadata_raw,
+		gecko_codes: None,
+		truncated: truncated,
+	})
+}
+
+/// Groups of indices into `hashes` (from `Game::content_hash`) that share a hash,
+/// i.e. likely duplicates. Each group is in ascending order, and groups are ordered
+/// by their first index. Use `is_same_game` to rule out collisions if needed.
+pub fn duplicates(hashes: &[u64]) -> Vec<Vec<usize>> {
+	let mut groups: Vec<Vec<usize>> = vec![];
+	let mut by_hash: HashMap<u64, usize> = HashMap::new();
+	for (n, hash) in hashes.iter().enumerate() {
+		match by_hash.get(hash) {
+			Some(g) => groups[*g].push(n),
+			None => {
+				by_hash.insert(*hash, groups.len());
+				groups.push(vec![n]);
+			},
+		}
+	}
+	groups.retain(|g| g.len() > 1);
+	groups
+}
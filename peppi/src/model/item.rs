@@ -1,11 +1,12 @@
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use peppi_derive::Arrow;
 use crate::model::{
 	enums::item::{State, Type},
+	frame,
 	primitives::{Direction, Port, Position, Velocity},
 };
 
-#[derive(Clone, Copy, Debug, PartialEq, Serialize, Arrow)]
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize, Arrow)]
 pub struct Item {
 	pub id: u32,
 	pub r#type: Type,
@@ -13,10 +14,49 @@ pub struct Item {
 	pub direction: Option<Direction>,
 	pub position: Position,
 	pub velocity: Velocity,
+	/// damage taken. Only meaningful for items that can be hit: containers (capsules, boxes,
+	/// barrels & party balls) and explosives (bob-ombs and Link's & Young Link's bombs),
+	/// which break or go off once it's high enough. Always 0 for other items
 	pub damage: u16,
+	/// frames until the item expires, i.e. disappears (or explodes, see `fuse_remaining`)
 	pub timer: f32,
 	#[serde(skip_serializing_if = "Option::is_none")]
 	#[slippi(version = "3.2")] pub misc: Option<[u8; 4]>,
 	#[serde(skip_serializing_if = "Option::is_none")]
+	#[serde(default, deserialize_with = "frame::deserialize_some")]
 	#[slippi(version = "3.5")] pub owner: Option<Option<Port>>,
 }
+
+impl Item {
+	/// Frames until this item explodes on its own, for bob-ombs and Link's & Young Link's
+	/// bombs. `None` for other items, for which `timer` just counts down to despawning.
+	pub fn fuse_remaining(&self) -> Option<f32> {
+		match self.r#type {
+			Type::BOB_OMB | Type::LINK_BOMB | Type::YOUNG_LINK_BOMB => Some(self.timer),
+			_ => None,
+		}
+	}
+
+	/// Whether this is a projectile whose power depends on how long it was charged:
+	/// Samus's Charge Shot & Mewtwo's Shadow Ball (and Kirby's copies of them).
+	///
+	/// Sheik's needles don't count: each thrown needle is a separate item, and how
+	/// many were charged is only recorded on Sheik.
+	pub fn is_charged_projectile(&self) -> bool {
+		matches!(self.r#type,
+			Type::SAMUS_CHARGE_SHOT | Type::KIRBY_COPY_SAMUS_CHARGE_SHOT |
+			Type::MEWTWO_SHADOW_BALL | Type::KIRBY_COPY_MEWTWO_SHADOW_BALL)
+	}
+
+	/// Charge power of a charged projectile (see `is_charged_projectile`), from the fourth
+	/// `misc` byte (v3.2+). `None` for other items or older replays.
+	pub fn charge(&self) -> Option<u8> {
+		self.misc.filter(|_| self.is_charged_projectile()).map(|m| m[3])
+	}
+
+	/// Whether a charged projectile has been fired, rather than still charging in its
+	/// user's hand, from the third `misc` byte (v3.2+). `None` for other items or older replays.
+	pub fn is_launched(&self) -> Option<bool> {
+		self.misc.filter(|_| self.is_charged_projectile()).map(|m| m[2] != 0)
+	}
+}
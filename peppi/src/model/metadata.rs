@@ -19,8 +19,10 @@ use crate::{
 	},
 };
 
-#[derive(Debug, Default, PartialEq, Serialize)]
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
 pub struct Metadata {
+	/// when the game started (`startAt`), assumed UTC if no time zone is given;
+	/// `None` if missing (a malformed `startAt` is a parse error)
 	#[serde(skip_serializing_if = "Option::is_none")]
 	pub date: Option<DateTime<Utc>>,
 	#[serde(skip_serializing_if = "Option::is_none")]
@@ -33,7 +35,7 @@ pub struct Metadata {
 	pub players: Option<Vec<Player>>,
 }
 
-#[derive(Debug, Default, PartialEq, Serialize)]
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
 pub struct Netplay {
 	pub code: String,
 	pub name: String,
@@ -53,7 +55,7 @@ pub fn serialize_characters<S>(characters: &Option<HashMap<character::Internal,
 	}
 }
 
-#[derive(Debug, Default, PartialEq, Serialize)]
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
 pub struct Player {
 	pub port: Port,
 	#[serde(skip_serializing_if = "Option::is_none")]
@@ -30,7 +30,40 @@ impl Default for Port {
 	}
 }
 
-#[derive(Clone, Copy, Debug, PartialEq, Serialize, IntoPrimitive, TryFromPrimitive)]
+pseudo_bitmask!(PortMask: u8 {
+	1 => P1,
+	2 => P2,
+	4 => P3,
+	8 => P4,
+});
+
+impl PortMask {
+	pub fn contains(self, port: Port) -> bool {
+		self.0 & (1 << port as u8) != 0
+	}
+
+	/// Number of ports in the mask.
+	pub fn len(self) -> usize {
+		self.0.count_ones() as usize
+	}
+
+	pub fn is_empty(self) -> bool {
+		self.0 == 0
+	}
+
+	/// Ports in the mask, in order.
+	pub fn iter(self) -> impl Iterator<Item = Port> {
+		(0 .. 4u8).map(|n| Port::try_from(n).unwrap()).filter(move |p| self.contains(*p))
+	}
+}
+
+impl From<Port> for PortMask {
+	fn from(port: Port) -> Self {
+		PortMask(1 << port as u8)
+	}
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize, IntoPrimitive, TryFromPrimitive)]
 #[repr(u8)]
 pub enum Direction { Left, Right }
 
@@ -64,13 +97,13 @@ impl From<Direction> for f32 {
 	}
 }
 
-#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Arrow)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Deserialize, Serialize, Arrow)]
 pub struct Position {
 	pub x: f32,
 	pub y: f32,
 }
 
-#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Arrow)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Deserialize, Serialize, Arrow)]
 pub struct Velocity {
 	pub x: f32,
 	pub y: f32,
@@ -24,6 +24,18 @@ macro_rules! pseudo_enum {
 			$( pub const $variant:$name = $name($value); )*
 		}
 
+		impl From<$type> for $name {
+			fn from(value: $type) -> Self {
+				$name(value)
+			}
+		}
+
+		impl From<$name> for $type {
+			fn from(e: $name) -> Self {
+				e.0
+			}
+		}
+
 		impl std::convert::TryFrom<$name> for String {
 			type Error = crate::model::pseudo_enum::ConversionError;
 			/// Returns the stringified name for this enum value, if any.
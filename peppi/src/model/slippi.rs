@@ -9,6 +9,59 @@ pub const fn version(major: u8, minor: u8) -> Version {
 	Version(major, minor, 0)
 }
 
+/// Versions that introduced notable replay data.
+impl Version {
+	/// UCF dashback & shield-drop info in Game Start.
+	pub const UCF: Version = version(1, 0);
+	/// In-game name tags in Game Start.
+	pub const NAME_TAGS: Version = version(1, 3);
+	/// PAL flag in Game Start.
+	pub const PAL: Version = version(1, 5);
+	/// Hurtbox state in Frame Post.
+	pub const HURTBOX_STATE: Version = version(2, 1);
+	/// Frame Start events (with per-frame random seeds), needed to make sense of rollbacks.
+	pub const ROLLBACK: Version = version(2, 2);
+	/// Item Update & Frame Bookend events.
+	pub const ITEMS: Version = version(3, 0);
+	/// Scene info in Game Start, and latest finalized frames in Frame Bookend.
+	pub const FINALIZED_FRAMES: Version = version(3, 7);
+	/// Hitlag remaining in Frame Post.
+	pub const HITLAG: Version = version(3, 8);
+	/// Netplay names & connect codes in Game Start.
+	pub const NETPLAY_NAMES: Version = version(3, 9);
+	/// Slippi UIDs in Game Start.
+	pub const SUID: Version = version(3, 11);
+	/// Language option in Game Start.
+	pub const LANGUAGE: Version = version(3, 12);
+	/// Match ID, game number & tiebreaker number in Game Start.
+	pub const MATCH_INFO: Version = version(3, 14);
+
+	/// Whether replays of this version have Item Update events (see `ITEMS`).
+	pub fn has_items(self) -> bool {
+		self >= Self::ITEMS
+	}
+
+	/// Whether replays of this version have Frame Start events (see `ROLLBACK`).
+	pub fn has_rollback(self) -> bool {
+		self >= Self::ROLLBACK
+	}
+
+	/// Whether replays of this version have netplay names & connect codes (see `NETPLAY_NAMES`).
+	pub fn has_netplay_names(self) -> bool {
+		self >= Self::NETPLAY_NAMES
+	}
+
+	/// Whether replays of this version have `Post::hurtbox_state` (see `HURTBOX_STATE`).
+	pub fn has_hurtbox_state(self) -> bool {
+		self >= Self::HURTBOX_STATE
+	}
+
+	/// Whether replays of this version have `Post::hitlag` (see `HITLAG`).
+	pub fn has_hitlag(self) -> bool {
+		self >= Self::HITLAG
+	}
+}
+
 pub struct ParseVersionError(pub String);
 
 impl From<std::num::ParseIntError> for ParseVersionError {
@@ -9,6 +9,7 @@ use crate::{
 		item,
 		metadata,
 		primitives::Port,
+		slippi,
 	},
 	serde::de::{self, FrameEvent, FrameId, Indexed, PortId},
 };
@@ -16,6 +17,9 @@ use crate::{
 #[derive(Clone, Copy, Debug, Default)]
 pub struct Opts {
 	pub rollbacks: bool,
+	/// Fail once the replay has more than this many frames (rolled-back ones included),
+	/// so that a malicious replay can't exhaust memory.
+	pub max_frames: Option<usize>,
 }
 
 #[derive(Debug, Default)]
@@ -39,6 +43,7 @@ pub struct Collector {
 	pub frames_followers: FrameEvents,
 	pub items: Vec<Vec<item::Item>>,
 	pub metadata: Option<Map<String, Value>>,
+	pub truncated: bool,
 }
 
 macro_rules! into_game {
@@ -112,6 +117,7 @@ macro_rules! into_game {
 			frames: Frames::$frames_type(frames),
 			metadata: metadata,
 			metadata_raw: metadata_raw,
+			truncated: $gp.truncated,
 		}
 	}}
 }
@@ -199,6 +205,9 @@ impl de::Handlers for Collector {
 			self.first_port = Some(evt.id.port);
 		}
 		if Some(evt.id.port) == self.first_port && !evt.id.is_follower {
+			if self.opts.max_frames.is_some_and(|max| self.frames_index.len() >= max) {
+				return Err(err!("too many frames (max: {})", self.opts.max_frames.unwrap()));
+			}
 			self.frames_index.push(evt.id.index);
 		}
 		match evt.id.is_follower {
@@ -236,6 +245,50 @@ impl de::Handlers for Collector {
 		Ok(())
 	}
 
+	fn truncated(&mut self) -> Result<()> {
+		self.truncated = true;
+		let v = self.start.as_ref().ok_or_else(|| err!("missing start event"))?.slippi.version;
+
+		// A frame is complete once we have every character's post-frame update
+		// (and the Frame Bookend, in versions that have one).
+		let mut complete = usize::MAX;
+		for events in [&self.frames_leaders, &self.frames_followers] {
+			for (pre, post) in events.pre.iter().zip(&events.post) {
+				if pre.len() > post.len() {
+					complete = complete.min(post.len());
+				}
+			}
+		}
+		if v >= slippi::Version::ITEMS {
+			let frame_count = self.frames_leaders.pre.iter().map(Vec::len).max().unwrap_or(0);
+			if self.frames_end.len() < frame_count {
+				complete = complete.min(self.frames_end.len());
+			}
+		}
+
+		if complete < usize::MAX {
+			for events in [&mut self.frames_leaders, &mut self.frames_followers] {
+				events.pre.iter_mut().for_each(|v| v.truncate(complete));
+				events.post.iter_mut().for_each(|v| v.truncate(complete));
+			}
+			if self.opts.rollbacks {
+				self.frames_index.truncate(complete);
+			}
+			self.frames_start.truncate(complete);
+			self.frames_end.truncate(complete);
+			self.items.truncate(complete);
+		}
+
+		if self.end.is_none() {
+			self.end = Some(game::End {
+				method: game::EndMethod::UNRESOLVED,
+				lras_initiator: (v >= slippi::version(2, 0)).then_some(None),
+			});
+		}
+
+		Ok(())
+	}
+
 	fn finalize(&mut self) -> Result<()> {
 		let frame_count = self.frames_leaders.pre.iter().map(Vec::len).max().unwrap_or(0);
 
@@ -1,7 +1,7 @@
 use std::{
 	cmp::min,
 	collections::HashMap,
-	io::{self, Read, Result},
+	io::{self, Read, Result, Seek},
 };
 
 use byteorder::ReadBytesExt;
@@ -37,24 +37,24 @@ const SHEIK_TRANSFORM_FRAME: u32 = 36;
 
 // We only track this for Sheik/Zelda transformations, which can't happen on
 // the first frame. So we can initialize with any arbitrary character value.
-const DEFAULT_CHAR_STATE: CharState = CharState {
+pub(crate) const DEFAULT_CHAR_STATE: CharState = CharState {
 	character: Internal(255),
 	state: State::Common(Common::WAIT),
 	age: 0
 };
 
 #[derive(Clone, Copy, Debug, PartialEq)]
-struct CharState {
-	character: Internal,
-	state: State,
-	age: u32,
+pub(crate) struct CharState {
+	pub(crate) character: Internal,
+	pub(crate) state: State,
+	pub(crate) age: u32,
 }
 
 pub(super) const PAYLOADS_EVENT_CODE: u8 = 0x35;
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, num_enum::TryFromPrimitive)]
 #[repr(u8)]
-pub(super) enum Event {
+pub enum Event {
 	GameStart = 0x36,
 	FramePre = 0x37,
 	FramePost = 0x38,
@@ -144,7 +144,7 @@ where F: FnOnce(&mut &[u8]) -> Result<T> {
 /// codes to payload sizes. This map uses raw event codes as keys (as opposed
 /// to `Event` enum values) for forwards compatibility, as it allows us to
 /// skip unknown event types.
-fn payload_sizes<R: Read>(r: &mut R) -> Result<(usize, HashMap<u8, u16>)> {
+pub(crate) fn payload_sizes<R: Read>(r: &mut R) -> Result<(usize, HashMap<u8, u16>)> {
 	let code = r.read_u8()?;
 	if code != PAYLOADS_EVENT_CODE {
 		return Err(err!("expected event payloads, but got: {}", code));
@@ -171,6 +171,99 @@ fn payload_sizes<R: Read>(r: &mut R) -> Result<(usize, HashMap<u8, u16>)> {
 	Ok((1 + size as usize, sizes)) // +1 byte for the event code
 }
 
+/// Layouts of fixed-size events are known up to (and including) this version.
+/// Newer replays may append fields we don't know about.
+const LAST_KNOWN_LAYOUT: slippi::Version = slippi::Version::LANGUAGE;
+
+/// Payload size for `event` in a replay of version `v`, or `None` if the size
+/// isn't determined by the version (or the event doesn't exist in that version).
+pub(super) fn expected_payload_size(event: Event, v: slippi::Version) -> Option<u16> {
+	use Event::*;
+	let ver = slippi::version;
+	match event {
+		FramePre => Some(
+			if v >= ver(1, 4) {
+				63
+			} else if v >= ver(1, 2) {
+				59
+			} else {
+				58
+			}
+		),
+		FramePost => Some(
+			if v >= ver(3, 11) {
+				80
+			} else if v >= ver(3, 8) {
+				76
+			} else if v >= ver(3, 5) {
+				72
+			} else if v >= ver(2, 1) {
+				52
+			} else if v >= ver(2, 0) {
+				51
+			} else if v >= ver(0, 2) {
+				37
+			} else {
+				33
+			}
+		),
+		GameEnd => Some(if v >= ver(2, 0) { 2 } else { 1 }),
+		FrameStart if v >= slippi::Version::ROLLBACK => Some(
+			if v >= ver(3, 10) {
+				12
+			} else {
+				8
+			}
+		),
+		Item if v >= slippi::Version::ITEMS => Some(
+			if v >= ver(3, 6) {
+				42
+			} else if v >= ver(3, 2) {
+				41
+			} else {
+				37
+			}
+		),
+		FrameEnd if v >= slippi::Version::ITEMS => Some(
+			if v >= slippi::Version::FINALIZED_FRAMES {
+				8
+			} else {
+				4
+			}
+		),
+		_ => None,
+	}
+}
+
+/// Payload sizes indexed by event code, for fast lookup in the event loop.
+pub(crate) type SizeTable = [Option<u16>; 256];
+
+pub(crate) fn size_table(payload_sizes: &HashMap<u8, u16>) -> SizeTable {
+	let mut table = [None; 256];
+	for (&code, &size) in payload_sizes {
+		table[code as usize] = Some(size);
+	}
+	table
+}
+
+/// Checks the declared payload sizes against the ones we expect for version `v`.
+/// Sizes larger than expected are allowed for versions newer than `LAST_KNOWN_LAYOUT`.
+fn check_payload_sizes(payload_sizes: &SizeTable, v: slippi::Version) -> Result<()> {
+	use Event::*;
+	for event in [FramePre, FramePost, GameEnd, FrameStart, Item, FrameEnd] {
+		let declared = payload_sizes[event as usize];
+		match (expected_payload_size(event, v), declared) {
+			(Some(expected), Some(declared)) if declared < expected
+				|| (declared > expected && v <= LAST_KNOWN_LAYOUT) =>
+				return Err(err!("wrong payload size for {:?} (v{}): expected {}, got {}", event, v, expected, declared)),
+			(None, Some(declared)) =>
+				return Err(err!("unexpected payload size for {:?} (v{}): {}", event, v, declared)),
+			_ => (),
+		}
+	}
+	Ok(())
+}
+
 fn player(port: Port, v0: &[u8; 36], is_teams: bool, v1_0: Option<[u8; 8]>, v1_3: Option<[u8; 16]>, v3_9_name: Option<[u8; 31]>, v3_9_code: Option<[u8; 10]>, v3_11: Option<[u8; 29]>) -> Result<Option<Player>> {
 	let mut r = &v0[..];
 	let mut unmapped = [0; 15];
@@ -202,11 +295,10 @@ fn player(port: Port, v0: &[u8; 36], is_teams: bool, v1_0: Option<[u8; 8]>, v1_3
 			_ => None,
 		}
 	};
-	r.read_exact(&mut unmapped[7..11])?;
+	r.read_exact(&mut unmapped[7..15])?;
 	let offense_ratio = r.read_f32::<BE>()?;
 	let defense_ratio = r.read_f32::<BE>()?;
 	let model_scale = r.read_f32::<BE>()?;
-	r.read_exact(&mut unmapped[11..15])?;
 	// total bytes: 0x24
 
 	// v1.0
@@ -344,6 +436,12 @@ fn game_start(mut r: &mut &[u8]) -> Result<game::Start> {
 	for p in &mut players_v0 {
 		r.read_exact(p)?;
 	}
+	// the game has slots for six players, but Slippi only records the first four
+	for (n, p) in players_v0.iter().enumerate().skip(NUM_PORTS) {
+		if matches!(PlayerType(p[1]), PlayerType::HUMAN | PlayerType::CPU | PlayerType::DEMO) {
+			return Err(err!("player in unsupported slot {} (max players: {})", n + 1, NUM_PORTS));
+		}
+	}
 	// @0x13d
 	let random_seed = r.read_u32::<BE>()?;
 
@@ -408,6 +506,17 @@ fn game_start(mut r: &mut &[u8]) -> Result<game::Start> {
 
 	let lang = if_more(r, |r| Ok(game::Language(r.read_u8()?)))?;
 
+	let r#match = if_more(r, |r| {
+		let mut id = [0; 51];
+		r.read_exact(&mut id)?;
+		let first_null = id.iter().position(|&x| x == 0).unwrap_or(51);
+		Ok(game::Match {
+			id: String::from_utf8_lossy(&id[0..first_null]).to_string(),
+			game: r.read_u32::<BE>()?,
+			tiebreaker: r.read_u32::<BE>()?,
+		})
+	})?;
+
 	Ok(game::Start {
 		slippi: slippi,
 		bitfield: bitfield,
@@ -430,6 +539,8 @@ fn game_start(mut r: &mut &[u8]) -> Result<game::Start> {
 		scene: scene,
 		// v3.12
 		language: lang,
+		// v3.14
+		r#match: r#match,
 	})
 }
 
@@ -518,38 +629,69 @@ fn predict_character(id: PortId, last_char_states: &[CharState; NUM_PORTS]) -> I
 	}
 }
 
+/// Size of the fields present in every version of Pre-Frame Update.
+const PRE_FIXED_SIZE: usize = 58;
+
+/// Splits off the first `N` bytes of `r`, so that fields within them can be
+/// read at fixed offsets without a bounds check for each one.
+fn fixed<'a, const N: usize>(r: &mut &'a [u8]) -> Result<&'a [u8; N]> {
+	if r.len() < N {
+		return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "event payload too short"));
+	}
+	let (head, tail) = r.split_at(N);
+	*r = tail;
+	Ok(head.try_into().unwrap())
+}
+
+fn u16_at<const N: usize>(b: &[u8; N], i: usize) -> u16 {
+	u16::from_be_bytes([b[i], b[i + 1]])
+}
+
+fn u32_at<const N: usize>(b: &[u8; N], i: usize) -> u32 {
+	u32::from_be_bytes([b[i], b[i + 1], b[i + 2], b[i + 3]])
+}
+
+fn i32_at<const N: usize>(b: &[u8; N], i: usize) -> i32 {
+	u32_at(b, i) as i32
+}
+
+fn f32_at<const N: usize>(b: &[u8; N], i: usize) -> f32 {
+	f32::from_bits(u32_at(b, i))
+}
+
 fn frame_pre(r: &mut &[u8], last_char_states: &[CharState; NUM_PORTS]) -> Result<FrameEvent<PortId, Pre>> {
-	let id = PortId::new(r.read_i32::<BE>()?, r.read_u8()?, r.read_u8()? != 0)?;
+	let b = fixed::<PRE_FIXED_SIZE>(r)?;
+	let id = PortId::new(i32_at(b, 0), b[4], b[5] != 0)?;
 	trace!("Pre-Frame Update: {:?}", id);
 
 	let character = predict_character(id, last_char_states);
 
-	let random_seed = r.read_u32::<BE>()?;
-	let state = State::from(r.read_u16::<BE>()?, character);
+	let random_seed = u32_at(b, 6);
+	let state = State::from(u16_at(b, 10), character);
 
 	let position = Position {
-		x: r.read_f32::<BE>()?,
-		y: r.read_f32::<BE>()?,
+		x: f32_at(b, 12),
+		y: f32_at(b, 16),
 	};
-	let direction = r.read_f32::<BE>()?.try_into()?;
+	let direction = f32_at(b, 20).try_into()?;
 	let joystick = Position {
-		x: r.read_f32::<BE>()?,
-		y: r.read_f32::<BE>()?,
+		x: f32_at(b, 24),
+		y: f32_at(b, 28),
 	};
 	let cstick = Position {
-		x: r.read_f32::<BE>()?,
-		y: r.read_f32::<BE>()?,
+		x: f32_at(b, 32),
+		y: f32_at(b, 36),
 	};
-	let trigger_logical = r.read_f32::<BE>()?;
+	let trigger_logical = f32_at(b, 40);
 	let buttons = frame::Buttons {
-		logical: buttons::Logical(r.read_u32::<BE>()?),
-		physical: buttons::Physical(r.read_u16::<BE>()?),
+		logical: buttons::Logical(u32_at(b, 44)),
+		physical: buttons::Physical(u16_at(b, 48)),
 	};
 	let triggers = frame::Triggers {
 		logical: trigger_logical,
 		physical: triggers::Physical {
-			l: r.read_f32::<BE>()?,
-			r: r.read_f32::<BE>()?,
+			l: f32_at(b, 50),
+			r: f32_at(b, 54),
 		},
 	};
 
@@ -610,29 +752,30 @@ fn update_last_char_state(id: PortId, character: Internal, state: State, last_ch
 	};
 }
 
+/// Size of the fields present in every version of Post-Frame Update.
+const POST_FIXED_SIZE: usize = 33;
+
 fn frame_post(r: &mut &[u8], last_char_states: &mut [CharState; NUM_PORTS]) -> Result<FrameEvent<PortId, Post>> {
-	let id = PortId::new(r.read_i32::<BE>()?, r.read_u8()?, r.read_u8()? != 0)?;
+	let b = fixed::<POST_FIXED_SIZE>(r)?;
+	let id = PortId::new(i32_at(b, 0), b[4], b[5] != 0)?;
 	trace!("Post-Frame Update: {:?}", id);
 
-	let character = Internal(r.read_u8()?);
-	let state = State::from(r.read_u16::<BE>()?, character);
+	let character = Internal(b[6]);
+	let state = State::from(u16_at(b, 7), character);
 	let position = Position {
-		x: r.read_f32::<BE>()?,
-		y: r.read_f32::<BE>()?,
+		x: f32_at(b, 9),
+		y: f32_at(b, 13),
 	};
-	let direction = r.read_f32::<BE>()?.try_into()?;
-	let damage = r.read_f32::<BE>()?;
-	let shield = r.read_f32::<BE>()?;
-	let last_attack_landed = {
-		let attack = r.read_u8()?;
-		match attack {
-			0 => None,
-			attack => Some(Attack(attack)),
-		}
+	let direction = f32_at(b, 17).try_into()?;
+	let damage = f32_at(b, 21);
+	let shield = f32_at(b, 25);
+	let last_attack_landed = match b[29] {
+		0 => None,
+		attack => Some(Attack(attack)),
 	};
-	let combo_count = r.read_u8()?;
-	let last_hit_by = Port::try_from(r.read_u8()?).ok();
-	let stocks = r.read_u8()?;
+	let combo_count = b[30];
+	let last_hit_by = Port::try_from(b[31]).ok();
+	let stocks = b[32];
 
 	// v0.2
 	let state_age = if_more(r, |r| r.read_f32::<BE>())?;
@@ -757,6 +900,11 @@ pub trait Handlers {
 	/// One event per frame per item, with a maximum of 15 updates per frame. Can be used for stats, training AIs, or visualization engines to handle items. Items include projectiles like lasers or needles.
 	fn item(&mut self, _: FrameEvent<FrameId, Item>) -> Result<()> { Ok(()) }
 
+	/// Called (before `finalize`) if the replay ends prematurely, e.g. because the
+	/// recording crashed. `game_end` & `metadata` may not have been called, and
+	/// the last frame's events may be incomplete.
+	fn truncated(&mut self) -> Result<()> { Ok(()) }
+
 	/// Called after all parse events have been handled.
 	fn finalize(&mut self) -> Result<()> { Ok(()) }
 }
@@ -797,23 +945,30 @@ fn handle_splitter_event(buf: &[u8], accumulator: &mut Option<Vec<u8>>) -> Resul
 /// supported `Event` types, calls the corresponding `Handler` callback with
 /// the parsed event.
 ///
-/// Returns the number of bytes read by this function.
+/// Returns the number of bytes read by this function, which is 0 if `r` has
+/// no more data (i.e. it ended between events).
 fn event<R: Read, H: Handlers>(
 		mut r: R,
-		payload_sizes: &HashMap<u8, u16>,
+		payload_sizes: &SizeTable,
+		buf: &mut Vec<u8>,
 		last_char_states: &mut [CharState; NUM_PORTS],
 		handlers: &mut H,
 		splitter_accumulator: &mut Option<Vec<u8>>,
+		strict: bool,
 	) -> Result<(usize, Option<Event>)> {
-	let mut code = r.read_u8()?;
+	let mut code = [0];
+	if r.read(&mut code)? == 0 {
+		return Ok((0, None));
+	}
+	let mut code = code[0];
 	debug!("Event: {:#x}", code);
 
-	let size = *payload_sizes.get(&code).ok_or_else(|| err!("unknown event: {}", code))? as usize;
-	let mut buf = vec![0; size];
-	r.read_exact(&mut *buf)?;
+	let size = payload_sizes[code as usize].ok_or_else(|| err!("unknown event: {}", code))? as usize;
+	buf.resize(size, 0);
+	r.read_exact(buf)?;
 
 	if code == 0x10 { // message splitter
-		if let Some(wrapped_event) = handle_splitter_event(&buf, splitter_accumulator)? {
+		if let Some(wrapped_event) = handle_splitter_event(buf, splitter_accumulator)? {
 			code = wrapped_event;
 			buf.clear();
 			buf.append(splitter_accumulator.as_mut().unwrap());
@@ -824,26 +979,82 @@ fn event<R: Read, H: Handlers>(
 	if let Some(event) = event {
 		use Event::*;
 		match event {
-			GameStart => handlers.game_start(game_start(&mut &*buf)?)?,
-			GameEnd => handlers.game_end(game_end(&mut &*buf)?)?,
-			FrameStart => handlers.frame_start(frame_start(&mut &*buf)?)?,
-			FramePre => handlers.frame_pre(frame_pre(&mut &*buf, last_char_states)?)?,
-			FramePost => handlers.frame_post(frame_post(&mut &*buf, last_char_states)?)?,
-			FrameEnd => handlers.frame_end(frame_end(&mut &*buf)?)?,
-			Item => handlers.item(item(&mut &*buf)?)?,
-			GeckoCodes => handlers.gecko_codes(&buf, payload_sizes[&(GeckoCodes as u8)])?,
+			GameStart => {
+				let start = game_start(&mut &buf[..])?;
+				if strict {
+					check_payload_sizes(payload_sizes, start.slippi.version)?;
+				}
+				handlers.game_start(start)?
+			},
+			GameEnd => handlers.game_end(game_end(&mut &buf[..])?)?,
+			FrameStart => handlers.frame_start(frame_start(&mut &buf[..])?)?,
+			FramePre => handlers.frame_pre(frame_pre(&mut &buf[..], last_char_states)?)?,
+			FramePost => handlers.frame_post(frame_post(&mut &buf[..], last_char_states)?)?,
+			FrameEnd => handlers.frame_end(frame_end(&mut &buf[..])?)?,
+			Item => handlers.item(item(&mut &buf[..])?)?,
+			GeckoCodes => handlers.gecko_codes(buf, payload_sizes[GeckoCodes as usize].unwrap())?,
 		};
 	}
 
 	Ok((1 + size as usize, event)) // +1 byte for the event code
 }
 
+/// Parses every event in `buf`, which must hold whole events only, carrying
+/// Zelda/Sheik transformation state over from (and back into) `last_char_states`.
+pub(crate) fn events<H: Handlers>(mut buf: &[u8], payload_sizes: &SizeTable, last_char_states: &mut [CharState; NUM_PORTS], handlers: &mut H) -> Result<()> {
+	let mut event_buf = Vec::new();
+	let mut splitter_accumulator = None;
+	while !buf.is_empty() {
+		event(&mut buf, payload_sizes, &mut event_buf, last_char_states, handlers, &mut splitter_accumulator, false)?;
+	}
+	Ok(())
+}
+
+/// Payload sizes by event, as for `Opts::payload_sizes`.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct PayloadSizes([Option<u16>; Event::GeckoCodes as usize - Event::GameStart as usize + 1]);
+
+impl PayloadSizes {
+	pub fn get(&self, event: Event) -> Option<u16> {
+		self.0[event as usize - Event::GameStart as usize]
+	}
+
+	pub fn insert(&mut self, event: Event, size: u16) {
+		self.0[event as usize - Event::GameStart as usize] = Some(size);
+	}
+
+	/// Events with a size, in order of event code.
+	pub fn iter(&self) -> impl Iterator<Item = (Event, u16)> + '_ {
+		self.0.iter().enumerate().filter_map(|(n, size)| {
+			let event = Event::try_from(Event::GameStart as u8 + n as u8).unwrap();
+			size.map(|size| (event, size))
+		})
+	}
+}
+
+impl<const N: usize> From<[(Event, u16); N]> for PayloadSizes {
+	fn from(sizes: [(Event, u16); N]) -> Self {
+		let mut payload_sizes = Self::default();
+		for (event, size) in sizes {
+			payload_sizes.insert(event, size);
+		}
+		payload_sizes
+	}
+}
+
 /// Options for parsing replays.
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, Default)]
 pub struct Opts {
 	/// Skip all frame data when parsing a replay for speed
 	/// (when you only need start/end/metadata).
 	pub skip_frames: bool,
+	/// Fail if the declared event payload sizes don't match the ones we expect
+	/// for the replay's version, which usually indicates a corrupted file.
+	/// Applies to the sizes after any `payload_sizes` overrides.
+	pub strict: bool,
+	/// Payload sizes to use instead of the ones declared by the replay, for
+	/// captures from modded or experimental Slippi builds.
+	pub payload_sizes: PayloadSizes,
 }
 
 /// Parses a Slippi replay from `r`, passing events to the callbacks in `handlers` as they occur.
@@ -855,18 +1066,27 @@ pub fn deserialize<R: Read, H: Handlers>(mut r: &mut R, handlers: &mut H, opts:
 		&[0x7b, 0x55, 0x03, 0x72, 0x61, 0x77, 0x5b, 0x24, 0x55, 0x23, 0x6c])?;
 
 	let raw_len = r.read_u32::<BE>()? as usize;
-	let (mut bytes_read, payload_sizes) = payload_sizes(&mut r)?;
+	let opts = opts.unwrap_or_default();
+	let (mut bytes_read, mut payload_sizes) = payload_sizes(&mut r)?;
+	for (event, size) in opts.payload_sizes.iter() {
+		info!("Overriding payload size for {:?}: {}", event, size);
+		payload_sizes.insert(event as u8, size);
+	}
+	let payload_sizes = size_table(&payload_sizes);
+	// reused across events to avoid allocating for each one
+	let mut buf = Vec::new();
 	let mut last_char_states = [DEFAULT_CHAR_STATE; NUM_PORTS];
 	let mut last_event: Option<Event> = None;
-	let skip_frames = opts.map(|o| o.skip_frames).unwrap_or(false);
 
 	let mut splitter_accumulator = None;
+	let mut started = false;
+	let mut truncated = false;
 
 	// `raw_len` will be 0 for an in-progress replay
 	while (raw_len == 0 || bytes_read < raw_len) && last_event != Some(Event::GameEnd) {
-		if skip_frames && last_event == Some(Event::GameStart) {
+		if opts.skip_frames && raw_len != 0 && last_event == Some(Event::GameStart) {
 			// Skip to GameEnd, which we assume is the last event in the stream!
-			let skip = raw_len - bytes_read - payload_sizes[&(Event::GameEnd as u8)] as usize - 1;
+			let skip = raw_len - bytes_read - payload_sizes[Event::GameEnd as usize].unwrap_or(0) as usize - 1;
 			// In theory we should seek() if `r` is Seekable, but it's not much
 			// faster and is very awkward to implement without specialization.
 			io::copy(&mut r.by_ref().take(skip as u64), &mut io::sink())?;
@@ -875,18 +1095,66 @@ pub fn deserialize<R: Read, H: Handlers>(mut r: &mut R, handlers: &mut H, opts:
 		let (bytes, event) = event(
 			r.by_ref(),
 			&payload_sizes,
+			&mut buf,
 			&mut last_char_states,
 			handlers,
 			&mut splitter_accumulator,
+			opts.strict,
 		)?;
+		if bytes == 0 {
+			// The stream ends cleanly between events (as when the game crashed), so keep
+			// whatever we got, as long as there's a game to speak of. Running out of data
+			// mid-event is still an error, since it usually means a corrupted file.
+			if !started {
+				return Err(io::ErrorKind::UnexpectedEof.into());
+			}
+			truncated = true;
+			break;
+		}
 		bytes_read += bytes;
 		last_event = event;
+		started |= event == Some(Event::GameStart);
+	}
+
+	if !truncated {
+		if raw_len != 0 && bytes_read != raw_len {
+			return Err(err!("failed to consume expected number of bytes: {}, {}", raw_len, bytes_read));
+		}
+
+		// likewise for metadata, which must be entirely missing
+		let mut first = [0];
+		match r.read(&mut first)? {
+			0 => truncated = true,
+			_ => handlers.metadata(metadata(&mut first.chain(r))?)?,
+		}
 	}
 
-	if raw_len != 0 && bytes_read != raw_len {
-		return Err(err!("failed to consume expected number of bytes: {}, {}", raw_len, bytes_read));
+	if truncated {
+		info!("Replay is truncated after {} bytes of raw data", bytes_read);
+		handlers.truncated()?;
 	}
 
+	handlers.finalize()?;
+	Ok(())
+}
+
+/// Reads only the `metadata` element of a replay, seeking past the raw event
+/// stream without parsing it. Fails for in-progress replays (whose `raw` length
+/// is 0, and which have no metadata yet).
+pub fn metadata_only<R: Read + Seek>(mut r: &mut R) -> Result<serde_json::Map<String, serde_json::Value>> {
+	expect_bytes(&mut r,
+		// top-level opening brace, `raw` key & type ("{U\x03raw[$U#l")
+		&[0x7b, 0x55, 0x03, 0x72, 0x61, 0x77, 0x5b, 0x24, 0x55, 0x23, 0x6c])?;
+	let raw_len = r.read_u32::<BE>()?;
+	if raw_len == 0 {
+		return Err(err!("unknown raw length (in-progress replay?)"));
+	}
+	r.seek(io::SeekFrom::Current(raw_len as i64))?;
+	metadata(&mut r)
+}
+
+/// Reads the `metadata` element and the top-level closing brace that follows.
+fn metadata<R: Read>(mut r: &mut R) -> Result<serde_json::Map<String, serde_json::Value>> {
 	expect_bytes(&mut r,
 		// `metadata` key & type ("U\x08metadata{")
 		&[0x55, 0x08, 0x6d, 0x65, 0x74, 0x61, 0x64, 0x61, 0x74, 0x61, 0x7b])?;
@@ -895,10 +1163,7 @@ pub fn deserialize<R: Read, H: Handlers>(mut r: &mut R, handlers: &mut H, opts:
 	// we know it's a map. `parse_map` will consume the corresponding "}".
 	let metadata = ubjson::de::to_map(&mut r)?;
 	info!("Raw metadata: {}", serde_json::to_string(&metadata)?);
-	handlers.metadata(metadata)?;
 
 	expect_bytes(&mut r, &[0x7d])?; // top-level closing brace ("}")
-
-	handlers.finalize()?;
-	Ok(())
+	Ok(metadata)
 }
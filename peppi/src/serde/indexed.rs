@@ -0,0 +1,244 @@
+//! A peppi-native container for replays that can jump straight to any frame.
+//!
+//! Frames are stored as their Slippi events (as in a `.slp`), followed by an index
+//! of where each frame starts. Parsing a frame on its own also needs the Zelda/Sheik
+//! transformation state left behind by the frames before it (see `de::CharState`),
+//! so the index records that too.
+//!
+//! Layout (big-endian):
+//!
+//! * `MAGIC`
+//! * header length (u32), then Event Payloads, Game Start & Gecko Codes
+//! * each frame's events, in the same order as `Game::frames`
+//! * Game End, then metadata (UBJSON)
+//! * the index: offset of Game End (u64) & frame count (u32), then for each frame
+//!   its offset (u64), length (u32) & a `CharState` per port
+//! * offset of the index (u64)
+
+use std::io::{Read, Result, Seek, SeekFrom, Write};
+
+use byteorder::{ReadBytesExt, WriteBytesExt};
+use serde_json::{Map, Value};
+
+use crate::{
+	model::{
+		enums::{action_state::State, character::Internal},
+		frame::{self, DynFrame},
+		game::{self, Frames, Game, GeckoCodes, NUM_PORTS},
+		primitives::Port,
+		slippi,
+	},
+	serde::{
+		collect::{self, Collector},
+		de::{self, CharState, Handlers, SizeTable, DEFAULT_CHAR_STATE},
+		ser,
+	},
+	ubjson,
+};
+
+type BE = byteorder::BigEndian;
+
+/// Identifies a peppi file; the last byte is the format version.
+pub const MAGIC: [u8; 8] = *b"peppi\x00\x00\x01";
+
+type CharStates = [CharState; NUM_PORTS];
+
+/// Ignores all events (we only want their effect on `CharState`s).
+struct Skip;
+
+impl Handlers for Skip {}
+
+fn write_char_states<W: Write>(w: &mut W, states: &CharStates) -> Result<()> {
+	for s in states {
+		w.write_u8(s.character.0)?;
+		w.write_u16::<BE>(s.state.into())?;
+		w.write_u32::<BE>(s.age)?;
+	}
+	Ok(())
+}
+
+fn read_char_states<R: Read>(r: &mut R) -> Result<CharStates> {
+	let mut states = [DEFAULT_CHAR_STATE; NUM_PORTS];
+	for s in &mut states {
+		let character = Internal(r.read_u8()?);
+		*s = CharState {
+			character: character,
+			state: State::from(r.read_u16::<BE>()?, character),
+			age: r.read_u32::<BE>()?,
+		};
+	}
+	Ok(states)
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Entry {
+	offset: u64,
+	len: u32,
+	char_states: CharStates,
+}
+
+fn frame_bytes<const N: usize>(f: &frame::Frame<N>, ports: &[Port], v: slippi::Version) -> Result<Vec<u8>> {
+	let mut buf = Vec::new();
+	ser::frames(&mut ser::Counter::new(&mut buf), [f], ports, v, &ser::Opts::default())?;
+	Ok(buf)
+}
+
+fn all_frame_bytes(game: &Game) -> Result<Vec<Vec<u8>>> {
+	let v = game.start.slippi.version;
+	let ports: Vec<_> = game.start.players.iter().map(|p| p.port).collect();
+	match &game.frames {
+		Frames::P1(f) => f.iter().map(|f| frame_bytes(f, &ports, v)).collect(),
+		Frames::P2(f) => f.iter().map(|f| frame_bytes(f, &ports, v)).collect(),
+		Frames::P3(f) => f.iter().map(|f| frame_bytes(f, &ports, v)).collect(),
+		Frames::P4(f) => f.iter().map(|f| frame_bytes(f, &ports, v)).collect(),
+	}
+}
+
+/// Writes `game` to `w` as a peppi file (see the module docs), for reading with `PeppiFile`.
+pub fn write<W: Write>(w: &mut W, game: &Game) -> Result<()> {
+	let mut header = Vec::new();
+	ser::raw_header(&mut ser::Counter::new(&mut header), &game.start, game.gecko_codes.as_ref())?;
+	let (_, payload_sizes) = de::payload_sizes(&mut &header[..])?;
+	let payload_sizes = de::size_table(&payload_sizes);
+
+	w.write_all(&MAGIC)?;
+	w.write_u32::<BE>(header.len().try_into().map_err(|_| err!("header too long"))?)?;
+	w.write_all(&header)?;
+	let mut offset = (MAGIC.len() + 4 + header.len()) as u64;
+
+	let mut char_states = [DEFAULT_CHAR_STATE; NUM_PORTS];
+	let mut index = Vec::with_capacity(game.frames.len());
+	for bytes in all_frame_bytes(game)? {
+		index.push(Entry {
+			offset: offset,
+			len: bytes.len() as u32,
+			char_states: char_states,
+		});
+		de::events(&bytes, &payload_sizes, &mut char_states, &mut Skip)?;
+		w.write_all(&bytes)?;
+		offset += bytes.len() as u64;
+	}
+
+	let end_offset = offset;
+	let mut tail = Vec::new();
+	ser::game_end(&mut tail, &game.end, game.start.slippi.version)?;
+	ubjson::ser::from_map(&mut tail, &game.metadata_raw)?;
+	tail.push(0x7d); // closing brace for the metadata map
+	w.write_all(&tail)?;
+	let index_offset = end_offset + tail.len() as u64;
+
+	w.write_u64::<BE>(end_offset)?;
+	w.write_u32::<BE>(index.len() as u32)?;
+	for e in &index {
+		w.write_u64::<BE>(e.offset)?;
+		w.write_u32::<BE>(e.len)?;
+		write_char_states(w, &e.char_states)?;
+	}
+	w.write_u64::<BE>(index_offset)?;
+
+	Ok(())
+}
+
+/// Reader for peppi files (see `write`). Only the header, game end, metadata & index
+/// are read up front; frames are parsed on demand by `seek_frame`.
+pub struct PeppiFile<R> {
+	r: R,
+	payload_sizes: SizeTable,
+	index: Vec<Entry>,
+	pub start: game::Start,
+	pub end: game::End,
+	pub gecko_codes: Option<GeckoCodes>,
+	pub metadata_raw: Map<String, Value>,
+}
+
+impl<R: Read + Seek> PeppiFile<R> {
+	/// Reads the header, game end, metadata & frame index from `r`.
+	pub fn open(mut r: R) -> Result<Self> {
+		let mut magic = [0; MAGIC.len()];
+		r.read_exact(&mut magic)?;
+		if magic != MAGIC {
+			return Err(err!("not a peppi file (magic: {:?})", magic));
+		}
+
+		let mut header = vec![0; r.read_u32::<BE>()? as usize];
+		r.read_exact(&mut header)?;
+		let mut rest = &header[..];
+		let (_, payload_sizes) = de::payload_sizes(&mut rest)?;
+		let payload_sizes = de::size_table(&payload_sizes);
+		let mut char_states = [DEFAULT_CHAR_STATE; NUM_PORTS];
+		let mut collector = Collector::default();
+		de::events(rest, &payload_sizes, &mut char_states, &mut collector)?;
+		let start = collector.start.take().ok_or_else(|| err!("missing start event"))?;
+
+		r.seek(SeekFrom::End(-8))?;
+		let index_offset = r.read_u64::<BE>()?;
+		r.seek(SeekFrom::Start(index_offset))?;
+		let end_offset = r.read_u64::<BE>()?;
+		let frame_count = r.read_u32::<BE>()? as usize;
+		let mut index = Vec::with_capacity(frame_count);
+		for _ in 0 .. frame_count {
+			index.push(Entry {
+				offset: r.read_u64::<BE>()?,
+				len: r.read_u32::<BE>()?,
+				char_states: read_char_states(&mut r)?,
+			});
+		}
+
+		r.seek(SeekFrom::Start(end_offset))?;
+		let end_size = payload_sizes[de::Event::GameEnd as usize]
+			.ok_or_else(|| err!("missing payload size for game end"))? as usize;
+		let mut end = vec![0; 1 + end_size];
+		r.read_exact(&mut end)?;
+		de::events(&end, &payload_sizes, &mut char_states, &mut collector)?;
+		let end = collector.end.take().ok_or_else(|| err!("missing end event"))?;
+		let metadata_raw = ubjson::de::to_map(&mut r)?;
+
+		Ok(PeppiFile {
+			r: r,
+			payload_sizes: payload_sizes,
+			index: index,
+			start: start,
+			end: end,
+			gecko_codes: collector.gecko_codes,
+			metadata_raw: metadata_raw,
+		})
+	}
+
+	/// Number of frames, including any rolled-back ones (as in `Game::frames`).
+	pub fn len(&self) -> usize {
+		self.index.len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.index.is_empty()
+	}
+
+	/// Reads & parses only the `n`th frame (as in `Game::frames`), without scanning
+	/// the frames before it.
+	pub fn seek_frame(&mut self, n: usize) -> Result<DynFrame> {
+		let entry = *self.index.get(n)
+			.ok_or_else(|| err!("frame {} out of range ({} frames)", n, self.index.len()))?;
+		self.r.seek(SeekFrom::Start(entry.offset))?;
+		let mut buf = vec![0; entry.len as usize];
+		self.r.read_exact(&mut buf)?;
+
+		let mut collector = Collector {
+			// keeps the frame's own index rather than numbering from the first frame
+			opts: collect::Opts { rollbacks: true, ..Default::default() },
+			start: Some(self.start.clone()),
+			end: Some(self.end.clone()),
+			..Default::default()
+		};
+		let mut char_states = entry.char_states;
+		de::events(&buf, &self.payload_sizes, &mut char_states, &mut collector)?;
+		collector.finalize()?;
+
+		let frame = match collector.into_game()?.frames {
+			Frames::P1(f) => f.into_iter().next().map(DynFrame::from),
+			Frames::P2(f) => f.into_iter().next().map(DynFrame::from),
+			Frames::P3(f) => f.into_iter().next().map(DynFrame::from),
+			Frames::P4(f) => f.into_iter().next().map(DynFrame::from),
+		};
+		frame.ok_or_else(|| err!("no data for frame {}", n))
+	}
+}
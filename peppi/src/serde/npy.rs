@@ -0,0 +1,86 @@
+//! Frame data as NumPy arrays (`.npy`), readable with `numpy.load`.
+//!
+//! See https://numpy.org/doc/stable/reference/generated/numpy.lib.format.html.
+
+use std::{fs, io::{Result, Write}, path::Path};
+
+use byteorder::WriteBytesExt;
+
+use crate::model::{
+	game::{Game, PortFramesOpts},
+	primitives::Port,
+};
+
+type LE = byteorder::LittleEndian;
+
+const MAGIC: &[u8] = b"\x93NUMPY\x01\x00";
+
+/// Header alignment recommended by the spec (in bytes, including `MAGIC`).
+const ALIGN: usize = 64;
+
+/// Writes the `.npy` preamble for a C-order array of `descr` (a NumPy dtype string,
+/// e.g. `<f4`) with dimensions `shape`.
+fn header<W: Write>(w: &mut W, descr: &str, shape: &[usize]) -> Result<()> {
+	let shape = match shape {
+		[n] => format!("({},)", n),
+		_ => format!("({})", shape.iter().map(|n| n.to_string()).collect::<Vec<_>>().join(", ")),
+	};
+	let mut dict = format!("{{'descr': '{}', 'fortran_order': False, 'shape': {}, }}", descr, shape);
+	// pad with spaces & a trailing newline, so the data starts aligned
+	let unpadded = MAGIC.len() + 2 + dict.len() + 1;
+	dict.extend(std::iter::repeat_n(' ', (ALIGN - unpadded % ALIGN) % ALIGN));
+	dict.push('\n');
+
+	w.write_all(MAGIC)?;
+	w.write_u16::<LE>(dict.len().try_into().map_err(|_| err!("npy header too long"))?)?;
+	w.write_all(dict.as_bytes())
+}
+
+fn write_f32s(path: &Path, shape: &[usize], values: impl IntoIterator<Item = f32>) -> Result<()> {
+	let mut buf = Vec::new();
+	header(&mut buf, "<f4", shape)?;
+	for v in values {
+		buf.write_f32::<LE>(v)?;
+	}
+	fs::write(path, buf)
+}
+
+fn write_u16s(path: &Path, shape: &[usize], values: impl IntoIterator<Item = u16>) -> Result<()> {
+	let mut buf = Vec::new();
+	header(&mut buf, "<u2", shape)?;
+	for v in values {
+		buf.write_u16::<LE>(v)?;
+	}
+	fs::write(path, buf)
+}
+
+/// Writes the following arrays (each with one row per finalized frame of the leader in
+/// `port`, in order) to `dir`, which must already exist:
+///
+/// * `position.npy`: `float32`, shape `(frames, 2)` — x & y
+/// * `velocity.npy`: `float32`, shape `(frames, 4)` — autogenous x & y, then knockback
+///   x & y (see `frame::Velocities`); all `NaN` before v3.5, which added velocities
+/// * `damage.npy`: `float32`, shape `(frames,)` — percent
+/// * `state.npy`: `uint16`, shape `(frames,)` — action state
+///
+/// Fails if there's no player in `port`.
+pub fn write(dir: &Path, game: &Game, port: Port) -> Result<()> {
+	let opts = Some(PortFramesOpts { finalized_only: true, ..Default::default() });
+	let posts: Vec<_> = game.port_frames(port, opts)
+		.ok_or_else(|| err!("no player in port {:?}", port))?
+		.filter(|f| !f.is_follower)
+		.map(|f| &f.data.post)
+		.collect();
+	let n = posts.len();
+
+	write_f32s(&dir.join("position.npy"), &[n, 2],
+		posts.iter().flat_map(|p| [p.position.x, p.position.y]))?;
+	write_f32s(&dir.join("velocity.npy"), &[n, 4], posts.iter().flat_map(|p| match p.velocities {
+		Some(v) => [v.autogenous.x, v.autogenous.y, v.knockback.x, v.knockback.y],
+		None => [f32::NAN; 4],
+	}))?;
+	write_f32s(&dir.join("damage.npy"), &[n], posts.iter().map(|p| p.damage))?;
+	write_u16s(&dir.join("state.npy"), &[n], posts.iter().map(|p| u16::from(p.state)))?;
+
+	Ok(())
+}
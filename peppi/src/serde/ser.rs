@@ -1,92 +1,127 @@
 use std::{
-	io::{Result, Seek, SeekFrom, Write},
+	collections::HashMap,
+	error, fmt,
+	io::{self, Cursor, Read, Result, Seek, SeekFrom, Write},
+	ops::Range,
 };
 
 use byteorder::{LittleEndian, WriteBytesExt};
+use encoding_rs::SHIFT_JIS;
 
 use crate::{
 	model::{
 		frame,
 		game::{self, Frames, GeckoCodes},
 		item,
+		primitives::Port,
 		slippi::{self, version as ver},
 	},
-	serde::de::{PAYLOADS_EVENT_CODE, Event, PortId},
+	serde::de::{self, PAYLOADS_EVENT_CODE, Event, FrameEvent, FrameId, Handlers, PortId, expected_payload_size},
 	ubjson,
 };
 
 type BE = byteorder::BigEndian;
 
-fn payload_sizes(game: &game::Game) -> Vec<(u8, u16)> {
-	let start = &game.start;
-	let v = start.slippi.version;
-	let mut sizes = Vec::new();
+/// Why a game couldn't be serialized.
+#[derive(Debug)]
+pub enum UnparseError {
+	/// A string doesn't fit in its fixed-size field once encoded (e.g. a name tag).
+	FieldTooLong { field: &'static str, value: String, len: usize, max: usize },
+	/// Data required by the replay's version is missing (e.g. frame start data since v2.2).
+	MissingField { field: &'static str, frame: Option<i32>, since: slippi::Version },
+	/// Data is present that the replay's version can't represent.
+	VersionMismatch { field: &'static str, frame: Option<i32>, since: slippi::Version },
+	/// A frame's index (or latest finalized frame) is out of sequence (e.g. the first frame
+	/// isn't `FIRST_FRAME_INDEX`).
+	FrameIndex { frame: i32, expected: i32 },
+	/// Writing failed.
+	Io(io::Error),
+}
 
-	sizes.push((Event::GameStart as u8, start.raw_bytes.len() as u16));
+impl fmt::Display for UnparseError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		use UnparseError::*;
+		match self {
+			FieldTooLong { field, value, len, max } =>
+				write!(f, "{} too long ({} bytes, max {}): {:?}", field, len, max, value),
+			MissingField { field, frame, since } | VersionMismatch { field, frame, since } => {
+				if let Some(frame) = frame {
+					write!(f, "frame {}: ", frame)?;
+				}
+				match self {
+					MissingField { .. } => write!(f, "missing {} (required since v{})", field, since),
+					_ => write!(f, "unexpected {} (not supported before v{})", field, since),
+				}
+			},
+			FrameIndex { frame, expected } =>
+				write!(f, "frame {}: out of sequence (expected frame {})", frame, expected),
+			Io(e) => write!(f, "{}", e),
+		}
+	}
+}
 
-	sizes.push((Event::FramePre as u8,
-		if v >= ver(1, 4) {
-			63
-		} else if v >= ver(1, 2) {
-			59
-		} else {
-			58
-		}
-	));
-
-	sizes.push((Event::FramePost as u8,
-		if v >= ver(3, 11) {
-			80
-		} else if v >= ver(3, 8) {
-			76
-		} else if v >= ver(3, 5) {
-			72
-		} else if v >= ver(2, 1) {
-			52
-		} else if v >= ver(2, 0) {
-			51
-		} else if v >= ver(0, 2) {
-			37
-		} else {
-			33
-		}
-	));
-
-	sizes.push((Event::GameEnd as u8, if v >= ver(2, 0) { 2 } else { 1 }));
-
-	if v >= ver(2, 2) {
-		sizes.push((Event::FrameStart as u8,
-			if v >= ver(3, 10) {
-				12
-			} else {
-				8
-			}
-		));
+impl error::Error for UnparseError {
+	fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+		match self {
+			UnparseError::Io(e) => Some(e),
+			_ => None,
+		}
 	}
+}
 
-	if v >= ver(3, 0) {
-		sizes.push((Event::Item as u8,
-			if v >= ver(3, 6) {
-				42
-			} else if v >= ver(3, 2) {
-				41
-			} else {
-				37
-			}
-		));
+/// Recovers the `UnparseError` carried by an `io::Error` (see the reverse conversion),
+/// or wraps it as `UnparseError::Io`.
+impl From<io::Error> for UnparseError {
+	fn from(e: io::Error) -> Self {
+		match e.get_ref().is_some_and(|inner| inner.is::<UnparseError>()) {
+			true => *e.into_inner().unwrap().downcast::<UnparseError>().unwrap(),
+			_ => UnparseError::Io(e),
+		}
 	}
+}
 
-	if v >= ver(3, 0) {
-		sizes.push((Event::FrameEnd as u8,
-			if v >= ver(3, 7) {
-				8
-			} else {
-				4
-			}
-		));
+/// Lets `UnparseError`s pass through code returning `io::Result` (e.g. `Handlers`).
+impl From<UnparseError> for io::Error {
+	fn from(e: UnparseError) -> Self {
+		match e {
+			UnparseError::Io(e) => e,
+			e => io::Error::new(io::ErrorKind::InvalidData, e),
+		}
+	}
+}
+
+/// Where Item Update events go within each frame.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ItemOrder {
+	/// between the Pre-Frame and Post-Frame Updates, as Slippi writes them
+	#[default]
+	BeforePost,
+	/// after the Post-Frame Updates, right before Frame Bookend
+	AfterPost,
+}
+
+/// Options for serializing replays.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Opts {
+	/// Order of events within each frame, for readers that expect a nonstandard one.
+	/// Peppi's own parser accepts either.
+	pub item_order: ItemOrder,
+}
+
+fn payload_sizes(start: &game::Start, gecko_codes: Option<&GeckoCodes>) -> Vec<(u8, u16)> {
+	let v = start.slippi.version;
+	let mut sizes = Vec::new();
+
+	sizes.push((Event::GameStart as u8, start.raw_bytes.len() as u16));
+
+	use Event::*;
+	for event in [FramePre, FramePost, GameEnd, FrameStart, Item, FrameEnd] {
+		if let Some(size) = expected_payload_size(event, v) {
+			sizes.push((event as u8, size));
+		}
 	}
 
-	if let Some(codes) = &game.gecko_codes {
+	if let Some(codes) = gecko_codes {
 		sizes.push((Event::GeckoCodes as u8, codes.actual_size));
 	}
 
@@ -97,31 +132,207 @@ fn payload_sizes(game: &game::Game) -> Vec<(u8, u16)> {
 	sizes
 }
 
-fn gecko_codes<W: Write>(w: &mut W, codes: &GeckoCodes) -> Result<()> {
+fn gecko_codes<W: Write>(w: &mut Counter<W>, codes: &GeckoCodes) -> Result<()> {
 	let mut pos = 0;
 	let actual_size = codes.actual_size as usize;
 	while pos < actual_size {
-		w.write_u8(0x10)?; // Message Splitter
-		w.write_all(&codes.bytes[pos .. pos + 512])?;
-		w.write_u16::<BE>(std::cmp::min(512, actual_size - pos) as u16)?;
-		w.write_u8(Event::GeckoCodes as u8)?;
+		w.event(TraceKind::MessageSplitter, |w| {
+			w.write_u8(0x10)?; // Message Splitter
+			w.write_all(&codes.bytes[pos .. pos + 512])?;
+			w.write_u16::<BE>(std::cmp::min(512, actual_size - pos) as u16)?;
+			w.write_u8(Event::GeckoCodes as u8)?;
+			w.write_u8(if pos + 512 < actual_size { 0 } else { 1 })
+		})?;
 		pos += 512;
-		w.write_u8(if pos < actual_size { 0 } else { 1 })?;
 	}
 	Ok(())
 }
 
+/// Writes `s` into a fixed-size, null-padded field, unless the field
+/// already decodes to `s` (preserving any bytes after the null).
+fn string_field<D, E>(buf: &mut [u8], s: &str, name: &'static str, decode: D, encode: E) -> Result<()>
+where D: FnOnce(&[u8]) -> String, E: FnOnce(&str) -> Vec<u8> {
+	let first_null = buf.iter().position(|&x| x == 0).unwrap_or(buf.len());
+	if decode(&buf[0 .. first_null]) == s {
+		return Ok(());
+	}
+	let encoded = encode(s);
+	if encoded.len() > buf.len() {
+		return Err(UnparseError::FieldTooLong {
+			field: name,
+			value: s.to_string(),
+			len: encoded.len(),
+			max: buf.len(),
+		}.into());
+	}
+	buf.fill(0);
+	buf[.. encoded.len()].copy_from_slice(&encoded);
+	Ok(())
+}
+
+fn shift_jis_field(buf: &mut [u8], s: &str, name: &'static str) -> Result<()> {
+	string_field(buf, s, name,
+		|b| SHIFT_JIS.decode_without_bom_handling(b).0.to_string(),
+		|s| SHIFT_JIS.encode(s).0.to_vec())
+}
+
+fn utf8_field(buf: &mut [u8], s: &str, name: &'static str) -> Result<()> {
+	string_field(buf, s, name,
+		|b| String::from_utf8_lossy(b).to_string(),
+		|s| s.as_bytes().to_vec())
+}
+
+/// Writes `value` into `b`, unless `b` already has the same truthiness.
+fn bool_field(b: &mut u8, value: bool) {
+	if (*b != 0) != value {
+		*b = value as u8;
+	}
+}
+
+fn player_bytes(buf: &mut [u8], p: &game::Player) -> Result<()> {
+	let mut w = &mut buf[0 .. 4];
+	w.write_u8(p.character.0)?;
+	w.write_u8(p.r#type.0)?;
+	w.write_u8(p.stocks)?;
+	w.write_u8(p.costume)?;
+	if let Some(team) = p.team {
+		buf[7] = team.shade.0;
+		buf[9] = team.color.0;
+	}
+	buf[8] = p.handicap;
+	buf[12] = p.bitfield;
+	if let Some(cpu_level) = p.cpu_level {
+		buf[15] = cpu_level;
+	}
+	let mut w = &mut buf[24 .. 36];
+	w.write_f32::<BE>(p.offense_ratio)?;
+	w.write_f32::<BE>(p.defense_ratio)?;
+	w.write_f32::<BE>(p.model_scale)?;
+	Ok(())
+}
+
+/// The bytes of `raw` at `range`, or an error naming `name` if `raw` is too short
+/// (i.e. the start block is from a version without that field).
+fn field<'a>(raw: &'a mut [u8], range: Range<usize>, name: &str) -> Result<&'a mut [u8]> {
+	let len = raw.len();
+	raw.get_mut(range.clone())
+		.ok_or_else(|| err!("game start too short for {}: {} bytes (need {})", name, len, range.end))
+}
+
+/// Overwrites the fields of `raw` (a copy of `s.raw_bytes`) with the values in `s`,
+/// so that changes to `s` are reflected in the output. Unmapped bytes are left as-is.
+pub(crate) fn game_start_bytes(raw: &mut [u8], s: &game::Start) -> Result<()> {
+	if s.players.len() > game::NUM_PORTS {
+		return Err(err!("too many players: {} (max: {})", s.players.len(), game::NUM_PORTS));
+	}
+	for (n, p) in s.players.iter().enumerate() {
+		if s.players[.. n].iter().any(|q| q.port == p.port) {
+			return Err(err!("multiple players in port {:?}", p.port));
+		}
+	}
+
+	if raw.len() < 320 {
+		return Err(err!("game start too short: {} bytes (min: 320)", raw.len()));
+	}
+
+	let v = s.slippi.version;
+	raw[0 .. 3].copy_from_slice(&[v.0, v.1, v.2]);
+	raw[4 .. 8].copy_from_slice(&s.bitfield);
+	bool_field(&mut raw[10], s.is_raining_bombs);
+	bool_field(&mut raw[12], s.is_teams);
+	raw[15] = s.item_spawn_frequency as u8;
+	raw[16] = s.self_destruct_score as u8;
+	(&mut raw[18 .. 20]).write_u16::<BE>(s.stage.0)?;
+	(&mut raw[20 .. 24]).write_u32::<BE>(s.timer)?;
+	raw[39 .. 44].copy_from_slice(&s.item_spawn_bitfield);
+	(&mut raw[52 .. 56]).write_f32::<BE>(s.damage_ratio)?;
+	(&mut raw[316 .. 320]).write_u32::<BE>(s.random_seed)?;
+
+	// mark ports without a player (& the two slots past the last port) as empty
+	for n in 0 .. game::MAX_PLAYERS {
+		let r#type = &mut raw[101 + 36 * n];
+		let is_player = matches!(game::PlayerType(*r#type),
+			game::PlayerType::HUMAN | game::PlayerType::CPU | game::PlayerType::DEMO);
+		if is_player && !s.players.iter().any(|p| p.port as usize == n) {
+			*r#type = 3;
+		}
+	}
+
+	// Fields added in later versions are written if the block has room for them,
+	// which is an error if they're set but it doesn't.
+	for p in &s.players {
+		let n = p.port as usize;
+		player_bytes(&mut raw[100 + 36 * n .. 136 + 36 * n], p)?;
+
+		// v1.0
+		if p.ucf.is_some() || raw.len() >= 352 {
+			let ucf = p.ucf.unwrap_or_default();
+			let mut w = field(raw, 320 + 8 * n .. 328 + 8 * n, "ucf")?;
+			w.write_u32::<BE>(ucf.dash_back.map(|db| db.0).unwrap_or(0))?;
+			w.write_u32::<BE>(ucf.shield_drop.map(|sd| sd.0).unwrap_or(0))?;
+		}
+
+		// v1.3
+		if p.name_tag.is_some() || raw.len() >= 416 {
+			let name_tag = p.name_tag.as_deref().unwrap_or("");
+			shift_jis_field(field(raw, 352 + 16 * n .. 368 + 16 * n, "name tag")?, name_tag, "name tag")?;
+		}
+
+		// v3.9
+		if p.netplay.is_some() || raw.len() >= 584 {
+			let netplay = p.netplay.clone().unwrap_or_default();
+			shift_jis_field(field(raw, 420 + 31 * n .. 451 + 31 * n, "netplay name")?, &netplay.name, "netplay name")?;
+			shift_jis_field(field(raw, 544 + 10 * n .. 554 + 10 * n, "netplay code")?, &netplay.code, "netplay code")?;
+
+			// v3.11
+			if netplay.suid.is_some() || raw.len() >= 700 {
+				let suid = netplay.suid.unwrap_or_default();
+				utf8_field(field(raw, 584 + 29 * n .. 612 + 29 * n, "netplay suid")?, &suid, "netplay suid")?;
+			}
+		}
+	}
+
+	// v1.5
+	if let Some(is_pal) = s.is_pal {
+		bool_field(&mut field(raw, 416 .. 417, "is_pal")?[0], is_pal);
+	}
+	// v2.0
+	if let Some(is_frozen_ps) = s.is_frozen_ps {
+		bool_field(&mut field(raw, 417 .. 418, "is_frozen_ps")?[0], is_frozen_ps);
+	}
+	// v3.7
+	if let Some(scene) = s.scene {
+		field(raw, 418 .. 420, "scene")?.copy_from_slice(&[scene.minor, scene.major]);
+	}
+	// v3.12
+	if let Some(language) = s.language {
+		field(raw, 700 .. 701, "language")?[0] = language.0;
+	}
+	// v3.14
+	if let Some(m) = &s.r#match {
+		utf8_field(field(raw, 701 .. 752, "match id")?, &m.id, "match id")?;
+		let mut w = field(raw, 752 .. 760, "match")?;
+		w.write_u32::<BE>(m.game)?;
+		w.write_u32::<BE>(m.tiebreaker)?;
+	}
+
+	Ok(())
+}
+
 fn game_start<W: Write>(w: &mut W, s: &game::Start, v: slippi::Version) -> Result<()> {
 	assert_eq!(v, s.slippi.version);
+	let mut raw = s.raw_bytes.clone();
+	game_start_bytes(&mut raw, s)?;
 	w.write_u8(Event::GameStart as u8)?;
-	w.write_all(&s.raw_bytes)
+	w.write_all(&raw)
 }
 
-fn game_end<W: Write>(w: &mut W, e: &game::End, v: slippi::Version) -> Result<()> {
+pub(crate) fn game_end<W: Write>(w: &mut W, e: &game::End, v: slippi::Version) -> Result<()> {
 	w.write_u8(Event::GameEnd as u8)?;
 	w.write_u8(e.method.0)?;
 	if v >= ver(2, 0) {
-		w.write_u8(e.lras_initiator.unwrap().map(|p| p.into()).unwrap_or(u8::MAX))?;
+		// unset is treated as "nobody LRAS'd"
+		w.write_u8(e.lras_initiator.flatten().map(|p| p.into()).unwrap_or(u8::MAX))?;
 	}
 	Ok(())
 }
@@ -136,7 +347,8 @@ fn frame_start<W: Write>(w: &mut W, s: &frame::Start, v: slippi::Version, frame_
 	Ok(())
 }
 
-fn frame_pre<W: Write>(w: &mut W, p: &frame::Pre, v: slippi::Version, id: PortId) -> Result<()> {
+/// `default_damage` is written in place of a missing `p.damage`.
+fn frame_pre<W: Write>(w: &mut W, p: &frame::Pre, v: slippi::Version, id: PortId, default_damage: Option<f32>) -> Result<()> {
 	w.write_u8(Event::FramePre as u8)?;
 	w.write_i32::<BE>(id.index)?;
 	w.write_u8(id.port as u8)?;
@@ -162,7 +374,12 @@ fn frame_pre<W: Write>(w: &mut W, p: &frame::Pre, v: slippi::Version, id: PortId
 	}
 
 	if v >= ver(1, 4) {
-		w.write_f32::<BE>(p.damage.unwrap())?;
+		let damage = p.damage.or(default_damage).ok_or(UnparseError::MissingField {
+			field: "pre-frame damage",
+			frame: Some(id.index),
+			since: ver(1, 4),
+		})?;
+		w.write_f32::<BE>(damage)?;
 	}
 
 	Ok(())
@@ -201,7 +418,7 @@ fn frame_post<W: Write>(w: &mut W, p: &frame::Post, v: slippi::Version, id: Port
 		w.write_u8(match p.l_cancel.unwrap() { Some(true) => 1, Some(false) => 2, _ => 0 })?;
 	}
 
-	if v >= ver(2, 1) {
+	if v.has_hurtbox_state() {
 		w.write_u8(p.hurtbox_state.unwrap().0)?;
 	}
 
@@ -214,10 +431,14 @@ fn frame_post<W: Write>(w: &mut W, p: &frame::Post, v: slippi::Version, id: Port
 		w.write_f32::<BE>(vel.autogenous_x.ground)?;
 	}
 
-	if v >= ver(3,8) {
+	if v.has_hitlag() {
 		w.write_f32::<BE>(p.hitlag.unwrap())?;
 	}
 
+	if v >= ver(3, 11) {
+		w.write_u32::<BE>(p.animation_index.unwrap())?;
+	}
+
 	Ok(())
 }
 
@@ -250,89 +471,566 @@ fn item<W: Write>(w: &mut W, i: &item::Item, v: slippi::Version, frame_idx: i32)
 fn frame_end<W: Write>(w: &mut W, e: &frame::End, v: slippi::Version, frame_idx: i32) -> Result<()> {
 	w.write_u8(Event::FrameEnd as u8)?;
 	w.write_i32::<BE>(frame_idx)?;
-	if v >= ver(3, 7) {
+	if v >= slippi::Version::FINALIZED_FRAMES {
 		w.write_i32::<BE>(e.latest_finalized_frame.unwrap())?;
 	}
 	Ok(())
 }
 
-fn frames<W: Write, const N: usize>(w: &mut W, frames: &[frame::Frame<N>], v: slippi::Version) -> Result<()> {
+fn items<W: Write>(w: &mut Counter<W>, items: Option<&Vec<item::Item>>, v: slippi::Version, frame_idx: i32) -> Result<()> {
+	if v.has_items() {
+		for i in items.unwrap() {
+			w.event(TraceKind::Event(Event::Item), |w| item(w, i, v, frame_idx))?;
+		}
+	}
+	Ok(())
+}
+
+/// `ports` are the players' ports, in the same order as each frame's `ports`.
+pub(crate) fn frames<'a, W: Write, const N: usize>(w: &mut Counter<W>, frames: impl IntoIterator<Item = &'a frame::Frame<N>>, ports: &[Port], v: slippi::Version, opts: &Opts) -> Result<()> {
+	// each character's post-frame damage (by port & follower) on the latest copy of each
+	// frame index written so far, so that a replayed frame follows the frame before it
+	// rather than the rolled-back frame written just before it
+	let mut post_damage: HashMap<i32, [[Option<f32>; 2]; N]> = HashMap::new();
 	for f in frames {
-		if v >= ver(2, 2) {
-			frame_start(w, f.start.as_ref().unwrap(), v, f.index)?;
+		match (v >= slippi::Version::ROLLBACK, &f.start) {
+			(true, Some(start)) => w.event(TraceKind::Event(Event::FrameStart), |w| frame_start(w, start, v, f.index))?,
+			(true, None) => return Err(UnparseError::MissingField {
+				field: "frame start",
+				frame: Some(f.index),
+				since: slippi::Version::ROLLBACK,
+			}.into()),
+			(false, Some(_)) => return Err(UnparseError::VersionMismatch {
+				field: "frame start",
+				frame: Some(f.index),
+				since: slippi::Version::ROLLBACK,
+			}.into()),
+			(false, None) => (),
 		}
 
-		let mut port_idx = 0u8;
-		for p in &f.ports {
-			frame_pre(w, &p.leader.pre, v, PortId::new(f.index, port_idx, false)?)?;
+		// pre-frame damage is the damage as of the previous frame's post-frame update
+		let prev_damage = post_damage.get(&(f.index - 1));
+		let default_damage = |n: usize, follower: bool, post: &frame::Post| {
+			Some(prev_damage.and_then(|d| d[n][follower as usize]).unwrap_or(post.damage))
+		};
+
+		for (n, p) in f.ports.iter().enumerate() {
+			let id = PortId::new(f.index, ports[n] as u8, false)?;
+			let damage = default_damage(n, false, &p.leader.post);
+			w.event(TraceKind::Event(Event::FramePre), |w| frame_pre(w, &p.leader.pre, v, id, damage))?;
 			if let Some(follower) = &p.follower {
-				frame_pre(w, &follower.pre, v, PortId::new(f.index, port_idx, true)?)?;
+				let id = PortId::new(f.index, ports[n] as u8, true)?;
+				let damage = default_damage(n, true, &follower.post);
+				w.event(TraceKind::Event(Event::FramePre), |w| frame_pre(w, &follower.pre, v, id, damage))?;
 			}
-			port_idx += 1;
 		}
 
-		if v >= ver(3, 0) {
-			for i in f.items.as_ref().unwrap() {
-				item(w, i, v, f.index)?;
-			}
+		if opts.item_order == ItemOrder::BeforePost {
+			items(w, f.items.as_ref(), v, f.index)?;
 		}
 
-		port_idx = 0u8;
-		for p in &f.ports {
-			frame_post(w, &p.leader.post, v, PortId::new(f.index, port_idx, false)?)?;
+		let mut damage = [[None; 2]; N];
+		for (n, p) in f.ports.iter().enumerate() {
+			let id = PortId::new(f.index, ports[n] as u8, false)?;
+			w.event(TraceKind::Event(Event::FramePost), |w| frame_post(w, &p.leader.post, v, id))?;
+			damage[n][0] = Some(p.leader.post.damage);
 			if let Some(follower) = &p.follower {
-				frame_post(w, &follower.post, v, PortId::new(f.index, port_idx, true)?)?;
+				let id = PortId::new(f.index, ports[n] as u8, true)?;
+				w.event(TraceKind::Event(Event::FramePost), |w| frame_post(w, &follower.post, v, id))?;
+				damage[n][1] = Some(follower.post.damage);
 			}
-			port_idx += 1;
 		}
+		post_damage.insert(f.index, damage);
 
-		if v >= ver(3, 0) {
-			frame_end(w, f.end.as_ref().unwrap(), v, f.index)?;
+		if opts.item_order == ItemOrder::AfterPost {
+			items(w, f.items.as_ref(), v, f.index)?;
+		}
+
+		match (v >= slippi::Version::ITEMS, &f.end) {
+			(true, Some(end)) => w.event(TraceKind::Event(Event::FrameEnd), |w| frame_end(w, end, v, f.index))?,
+			(true, None) => return Err(UnparseError::MissingField {
+				field: "frame end",
+				frame: Some(f.index),
+				since: slippi::Version::ITEMS,
+			}.into()),
+			(false, Some(_)) => return Err(UnparseError::VersionMismatch {
+				field: "frame end",
+				frame: Some(f.index),
+				since: slippi::Version::ITEMS,
+			}.into()),
+			(false, None) => (),
 		}
 	}
 	Ok(())
 }
 
-pub fn serialize<W: Write + Seek>(w: &mut W, game: &game::Game) -> Result<()> {
-	w.write_all(
-		&[0x7b, 0x55, 0x03, 0x72, 0x61, 0x77, 0x5b, 0x24, 0x55, 0x23, 0x6c])?;
-	w.write_u32::<BE>(0)?;
-	let raw_start = w.stream_position()?;
+fn frames_size<const N: usize>(frames: &[frame::Frame<N>], size: impl Fn(Event) -> usize) -> usize {
+	frames.iter().map(|f| {
+		let characters = f.ports.iter().map(|p| 1 + p.follower.is_some() as usize).sum::<usize>();
+		size(Event::FrameStart)
+			+ characters * (size(Event::FramePre) + size(Event::FramePost))
+			+ f.items.as_ref().map_or(0, |i| i.len()) * size(Event::Item)
+			+ size(Event::FrameEnd)
+	}).sum()
+}
 
-	let payload_sizes = payload_sizes(game);
-	w.write_u8(PAYLOADS_EVENT_CODE)?;
-	w.write_u8((payload_sizes.len() * 3 + 1).try_into().unwrap())?; // see note in `parse::payload_sizes`
-	for (event, size) in payload_sizes {
-		w.write_u8(event)?;
-		w.write_u16::<BE>(size)?;
-	}
+/// Number of bytes `serialize` will write for `game` (see `Game::unparse_size`).
+pub(crate) fn serialized_size(game: &game::Game) -> std::result::Result<usize, UnparseError> {
+	let payload_sizes = payload_sizes(&game.start, game.gecko_codes.as_ref());
+	// +1 byte for each event's code
+	let size = |event: Event| payload_sizes.iter()
+		.find(|(e, _)| *e == event as u8)
+		.map_or(0, |(_, size)| 1 + *size as usize);
 
-	let v = game.start.slippi.version;
-	game_start(w, &game.start, v)?;
+	let mut metadata = Vec::new();
+	ubjson::ser::from_map(&mut metadata, &game.metadata_raw)?;
+
+	let gecko_codes = game.gecko_codes.as_ref().map_or(0, |c|
+		(c.actual_size as usize).div_ceil(512) * (1 + 516));
+
+	Ok(11 + 4 // `raw` key & length
+		+ 2 + 3 * payload_sizes.len()
+		+ size(Event::GameStart)
+		+ gecko_codes
+		+ match &game.frames {
+			Frames::P1(f) => frames_size(f, size),
+			Frames::P2(f) => frames_size(f, size),
+			Frames::P3(f) => frames_size(f, size),
+			Frames::P4(f) => frames_size(f, size),
+		}
+		+ size(Event::GameEnd)
+		+ 11 + metadata.len() + 1 // `metadata` key & value
+		+ 1) // top-level closing brace
+}
+
+/// Serializes `game` into a new buffer, allocated up front (see `Game::unparse_size`).
+pub fn serialize_to_vec(game: &game::Game) -> std::result::Result<Vec<u8>, UnparseError> {
+	let mut buf = Vec::with_capacity(serialized_size(game)?);
+	serialize(&mut Cursor::new(&mut buf), game)?;
+	Ok(buf)
+}
 
-	if let Some(codes) = &game.gecko_codes {
+/// Writes the Event Payloads event, followed by Game Start and any Gecko codes.
+pub(crate) fn raw_header<W: Write>(w: &mut Counter<W>, start: &game::Start, codes: Option<&GeckoCodes>) -> Result<()> {
+	let payload_sizes = payload_sizes(start, codes);
+	w.event(TraceKind::Payloads, |w| {
+		w.write_u8(PAYLOADS_EVENT_CODE)?;
+		w.write_u8((payload_sizes.len() * 3 + 1).try_into().unwrap())?; // see note in `parse::payload_sizes`
+		for (event, size) in payload_sizes {
+			w.write_u8(event)?;
+			w.write_u16::<BE>(size)?;
+		}
+		Ok(())
+	})?;
+
+	w.event(TraceKind::Event(Event::GameStart), |w| game_start(w, start, start.slippi.version))?;
+
+	if let Some(codes) = codes {
 		gecko_codes(w, codes)?;
 	}
 
+	Ok(())
+}
+
+pub fn serialize<W: Write + Seek>(w: &mut W, game: &game::Game) -> std::result::Result<(), UnparseError> {
+	serialize_with_opts(w, game, None)
+}
+
+/// Like `serialize`, but with non-default options.
+pub fn serialize_with_opts<W: Write + Seek>(w: &mut W, game: &game::Game, opts: Option<&Opts>) -> std::result::Result<(), UnparseError> {
+	write_game(w, game, opts, false)?;
+	Ok(())
+}
+
+/// Does the work of `serialize_with_opts`, returning a trace of what was written if
+/// `trace` is set (see `unparse_trace`).
+fn write_game<W: Write + Seek>(w: &mut W, game: &game::Game, opts: Option<&Opts>, trace: bool) -> std::result::Result<Option<Vec<EventTrace>>, UnparseError> {
+	let opts = opts.copied().unwrap_or_default();
+	if !game.frames.is_empty() && game.frames.index(0) != game::FIRST_FRAME_INDEX {
+		return Err(UnparseError::FrameIndex {
+			frame: game.frames.index(0),
+			expected: game::FIRST_FRAME_INDEX,
+		});
+	}
+
+	let mut c = Counter::new(&mut *w);
+	c.trace = trace.then(Vec::new);
+	c.event(TraceKind::RawHeader, |c| raw_open(c, 0))?; // raw length filled in below
+	let raw_start = c.offset;
+
+	raw_header(&mut c, &game.start, game.gecko_codes.as_ref())?;
+	let v = game.start.slippi.version;
+	let ports: Vec<_> = game.start.players.iter().map(|p| p.port).collect();
+
 	match &game.frames {
-		Frames::P1(f) => frames(w, f, v)?,
-		Frames::P2(f) => frames(w, f, v)?,
-		Frames::P3(f) => frames(w, f, v)?,
-		Frames::P4(f) => frames(w, f, v)?,
+		Frames::P1(f) => frames(&mut c, f, &ports, v, &opts)?,
+		Frames::P2(f) => frames(&mut c, f, &ports, v, &opts)?,
+		Frames::P3(f) => frames(&mut c, f, &ports, v, &opts)?,
+		Frames::P4(f) => frames(&mut c, f, &ports, v, &opts)?,
 	};
 
-	game_end(w, &game.end, v)?;
+	c.event(TraceKind::Event(Event::GameEnd), |c| game_end(c, &game.end, v))?;
 
-	let raw_len = w.stream_position()? - raw_start;
+	let raw_len = c.offset - raw_start;
 
-	w.write_all(
-		&[0x55, 0x08, 0x6d, 0x65, 0x74, 0x61, 0x64, 0x61, 0x74, 0x61, 0x7b])?;
-	ubjson::ser::from_map(w, &game.metadata_raw)?;
-	w.write_all(&[0x7d])?; // closing brace for `metadata`
-	w.write_all(&[0x7d])?; // closing brace for top-level map
+	c.event(TraceKind::Metadata, |c| metadata(c, &game.metadata_raw))?;
+	let trace = c.trace;
 
 	w.seek(SeekFrom::Start(11))?;
 	w.write_u32::<BE>(raw_len as u32)?;
 
+	Ok(trace)
+}
+
+/// Writes the start of the UBJSON wrapper, up to & including the length of the `raw`
+/// element (0 while the replay is in progress).
+fn raw_open<W: Write>(w: &mut W, raw_len: u32) -> Result<()> {
+	// top-level opening brace, `raw` key & type ("{U\x03raw[$U#l")
+	w.write_all(&[0x7b, 0x55, 0x03, 0x72, 0x61, 0x77, 0x5b, 0x24, 0x55, 0x23, 0x6c])?;
+	w.write_u32::<BE>(raw_len)
+}
+
+/// Writes the `metadata` element, closing the UBJSON wrapper.
+fn metadata<W: Write>(w: &mut W, metadata: &serde_json::Map<String, serde_json::Value>) -> Result<()> {
+	w.write_all(&[0x55, 0x08, 0x6d, 0x65, 0x74, 0x61, 0x64, 0x61, 0x74, 0x61, 0x7b])?;
+	ubjson::ser::from_map(w, metadata)?;
+	w.write_all(&[0x7d])?; // closing brace for `metadata`
+	w.write_all(&[0x7d]) // closing brace for top-level map
+}
+
+/// Where a replay segment (see `serialize_segment`) picks up from the one before it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Resume {
+	/// Index of the frame after the last one written.
+	pub index: i32,
+	/// `latest_finalized_frame` of the last Frame Bookend written (v3.7+).
+	pub latest_finalized_frame: Option<i32>,
+}
+
+/// Checks that `f` can follow a segment that ended at `resume`: it's the next frame, or
+/// a rollback to an earlier one that hadn't been finalized yet, & it doesn't un-finalize
+/// any frames.
+fn check_resume<const N: usize>(f: &frame::Frame<N>, resume: Resume) -> std::result::Result<(), UnparseError> {
+	let lowest = resume.latest_finalized_frame.map_or(resume.index, |l| l + 1);
+	if f.index > resume.index || f.index < lowest {
+		return Err(UnparseError::FrameIndex { frame: f.index, expected: resume.index });
+	}
+	let latest = f.end.as_ref().and_then(|e| e.latest_finalized_frame);
+	if let (Some(latest), Some(prior)) = (latest, resume.latest_finalized_frame) {
+		if latest < prior {
+			return Err(UnparseError::FrameIndex { frame: latest, expected: prior });
+		}
+	}
 	Ok(())
 }
+
+/// Writes one segment's `frames`, preceded by everything before the frames if `first`.
+fn frames_segment<W: Write, const N: usize>(w: &mut Counter<W>, game: &game::Game, frames: &[frame::Frame<N>], resume: Resume, first: bool, opts: &Opts) -> std::result::Result<Resume, UnparseError> {
+	if let Some(f) = frames.first() {
+		check_resume(f, resume)?;
+	}
+	if first {
+		raw_open(w, 0)?;
+		raw_header(w, &game.start, game.gecko_codes.as_ref())?;
+	}
+	let ports: Vec<_> = game.start.players.iter().map(|p| p.port).collect();
+	self::frames(w, frames, &ports, game.start.slippi.version, opts)?;
+	Ok(frames.last().map_or(resume, |f| Resume {
+		index: f.index + 1,
+		latest_finalized_frame: f.end.as_ref().and_then(|e| e.latest_finalized_frame),
+	}))
+}
+
+/// Writes `game`'s frames in `range` as one segment of a replay split across several
+/// writes (e.g. a long capture split across files), such that the segments, concatenated
+/// in order, make up the whole replay.
+///
+/// The first segment (`resume` is `None`) must start at the first frame, and also writes
+/// everything before the frames. Later ones pick up from the `Resume` returned by the
+/// segment before. A segment that doesn't continue from there fails with
+/// `UnparseError::FrameIndex`, without writing anything. The segment ending at the last frame also writes Game End & the metadata. Since the
+/// length of the raw event stream isn't known up front, it's written as 0, as for an
+/// in-progress replay.
+pub fn serialize_segment<W: Write>(w: &mut W, game: &game::Game, range: Range<usize>, resume: Option<Resume>, opts: Option<&Opts>) -> std::result::Result<Resume, UnparseError> {
+	let opts = opts.copied().unwrap_or_default();
+	let last = range.end == game.frames.len();
+	let mut w = Counter::new(w);
+
+	let first = resume.is_none();
+	let resume = resume.unwrap_or(Resume { index: game::FIRST_FRAME_INDEX, latest_finalized_frame: None });
+	let resume = match &game.frames {
+		Frames::P1(f) => frames_segment(&mut w, game, &f[range], resume, first, &opts)?,
+		Frames::P2(f) => frames_segment(&mut w, game, &f[range], resume, first, &opts)?,
+		Frames::P3(f) => frames_segment(&mut w, game, &f[range], resume, first, &opts)?,
+		Frames::P4(f) => frames_segment(&mut w, game, &f[range], resume, first, &opts)?,
+	};
+
+	if last {
+		game_end(&mut w, &game.end, game.start.slippi.version)?;
+		metadata(&mut w, &game.metadata_raw)?;
+	}
+
+	Ok(resume)
+}
+
+/// What a span of a serialized replay holds (see `unparse_trace`).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TraceKind {
+	/// start of the UBJSON wrapper, up to & including the raw length
+	RawHeader,
+	/// the Event Payloads event
+	Payloads,
+	Event(Event),
+	/// a Message Splitter event (wrapping part of the Gecko codes)
+	MessageSplitter,
+	/// the metadata & closing braces, after the raw event data
+	Metadata,
+}
+
+/// Where `serialize` wrote something, as a byte offset & size.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct EventTrace {
+	pub offset: usize,
+	pub size: usize,
+	pub kind: TraceKind,
+}
+
+/// Counts the bytes written through it, and if `trace` is set, records the span of each
+/// event (or other part of a replay) written with `event`.
+pub(crate) struct Counter<W> {
+	w: W,
+	offset: usize,
+	trace: Option<Vec<EventTrace>>,
+}
+
+impl<W: Write> Counter<W> {
+	pub(crate) fn new(w: W) -> Self {
+		Counter { w: w, offset: 0, trace: None }
+	}
+
+	/// Writes one event with `write`, recording where it went.
+	fn event(&mut self, kind: TraceKind, write: impl FnOnce(&mut Self) -> Result<()>) -> Result<()> {
+		let offset = self.offset;
+		write(self)?;
+		if let Some(trace) = &mut self.trace {
+			trace.push(EventTrace { offset: offset, size: self.offset - offset, kind: kind });
+		}
+		Ok(())
+	}
+}
+
+impl<W: Write> Write for Counter<W> {
+	fn write(&mut self, buf: &[u8]) -> Result<usize> {
+		let n = self.w.write(buf)?;
+		self.offset += n;
+		Ok(n)
+	}
+
+	fn flush(&mut self) -> Result<()> {
+		self.w.flush()
+	}
+}
+
+/// Like `serialize`, but also returns the number of bytes written, and the offset & size
+/// of each event (plus the wrapper around them), in order, as the serializer wrote them.
+/// For finding where a serialized replay diverges from the original, e.g. when it doesn't
+/// round-trip.
+pub fn unparse_trace<W: Write>(w: &mut W, game: &game::Game) -> std::result::Result<(usize, Vec<EventTrace>), UnparseError> {
+	let mut buf = Cursor::new(Vec::new());
+	let trace = write_game(&mut buf, game, None, true)?.unwrap();
+	let bytes = buf.into_inner();
+	w.write_all(&bytes)?;
+	Ok((bytes.len(), trace))
+}
+
+/// 64-bit FNV-1a, which unlike `DefaultHasher` is stable across Rust releases.
+struct Fnv(u64);
+
+impl Write for Fnv {
+	fn write(&mut self, buf: &[u8]) -> Result<usize> {
+		for b in buf {
+			self.0 = (self.0 ^ *b as u64).wrapping_mul(0x100000001b3);
+		}
+		Ok(buf.len())
+	}
+
+	fn flush(&mut self) -> Result<()> {
+		Ok(())
+	}
+}
+
+fn finalized_frames<'a, const N: usize>(frames: &'a [frame::Frame<N>], finalized: &'a [bool]) -> impl Iterator<Item = &'a frame::Frame<N>> {
+	frames.iter().zip(finalized).filter(|(_, f)| **f).map(|(f, _)| f)
+}
+
+/// Hash of the serialized Game Start event and finalized frames (see `Game::content_hash`).
+pub(crate) fn content_hash(game: &game::Game) -> Result<u64> {
+	let mut h = Counter::new(Fnv(0xcbf29ce484222325));
+	let v = game.start.slippi.version;
+	game_start(&mut h, &game.start, v)?;
+	let ports: Vec<_> = game.start.players.iter().map(|p| p.port).collect();
+	let finalized = game.frames.finalized();
+	match &game.frames {
+		Frames::P1(f) => frames(&mut h, finalized_frames(f, &finalized), &ports, v, &Opts::default())?,
+		Frames::P2(f) => frames(&mut h, finalized_frames(f, &finalized), &ports, v, &Opts::default())?,
+		Frames::P3(f) => frames(&mut h, finalized_frames(f, &finalized), &ports, v, &Opts::default())?,
+		Frames::P4(f) => frames(&mut h, finalized_frames(f, &finalized), &ports, v, &Opts::default())?,
+	};
+	game_end(&mut h, &game.end, v)?;
+	Ok(h.w.0)
+}
+
+/// Size of the Game Start payload in version `v`.
+pub(crate) fn game_start_size(v: slippi::Version) -> usize {
+	use slippi::Version as V;
+	match v {
+		v if v >= V::MATCH_INFO => 760,
+		v if v >= V::LANGUAGE => 701,
+		v if v >= V::SUID => 700,
+		v if v >= V::NETPLAY_NAMES => 584,
+		v if v >= ver(3, 7) => 420,
+		v if v >= ver(2, 0) => 418,
+		v if v >= V::PAL => 417,
+		v if v >= V::NAME_TAGS => 416,
+		v if v >= V::UCF => 352,
+		_ => 320,
+	}
+}
+
+/// Streams events from the parser straight to `w`, re-encoded for `target`.
+struct Transcoder<'a, W: Write> {
+	w: &'a mut W,
+	target: slippi::Version,
+	/// held back until we know whether there are Gecko codes, since those
+	/// are needed for the payload sizes table
+	start: Option<game::Start>,
+	gecko_codes: Option<GeckoCodes>,
+}
+
+impl<W: Write> Transcoder<'_, W> {
+	fn flush_header(&mut self) -> Result<()> {
+		if let Some(start) = self.start.take() {
+			raw_header(&mut Counter::new(&mut *self.w), &start, self.gecko_codes.as_ref())?;
+		}
+		Ok(())
+	}
+}
+
+impl<W: Write> Handlers for Transcoder<'_, W> {
+	fn gecko_codes(&mut self, codes: &[u8], actual_size: u16) -> Result<()> {
+		if self.target >= ver(3, 3) {
+			self.gecko_codes = Some(GeckoCodes {
+				bytes: codes.to_vec(),
+				actual_size: actual_size,
+			});
+		}
+		Ok(())
+	}
+
+	fn game_start(&mut self, mut s: game::Start) -> Result<()> {
+		if self.target > s.slippi.version {
+			return Err(err!("can't transcode v{} replay to newer version v{}", s.slippi.version, self.target));
+		}
+		let v = self.target;
+		s.slippi.version = v;
+		s.raw_bytes.truncate(game_start_size(v));
+		if v < slippi::Version::PAL {
+			s.is_pal = None;
+		}
+		if v < ver(2, 0) {
+			s.is_frozen_ps = None;
+		}
+		if v < ver(3, 7) {
+			s.scene = None;
+		}
+		if v < slippi::Version::LANGUAGE {
+			s.language = None;
+		}
+		if v < slippi::Version::MATCH_INFO {
+			s.r#match = None;
+		}
+		for p in &mut s.players {
+			if v < slippi::Version::UCF {
+				p.ucf = None;
+			}
+			if v < slippi::Version::NAME_TAGS {
+				p.name_tag = None;
+			}
+			if v < slippi::Version::NETPLAY_NAMES {
+				p.netplay = None;
+			} else if v < slippi::Version::SUID {
+				if let Some(netplay) = &mut p.netplay {
+					netplay.suid = None;
+				}
+			}
+		}
+		self.start = Some(s);
+		Ok(())
+	}
+
+	fn game_end(&mut self, e: game::End) -> Result<()> {
+		self.flush_header()?;
+		game_end(self.w, &e, self.target)
+	}
+
+	fn metadata(&mut self, metadata: serde_json::Map<String, serde_json::Value>) -> Result<()> {
+		self.w.write_all(
+			&[0x55, 0x08, 0x6d, 0x65, 0x74, 0x61, 0x64, 0x61, 0x74, 0x61, 0x7b])?;
+		ubjson::ser::from_map(self.w, &metadata)?;
+		self.w.write_all(&[0x7d])?; // closing brace for `metadata`
+		self.w.write_all(&[0x7d]) // closing brace for top-level map
+	}
+
+	fn frame_start(&mut self, s: FrameEvent<FrameId, frame::Start>) -> Result<()> {
+		self.flush_header()?;
+		match self.target >= slippi::Version::ROLLBACK {
+			true => frame_start(self.w, &s.event, self.target, s.id.index),
+			_ => Ok(()),
+		}
+	}
+
+	fn frame_pre(&mut self, p: FrameEvent<PortId, frame::Pre>) -> Result<()> {
+		self.flush_header()?;
+		frame_pre(self.w, &p.event, self.target, p.id, None)
+	}
+
+	fn frame_post(&mut self, p: FrameEvent<PortId, frame::Post>) -> Result<()> {
+		self.flush_header()?;
+		frame_post(self.w, &p.event, self.target, p.id)
+	}
+
+	fn frame_end(&mut self, e: FrameEvent<FrameId, frame::End>) -> Result<()> {
+		self.flush_header()?;
+		match self.target >= slippi::Version::ITEMS {
+			true => frame_end(self.w, &e.event, self.target, e.id.index),
+			_ => Ok(()),
+		}
+	}
+
+	fn item(&mut self, i: FrameEvent<FrameId, item::Item>) -> Result<()> {
+		self.flush_header()?;
+		match self.target >= slippi::Version::ITEMS {
+			true => item(self.w, &i.event, self.target, i.id.index),
+			_ => Ok(()),
+		}
+	}
+}
+
+/// Re-encodes the replay in `r` as version `target`, writing it to `w` as it's parsed.
+/// Fields added after `target` are dropped, as are events it doesn't support.
+/// Fails if `target` is newer than the source replay, which lacks the data to fill in.
+///
+/// Since `w` needn't be seekable, the raw length is written as 0, as for an
+/// in-progress replay.
+pub fn transcode<R: Read, W: Write>(r: &mut R, w: &mut W, target: slippi::Version) -> Result<()> {
+	w.write_all(
+		&[0x7b, 0x55, 0x03, 0x72, 0x61, 0x77, 0x5b, 0x24, 0x55, 0x23, 0x6c])?;
+	w.write_u32::<BE>(0)?;
+	let mut transcoder = Transcoder {
+		w: w,
+		target: target,
+		start: None,
+		gecko_codes: None,
+	};
+	de::deserialize(r, &mut transcoder, None)
+}
@@ -0,0 +1,45 @@
+use serde::Serialize;
+
+use crate::model::{
+	game::{Game, PortFramesOpts},
+	primitives::Port,
+};
+
+/// Time spent in the air & on the ground by a single port, in frames.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct AerialStats {
+	pub airborne_frames: usize,
+	pub grounded_frames: usize,
+	/// frames without an `airborne` value (all of them before v2.0)
+	pub unknown_frames: usize,
+	/// number of times the remaining jumps went down
+	pub jumps: usize,
+}
+
+/// Airborne & grounded frame totals for the player in `port` (leader only), from
+/// `Post::airborne`, which together cover every finalized frame of the game.
+///
+/// Jumps are counted from `Post::jumps`, so they include leaving the ground without
+/// jumping (e.g. walking off an edge), which also uses up the grounded jump. Both are
+/// only available since v2.0; for older replays, every frame is unknown & no jumps are
+/// counted.
+pub fn aerial_time(game: &Game, port: Port) -> AerialStats {
+	let opts = Some(PortFramesOpts { merge_followers: true, finalized_only: true });
+	let mut stats = AerialStats::default();
+	let mut prev_jumps: Option<u8> = None;
+
+	for f in game.port_frames(port, opts).into_iter().flatten() {
+		let post = &f.data.post;
+		match post.airborne {
+			Some(true) => stats.airborne_frames += 1,
+			Some(false) => stats.grounded_frames += 1,
+			None => stats.unknown_frames += 1,
+		}
+		if prev_jumps.zip(post.jumps).is_some_and(|(prev, cur)| cur < prev) {
+			stats.jumps += 1;
+		}
+		prev_jumps = post.jumps;
+	}
+
+	stats
+}
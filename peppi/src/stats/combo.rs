@@ -0,0 +1,144 @@
+use serde::Serialize;
+
+use crate::model::{
+	frame::Post,
+	game::{Game, PortFramesOpts},
+	primitives::Port,
+};
+
+/// Frames the victim must spend actionable before a combo is considered over.
+pub(crate) const COMBO_RESET_FRAMES: usize = 45;
+
+/// A string of hits on one opponent, without them getting a chance to act in between.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+pub struct Combo {
+	pub victim: Port,
+	/// frame index of the first hit
+	pub start: i32,
+	/// frame index of the last frame on which the victim was hit, held, knocked down
+	/// or teching (or the frame on which they died)
+	pub end: i32,
+	/// number of hits (i.e. increases in the victim's percent)
+	pub hits: usize,
+	/// victim's percent before the first hit
+	pub start_percent: f32,
+	/// victim's percent after the last hit
+	pub end_percent: f32,
+	/// whether the combo ended with the victim losing a stock
+	pub killed: bool,
+}
+
+/// Options for `combos`.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Opts {
+	/// End combos when the game resets the attacker's `combo_count` (Popo's, for Ice
+	/// Climbers), rather than after the victim spends 45 frames actionable. Unreliable
+	/// before v2.0, where the game's counter rarely moves past 1.
+	pub use_game_counter: bool,
+}
+
+/// Whether the victim is unable to act because of something the attacker did.
+pub(crate) fn is_punished(post: &Post) -> bool {
+	post.is_damaged() || post.is_grabbed() || post.is_downed() || post.is_teching()
+}
+
+fn victim_combos(game: &Game, port: Port, victim: Port, opts: Opts) -> Vec<Combo> {
+	let frames_opts = Some(PortFramesOpts { merge_followers: true, finalized_only: true });
+	let attacker_frames = game.port_frames(port, frames_opts).into_iter().flatten();
+	let victim_frames = game.port_frames(victim, frames_opts).into_iter().flatten();
+
+	let mut combos = Vec::new();
+	let mut current: Option<Combo> = None;
+	let mut actionable = 0;
+	let mut prev_damage: Option<f32> = None;
+	let mut prev_count = 0;
+
+	for (a, f) in attacker_frames.zip(victim_frames) {
+		let post = &f.data.post;
+		let prev = prev_damage.unwrap_or(post.damage);
+		prev_damage = Some(post.damage);
+		let count = a.data.post.combo_count;
+		let is_hit = post.damage > prev && post.last_hit_by == Some(port);
+
+		if opts.use_game_counter && (count < prev_count || (is_hit && count <= 1)) {
+			combos.extend(current.take());
+		}
+		prev_count = count;
+
+		if post.is_dead() {
+			if let Some(mut combo) = current.take() {
+				combo.end = f.index;
+				combo.killed = true;
+				combos.push(combo);
+			}
+			continue;
+		}
+
+		if is_hit {
+			let combo = current.get_or_insert(Combo {
+				victim: victim,
+				start: f.index,
+				end: f.index,
+				hits: 0,
+				start_percent: prev,
+				end_percent: prev,
+				killed: false,
+			});
+			combo.hits += 1;
+			combo.end_percent = post.damage;
+			combo.end = f.index;
+			actionable = 0;
+		} else if let Some(combo) = &mut current {
+			if is_punished(post) {
+				combo.end = f.index;
+				actionable = 0;
+			} else if !opts.use_game_counter {
+				actionable += 1;
+				if actionable > COMBO_RESET_FRAMES {
+					combos.extend(current.take());
+				}
+			}
+		}
+	}
+
+	combos.extend(current);
+	combos
+}
+
+/// Combos performed by the player in `port` on each opponent, ordered by start frame.
+///
+/// Heuristic (much like Slippi's own): a combo starts when the player hits an opponent, and
+/// continues until that opponent has spent 45 consecutive frames not being hit, held, knocked
+/// down or teching, or until they die. Only the leader (not Nana) is considered as a victim.
+/// See `Opts::use_game_counter` to split combos the way the game does instead.
+pub fn combos(game: &Game, port: Port, opts: Option<Opts>) -> Vec<Combo> {
+	let opts = opts.unwrap_or_default();
+	let mut combos: Vec<_> = game.start.players.iter()
+		.filter(|p| p.port != port)
+		.flat_map(|p| victim_combos(game, port, p.port, opts))
+		.collect();
+	combos.sort_by_key(|c| c.start);
+	combos
+}
+
+/// Frame ranges (inclusive) for cutting video of each of the combos performed by the player in
+/// `port`, padded by `pad_before` & `pad_after` frames. Ranges are clamped to the game's first &
+/// last frames, and merged where they overlap.
+pub fn clip_ranges(game: &Game, port: Port, pad_before: i32, pad_after: i32) -> Vec<(i32, i32)> {
+	if game.frames.is_empty() {
+		return vec![];
+	}
+	let first = game.frames.index(0);
+	let last = game.frames.index(game.frames.len() - 1);
+
+	let mut ranges: Vec<(i32, i32)> = Vec::new();
+	for c in combos(game, port, None) {
+		let start = (c.start - pad_before).max(first);
+		let end = (c.end + pad_after).min(last);
+		match ranges.last_mut() {
+			Some(prev) if start <= prev.1 => prev.1 = prev.1.max(end),
+			_ => ranges.push((start, end)),
+		}
+	}
+	ranges
+}
@@ -0,0 +1,182 @@
+use serde::Serialize;
+
+use crate::model::{
+	enums::{action_state::{Common, State}, attack::Attack},
+	frame::Post,
+	game::Game,
+	primitives::Port,
+};
+
+/// Frames the victim must spend after regaining control before a conversion is over
+/// (slippi-js's `PUNISH_RESET_FRAMES`).
+pub const RESET_FRAMES: usize = 45;
+
+/// A move that hit during a conversion, counting each hit of a multi-hit move.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+pub struct Move {
+	/// frame index of the first hit
+	pub index: i32,
+	/// the attacker's `last_attack_landed` on the first hit
+	pub attack: Option<Attack>,
+	pub hits: usize,
+	pub damage: f32,
+}
+
+/// A punish, from the opening hit or grab until the victim escapes or dies, as in
+/// slippi-js's `ConversionType`.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Conversion {
+	pub victim: Port,
+	/// frame index of the opening
+	pub start: i32,
+	/// frame index on which the conversion was over, or `None` if the game ended first
+	pub end: Option<i32>,
+	/// victim's percent before the opening
+	pub start_percent: f32,
+	/// victim's percent at the end (before dying, if they did)
+	pub end_percent: f32,
+	pub moves: Vec<Move>,
+	/// whether the victim lost a stock
+	pub killed: bool,
+}
+
+impl Conversion {
+	/// The move that started the conversion, if it wasn't a grab that never hit.
+	pub fn opening_move(&self) -> Option<&Move> {
+		self.moves.first()
+	}
+
+	/// Damage dealt to the victim over the course of the conversion.
+	pub fn damage(&self) -> f32 {
+		self.end_percent - self.start_percent
+	}
+
+	/// Number of frames from the opening to the end, if the conversion ended.
+	pub fn duration(&self) -> Option<i32> {
+		self.end.map(|end| end - self.start)
+	}
+}
+
+/// slippi-js's `isInControl`: standing, walking, dashing, crouching, using a ground attack
+/// or grabbing.
+fn is_in_control(post: &Post) -> bool {
+	matches!(post.state, State::Common(s)
+		if (Common::WAIT.0 ..= Common::KNEE_BEND.0).contains(&s.0)
+		|| (Common::SQUAT.0 ..= Common::SQUAT_RV.0).contains(&s.0)
+		|| (Common::ATTACK_11.0 ..= Common::ATTACK_LW_4.0).contains(&s.0)
+		|| s == Common::CATCH)
+}
+
+/// slippi-js's `isDamaged || isGrabbed` (unlike for `combo::combos`, being downed or
+/// teching doesn't count).
+fn is_punished(post: &Post) -> bool {
+	post.is_damaged() || post.is_grabbed()
+}
+
+fn victim_conversions(game: &Game, attacker_idx: usize, victim_idx: usize) -> Vec<Conversion> {
+	let victim_port = game.start.players[victim_idx].port;
+	let finalized = game.frames.finalized();
+	let post = |n: usize, idx: usize| &game.frames.port_data(n, idx).leader.post;
+	let frames: Vec<_> = (0 .. game.frames.len()).filter(|n| finalized[*n])
+		.map(|n| (game.frames.index(n), post(n, attacker_idx), post(n, victim_idx)))
+		.collect();
+
+	let mut conversions = Vec::new();
+	let mut current: Option<Conversion> = None;
+	let mut reset = 0;
+	// attacker's action state before its last hit, while it's still in that move
+	let mut last_hit_state: Option<State> = None;
+
+	for w in frames.windows(2) {
+		let ((_, prev_attacker, prev_victim), (index, attacker, victim)) = (w[0], w[1]);
+
+		let state_restarted = attacker.state_age.zip(prev_attacker.state_age)
+			.is_some_and(|(cur, prev)| cur < prev);
+		if last_hit_state != Some(attacker.state) || state_restarted {
+			last_hit_state = None;
+		}
+
+		let damage = match victim.is_dead() {
+			true => 0.0,
+			_ => victim.damage - prev_victim.damage,
+		};
+		if is_punished(victim) {
+			let conversion = current.get_or_insert(Conversion {
+				victim: victim_port,
+				start: index,
+				end: None,
+				start_percent: prev_victim.damage,
+				end_percent: victim.damage,
+				moves: vec![],
+				killed: false,
+			});
+			if damage > 0.0 {
+				if last_hit_state.is_none() {
+					conversion.moves.push(Move {
+						index: index,
+						attack: attacker.last_attack_landed,
+						hits: 0,
+						damage: 0.0,
+					});
+				}
+				if let Some(m) = conversion.moves.last_mut() {
+					m.hits += 1;
+					m.damage += damage;
+				}
+				last_hit_state = Some(prev_attacker.state);
+			}
+		}
+
+		let conversion = match &mut current {
+			Some(c) => c,
+			None => continue,
+		};
+		let lost_stock = victim.stocks < prev_victim.stocks;
+		if !lost_stock {
+			conversion.end_percent = victim.damage;
+		}
+		if is_punished(victim) {
+			reset = 0;
+		}
+		if reset > 0 || is_in_control(victim) {
+			reset += 1;
+		}
+
+		if lost_stock || reset > RESET_FRAMES {
+			conversion.killed = lost_stock;
+			conversion.end = Some(index);
+			conversion.end_percent = prev_victim.damage;
+			conversions.extend(current.take());
+			reset = 0;
+			last_hit_state = None;
+		}
+	}
+
+	conversions.extend(current);
+	conversions
+}
+
+/// Conversions by the player in `port` (leader only) against each opponent, ordered by
+/// start frame, following slippi-js's `ConversionComputer`.
+///
+/// A conversion starts when an opponent is damaged or grabbed, & lasts until they lose
+/// a stock or spend more than `RESET_FRAMES` frames in control (standing, walking,
+/// dashing, crouching, using a ground attack or grabbing) without being hit or grabbed
+/// again. Once the counter starts it keeps counting while the victim is out of control,
+/// so e.g. falling or teching out of a punish still ends it. Each increase in the
+/// victim's percent is a hit; consecutive hits are counted as one `Move` until the
+/// attacker changes action state or restarts the same one. As in slippi-js, the
+/// damage isn't checked against `Post::last_hit_by`, so in doubles another player's
+/// hits on the victim are counted too.
+pub fn conversions(game: &Game, port: Port) -> Vec<Conversion> {
+	let attacker_idx = match game.port_index(port) {
+		Some(idx) => idx,
+		None => return vec![],
+	};
+	let mut conversions: Vec<_> = (0 .. game.start.players.len())
+		.filter(|idx| *idx != attacker_idx)
+		.flat_map(|idx| victim_conversions(game, attacker_idx, idx))
+		.collect();
+	conversions.sort_by_key(|c| c.start);
+	conversions
+}
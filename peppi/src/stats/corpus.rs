@@ -0,0 +1,106 @@
+use std::{
+	collections::HashMap,
+	fs, io,
+	path::{Path, PathBuf},
+	time::Duration,
+};
+
+use crate::model::{
+	enums::{character::External, stage::Stage},
+	game::Game,
+};
+
+/// Wins & losses.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Record {
+	pub wins: usize,
+	pub losses: usize,
+}
+
+/// Aggregate statistics over many games.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct CorpusSummary {
+	/// number of games summarized
+	pub games: usize,
+	/// files that couldn't be read or parsed (only from `summarize_dir`)
+	pub skipped: Vec<PathBuf>,
+	/// record of each (starting) character, over games with a winner (see `Game::winners`)
+	pub characters: HashMap<External, Record>,
+	/// number of singles games between each pair of characters, lowest id first
+	pub matchups: HashMap<(External, External), usize>,
+	/// number of games on each stage
+	pub stages: HashMap<Stage, usize>,
+	/// total number of frames in all games (not counting rolled-back frames)
+	pub frames: usize,
+	/// total length of all games (`frames` at 60fps)
+	pub playtime: Duration,
+}
+
+impl CorpusSummary {
+	/// Adds `game` to the summary.
+	pub fn add(&mut self, game: &Game) {
+		self.games += 1;
+		*self.stages.entry(game.stage()).or_default() += 1;
+
+		self.frames += game.logical_frame_count();
+		self.playtime = Duration::from_secs(self.frames as u64) / 60;
+
+		let winners = game.winners();
+		if !winners.is_empty() {
+			for (port, character) in game.characters() {
+				let record = self.characters.entry(character).or_default();
+				match winners.contains(&port) {
+					true => record.wins += 1,
+					_ => record.losses += 1,
+				}
+			}
+		}
+
+		if let [(_, a), (_, b)] = game.characters()[..] {
+			let matchup = match a.0 <= b.0 {
+				true => (a, b),
+				_ => (b, a),
+			};
+			*self.matchups.entry(matchup).or_default() += 1;
+		}
+	}
+}
+
+/// Summarizes `games`.
+pub fn summarize<'a>(games: impl IntoIterator<Item = &'a Game>) -> CorpusSummary {
+	let mut summary = CorpusSummary::default();
+	for game in games {
+		summary.add(game);
+	}
+	summary
+}
+
+/// Summarizes every `.slp` file directly inside the directory `path` (in name order, not
+/// recursively). Files that fail to parse are listed in `CorpusSummary::skipped`; only
+/// failing to list the directory itself is an error.
+pub fn summarize_dir<P: AsRef<Path>>(path: P) -> io::Result<CorpusSummary> {
+	let mut paths = Vec::new();
+	for entry in fs::read_dir(path)? {
+		let path = entry?.path();
+		if path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("slp")) {
+			paths.push(path);
+		}
+	}
+	paths.sort();
+
+	let mut summary = CorpusSummary::default();
+	for path in paths {
+		let game = fs::File::open(&path)
+			.map(io::BufReader::new)
+			.map_err(|e| e.to_string())
+			.and_then(|mut r| crate::game(&mut r, None, None).map_err(|e| e.to_string()));
+		match game {
+			Ok(game) => summary.add(&game),
+			Err(e) => {
+				log::warn!("skipping {}: {}", path.display(), e);
+				summary.skipped.push(path);
+			},
+		}
+	}
+	Ok(summary)
+}
@@ -0,0 +1,59 @@
+use std::collections::HashMap;
+
+use serde::Serialize;
+
+use crate::model::{
+	game::Game,
+	primitives::{Direction, Port},
+};
+
+use super::combo;
+
+/// An opening hit that landed on the back of the opponent.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+pub struct Crossup {
+	pub victim: Port,
+	/// frame index of the opening hit
+	pub index: i32,
+	/// direction the victim was facing
+	pub direction: Direction,
+	/// attacker's x position minus the victim's, on the frame before the hit
+	pub offset: f32,
+}
+
+/// Openings (the first hit of each combo, see `combo::combos`) by the player in `port`
+/// that were crossups, ordered by frame index. Leaders only.
+///
+/// We compare `Post` positions & directions on the frame before the hit, since being hit
+/// usually turns the victim around to face the attacker. A hit is a crossup if the
+/// attacker was strictly behind the victim: to their left while they faced right, or to
+/// their right while they faced left. Vertical position is ignored.
+pub fn crossups(game: &Game, port: Port) -> Vec<Crossup> {
+	let port_idx = match game.port_index(port) {
+		Some(idx) => idx,
+		None => return vec![],
+	};
+	let finalized = game.frames.finalized();
+	let frames: HashMap<i32, usize> = (0 .. game.frames.len())
+		.filter(|n| finalized[*n])
+		.map(|n| (game.frames.index(n), n))
+		.collect();
+	let post = |n: usize, idx: usize| &game.frames.port_data(n, idx).leader.post;
+
+	combo::combos(game, port, None).into_iter().filter_map(|c| {
+		let n = *frames.get(&(c.start - 1))?;
+		let victim_idx = game.port_index(c.victim)?;
+		let (attacker, victim) = (post(n, port_idx), post(n, victim_idx));
+		let offset = attacker.position.x - victim.position.x;
+		let is_behind = match victim.direction {
+			Direction::Right => offset < 0.0,
+			Direction::Left => offset > 0.0,
+		};
+		is_behind.then_some(Crossup {
+			victim: c.victim,
+			index: c.start,
+			direction: victim.direction,
+			offset: offset,
+		})
+	}).collect()
+}
@@ -0,0 +1,79 @@
+use serde::Serialize;
+
+use crate::model::{
+	enums::{
+		action_state::{Common, State},
+		stage::BlastZones,
+	},
+	game::{Game, PortFramesOpts},
+	primitives::{Port, Position},
+};
+
+/// Which blast zone a character was KO'd through.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+pub enum BlastZone {
+	Left,
+	Right,
+	Bottom,
+	/// regular KO off the top
+	Top,
+	/// off the top, flying into the background ("star KO")
+	TopStar,
+	/// off the top, hitting the camera ("screen KO")
+	TopScreen,
+}
+
+/// The blast zone nearest to `pos`, which may be slightly inside it
+/// (characters are KO'd on the frame they cross it).
+fn nearest(pos: Position, zones: BlastZones) -> BlastZone {
+	[
+		(pos.x - zones.left, BlastZone::Left),
+		(zones.right - pos.x, BlastZone::Right),
+		(zones.top - pos.y, BlastZone::Top),
+		(pos.y - zones.bottom, BlastZone::Bottom),
+	].into_iter()
+		.min_by(|a, b| a.0.total_cmp(&b.0))
+		.map(|(_, zone)| zone)
+		.unwrap()
+}
+
+fn classify(state: Common, prev_position: Position, zones: Option<BlastZones>) -> BlastZone {
+	match state {
+		Common::DEAD_UP_STAR | Common::DEAD_UP_STAR_ICE => BlastZone::TopStar,
+		s if s.0 >= Common::DEAD_UP_FALL.0 => BlastZone::TopScreen,
+		s => match zones {
+			Some(zones) => nearest(prev_position, zones),
+			None => match s {
+				Common::DEAD_LEFT => BlastZone::Left,
+				Common::DEAD_RIGHT => BlastZone::Right,
+				Common::DEAD_UP => BlastZone::Top,
+				_ => BlastZone::Bottom,
+			},
+		},
+	}
+}
+
+/// Frame index and blast zone for each death of the player in `port`.
+///
+/// Deaths are detected by entry into one of the `DEAD_*` action states.
+/// Star & screen KOs are identified by their specific action states. Otherwise
+/// we use the blast zone closest to the character's position on the preceding
+/// frame (see `Stage::blast_zones`), or the action state for unknown stages.
+pub fn death_directions(game: &Game, port: Port) -> Vec<(i32, BlastZone)> {
+	let zones = game.start.stage.blast_zones();
+	let mut deaths = Vec::new();
+	let mut prev: Option<(bool, Position)> = None;
+
+	let opts = Some(PortFramesOpts { merge_followers: true, finalized_only: true });
+	for f in game.port_frames(port, opts).into_iter().flatten() {
+		let post = &f.data.post;
+		if let (State::Common(state), Some((false, prev_position))) = (post.state, prev) {
+			if post.is_dead() {
+				deaths.push((f.index, classify(state, prev_position, zones)));
+			}
+		}
+		prev = Some((post.is_dead(), post.position));
+	}
+
+	deaths
+}
@@ -0,0 +1,127 @@
+use serde::Serialize;
+
+use crate::{
+	model::{
+		enums::stage::BlastZones,
+		game::{Game, PortFramesOpts},
+		primitives::{Port, Position},
+	},
+	stats::sdi::in_hitlag,
+};
+
+/// Most (in degrees) that DI can rotate a launch by, with the stick held perpendicular to it.
+pub const MAX_DI_ANGLE: f32 = 18.0;
+
+/// Steps over the range of possible DI angles when finding the best & worst ones.
+const SAMPLES: usize = 36;
+
+/// How a player DI'd a single launch.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+pub struct DiEvent {
+	/// frame index of launch, i.e. the first frame after hitlag
+	pub index: i32,
+	/// where the player was launched from
+	pub position: Position,
+	/// (processed) joystick on the frame of launch
+	pub joystick: Position,
+	/// angle the hit would have sent the player at without DI, in degrees counterclockwise
+	/// from right (estimated, see `di`)
+	pub launch_angle: f32,
+	/// how far DI rotated the launch, in degrees (positive is counterclockwise)
+	pub rotation: f32,
+	/// from 0 (the worst possible DI for survival) to 1 (the best), or `None` if the stage's
+	/// blast zones aren't known or DI couldn't make a difference
+	pub score: Option<f32>,
+}
+
+fn direction(angle: f32) -> (f32, f32) {
+	let (sin, cos) = angle.to_radians().sin_cos();
+	(cos, sin)
+}
+
+/// Rotation DI gives a launch at `angle` with the stick at `joystick`: `MAX_DI_ANGLE` times
+/// the square of the stick's component perpendicular to the launch, toward the stick.
+fn rotation(angle: f32, joystick: Position) -> f32 {
+	let (x, y) = direction(angle);
+	let perpendicular = x * joystick.y - y * joystick.x;
+	MAX_DI_ANGLE * perpendicular * perpendicular.abs()
+}
+
+/// Distance from `from` to the blast zones, heading in a straight line at `angle`.
+fn distance_to_blast_zone(from: Position, angle: f32, zones: BlastZones) -> f32 {
+	let (x, y) = direction(angle);
+	let to_edge = |delta: f32, v: f32| match v {
+		v if v.abs() < f32::EPSILON => f32::INFINITY,
+		v => (delta / v).max(0.0),
+	};
+	let horizontal = to_edge(if x > 0.0 { zones.right - from.x } else { zones.left - from.x }, x);
+	let vertical = to_edge(if y > 0.0 { zones.top - from.y } else { zones.bottom - from.y }, y);
+	horizontal.min(vertical)
+}
+
+/// Launches of the player in `port` (leader only), with how well they DI'd each, from v3.5
+/// (which added knockback velocities) on; empty before.
+///
+/// A launch is the frame after a period of hitlag spent in a damage state (as for
+/// `sdi::sdi`) on which the player has knockback. DI rotates the launch toward the stick by
+/// up to `MAX_DI_ANGLE`, in proportion to the square of the stick's component perpendicular
+/// to the launch; we read the DI'd angle off the knockback velocity, & work back to the
+/// original angle from the stick.
+///
+/// DI is scored by how far the player could travel in a straight line at the DI'd angle
+/// before reaching a blast zone, relative to the shortest & longest such distances for any
+/// DI. This is a rough model of survival DI, with some limits:
+///
+/// * gravity, knockback decay & air friction bend the real path, so it's better at
+///   telling DI apart on strong hits than on weak ones, & ignores which DI would avoid
+///   tumble or set up a tech
+/// * the stick is read on the frame of launch, which may be a frame off from the game's
+///   own reading at the end of hitlag
+/// * SDI & ASDI (see `sdi::sdi`), which move the player before launch, are left out
+/// * hits that send the player into the stage or a wall are scored as if they didn't
+pub fn di(game: &Game, port: Port) -> Vec<DiEvent> {
+	let zones = game.start.stage.blast_zones();
+	let opts = Some(PortFramesOpts { finalized_only: true, ..Default::default() });
+	let frames: Vec<_> = game.port_frames(port, opts).into_iter().flatten()
+		.filter(|f| !f.is_follower)
+		.collect();
+
+	frames.windows(2).filter_map(|w| {
+		let (prev, cur) = (w[0].data, w[1].data);
+		if !in_hitlag(prev) || !prev.post.is_damaged() || in_hitlag(cur) {
+			return None;
+		}
+		let knockback = cur.post.velocities?.knockback;
+		if knockback.x == 0.0 && knockback.y == 0.0 {
+			return None;
+		}
+
+		let joystick = cur.pre.joystick;
+		let angle = knockback.y.atan2(knockback.x).to_degrees();
+		// DI'd angle = launch angle + rotation(launch angle), which converges quickly
+		let mut launch_angle = angle;
+		for _ in 0 .. 8 {
+			launch_angle = angle - rotation(launch_angle, joystick);
+		}
+
+		let position = cur.post.position;
+		let score = zones.and_then(|zones| {
+			let distance = |a: f32| distance_to_blast_zone(position, a, zones);
+			let (min, max) = (0 ..= SAMPLES)
+				.map(|n| launch_angle - MAX_DI_ANGLE + 2.0 * MAX_DI_ANGLE * n as f32 / SAMPLES as f32)
+				.chain([angle])
+				.map(distance)
+				.fold((f32::INFINITY, f32::NEG_INFINITY), |(min, max), d| (min.min(d), max.max(d)));
+			(max - min > f32::EPSILON).then(|| (distance(angle) - min) / (max - min))
+		});
+
+		Some(DiEvent {
+			index: w[1].index,
+			position: position,
+			joystick: joystick,
+			launch_angle: launch_angle,
+			rotation: angle - launch_angle,
+			score: score,
+		})
+	}).collect()
+}
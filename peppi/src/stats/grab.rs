@@ -0,0 +1,138 @@
+use serde::Serialize;
+
+use crate::model::{
+	enums::{
+		action_state::{Common, State},
+		attack::Attack,
+	},
+	frame::Post,
+	game::Game,
+	primitives::Port,
+};
+
+use super::combo;
+
+/// A successful grab, and what came of it.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+pub struct Grab {
+	/// frame index on which the grab connected
+	pub index: i32,
+	pub victim: Port,
+	/// throw used (see `Attack::is_throw`), or `None` if the victim got away
+	pub throw: Option<Attack>,
+	/// whether the victim teched (`Some(true)`) or missed the tech (`Some(false)`) after
+	/// the throw, or `None` if they did neither before leaving hitstun
+	pub teched: Option<bool>,
+	/// damage dealt to the victim after the throw itself, until the resulting combo
+	/// ended (see `combo::combos`)
+	pub follow_up_damage: f32,
+}
+
+#[derive(Clone, Copy, Debug)]
+enum Phase {
+	Holding,
+	/// frame index of the throw's release, and the victim's percent after the throw
+	/// (which only shows up on the frame after release)
+	Thrown { release: i32, after_throw: Option<f32> },
+}
+
+struct Tracking {
+	grab: Grab,
+	victim_idx: usize,
+	phase: Phase,
+}
+
+fn is_grab_connected(state: State) -> bool {
+	matches!(state, State::Common(Common::CATCH_PULL | Common::CATCH_DASH_PULL))
+}
+
+fn is_throwing(state: State) -> bool {
+	matches!(state, State::Common(s) if (Common::THROW_F.0 ..= Common::THROW_LW.0).contains(&s.0))
+}
+
+fn follow_up_damage(combos: &[combo::Combo], victim: Port, release: i32, after_throw: f32) -> f32 {
+	combos.iter()
+		.find(|c| c.victim == victim && c.start <= release && release <= c.end)
+		.map_or(0.0, |c| (c.end_percent - after_throw).max(0.0))
+}
+
+/// Advances `t` by one frame. Returns whether the grab is over.
+fn update(t: &mut Tracking, index: i32, attacker: &Post, victim: &Post, combos: &[combo::Combo]) -> bool {
+	match t.phase {
+		Phase::Holding if victim.is_grabbed() => false,
+		Phase::Holding if is_throwing(attacker.state) => {
+			t.grab.throw = attacker.last_attack_landed.filter(|a| a.is_throw());
+			t.phase = Phase::Thrown { release: index, after_throw: None };
+			false
+		},
+		// escaped
+		Phase::Holding => true,
+		Phase::Thrown { release, ref mut after_throw } => {
+			let after_throw = *after_throw.get_or_insert(victim.damage);
+			t.grab.follow_up_damage = follow_up_damage(combos, t.grab.victim, release, after_throw);
+			if victim.is_teching() {
+				t.grab.teched = Some(true);
+				true
+			} else if victim.is_downed() {
+				t.grab.teched = Some(false);
+				true
+			} else {
+				!victim.is_damaged()
+			}
+		},
+	}
+}
+
+/// Grabs by the player in `port`, with the throw (if any) and its follow-up.
+///
+/// A grab starts when the player enters `CATCH_PULL` or `CATCH_DASH_PULL` with another player
+/// held, and lasts until that player is released. If the grabber was mid-throw at that point,
+/// the throw is read from `last_attack_landed`. Tech tracking stops once the victim leaves
+/// hitstun. Only leaders (not Nana) are considered.
+pub fn grabs(game: &Game, port: Port) -> Vec<Grab> {
+	let port_idx = match game.port_index(port) {
+		Some(idx) => idx,
+		None => return vec![],
+	};
+	let combos = combo::combos(game, port, None);
+	let finalized = game.frames.finalized();
+	let post = |n: usize, idx: usize| &game.frames.port_data(n, idx).leader.post;
+
+	let mut grabs = Vec::new();
+	let mut current: Option<Tracking> = None;
+	let mut prev_state = None;
+
+	for n in (0 .. game.frames.len()).filter(|n| finalized[*n]) {
+		let index = game.frames.index(n);
+		let attacker = post(n, port_idx);
+
+		if let Some(mut t) = current.take() {
+			let victim = post(n, t.victim_idx);
+			match update(&mut t, index, attacker, victim, &combos) {
+				true => grabs.push(t.grab),
+				_ => current = Some(t),
+			}
+		}
+
+		if is_grab_connected(attacker.state) && prev_state != Some(attacker.state) {
+			grabs.extend(current.take().map(|t| t.grab));
+			let victim_idx = (0 .. game.start.players.len())
+				.find(|idx| *idx != port_idx && post(n, *idx).is_grabbed());
+			current = victim_idx.map(|victim_idx| Tracking {
+				grab: Grab {
+					index: index,
+					victim: game.start.players[victim_idx].port,
+					throw: None,
+					teched: None,
+					follow_up_damage: 0.0,
+				},
+				victim_idx: victim_idx,
+				phase: Phase::Holding,
+			});
+		}
+		prev_state = Some(attacker.state);
+	}
+
+	grabs.extend(current.map(|t| t.grab));
+	grabs
+}
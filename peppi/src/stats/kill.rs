@@ -0,0 +1,94 @@
+use serde::Serialize;
+
+use crate::{
+	model::{
+		enums::attack::Attack,
+		game::Game,
+		primitives::Port,
+	},
+	stats::combo::{is_punished, COMBO_RESET_FRAMES},
+};
+
+/// A stock lost to another player's attack.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+pub struct Kill {
+	pub killer: Port,
+	pub victim: Port,
+	/// the killer's `last_attack_landed` when they last hit the victim
+	pub attack: Option<Attack>,
+	/// frame index on which the victim died
+	pub index: i32,
+	/// victim's percent on the frame before they died
+	pub percent: f32,
+}
+
+fn victim_kills(game: &Game, victim_idx: usize) -> Vec<Kill> {
+	let victim = game.start.players[victim_idx].port;
+	let finalized = game.frames.finalized();
+	let post = |n: usize, idx: usize| &game.frames.port_data(n, idx).leader.post;
+
+	let mut kills = Vec::new();
+	// killer & their attack on the most recent hit still within the death window
+	let mut last_hit: Option<(Port, Option<Attack>)> = None;
+	let mut actionable = 0;
+	let mut prev: Option<usize> = None;
+
+	for n in (0 .. game.frames.len()).filter(|n| finalized[*n]) {
+		let cur = post(n, victim_idx);
+		let p = match prev.replace(n) {
+			Some(p) => post(p, victim_idx),
+			None => continue,
+		};
+
+		if cur.is_dead() {
+			if !p.is_dead() {
+				if let Some((killer, attack)) = last_hit {
+					kills.push(Kill {
+						killer: killer,
+						victim: victim,
+						attack: attack,
+						index: game.frames.index(n),
+						percent: p.damage,
+					});
+				}
+			}
+			last_hit = None;
+			continue;
+		}
+
+		let killer = cur.last_hit_by.filter(|k| *k != victim)
+			.and_then(|k| game.port_index(k).map(|idx| (k, idx)));
+		match killer {
+			Some((k, idx)) if cur.damage > p.damage => {
+				last_hit = Some((k, post(n, idx).last_attack_landed));
+				actionable = 0;
+			},
+			_ if is_punished(cur) => actionable = 0,
+			_ => {
+				actionable += 1;
+				if actionable > COMBO_RESET_FRAMES {
+					last_hit = None;
+				}
+			},
+		}
+	}
+
+	kills
+}
+
+/// Every stock lost to another player, ordered by frame index. Only leaders (not Nana)
+/// are considered.
+///
+/// A death is credited to the last player to hit the victim (see `Post::last_hit_by`),
+/// with the killer's `last_attack_landed` at that hit as the killing move, provided the
+/// victim didn't spend 45 consecutive frames actionable in between (as for
+/// `combo::combos`). Deaths with no such hit are self-destructs, and are left out. This
+/// still credits edgeguards where the victim was hit offstage & then failed to recover,
+/// as long as they die within the window.
+pub fn kills(game: &Game) -> Vec<Kill> {
+	let mut kills: Vec<_> = (0 .. game.start.players.len())
+		.flat_map(|idx| victim_kills(game, idx))
+		.collect();
+	kills.sort_by_key(|k| k.index);
+	kills
+}
@@ -0,0 +1,71 @@
+use serde::Serialize;
+
+use crate::model::{
+	enums::action_state::{Common, State},
+	frame::Post,
+	game::Game,
+	primitives::Port,
+};
+
+/// Height below which a player is considered off-stage even if between the ledges. Not 0,
+/// because some stages (e.g. Yoshi's Story) slope downwards near the edges.
+const STAGE_BOTTOM: f32 = -10.0;
+
+/// Ledge usage by a single player.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct LedgeStats {
+	/// number of times the player grabbed a ledge
+	pub grabs: usize,
+	/// frames spent hanging from a ledge
+	pub frames: usize,
+	/// number of times the player was hanging from a ledge while an opponent was off-stage
+	/// (always 0 on stages without known ledge positions, see `Stage::ledge_x`)
+	pub ledgehogs: usize,
+}
+
+/// Grabbing or hanging from a ledge (`CLIFF_CATCH` or `CLIFF_WAIT`).
+fn is_hanging(post: &Post) -> bool {
+	matches!(post.state, State::Common(Common::CLIFF_CATCH | Common::CLIFF_WAIT))
+}
+
+/// Beyond either ledge, or below the stage. Doesn't count dead or respawning players.
+fn is_off_stage(post: &Post, ledge_x: f32) -> bool {
+	!post.is_dead() && !post.is_respawn()
+		&& (post.position.x.abs() > ledge_x || post.position.y < STAGE_BOTTOM)
+}
+
+/// Ledge grabs, hang time, and ledgehogs by the player in `port` (leader only).
+pub fn ledge(game: &Game, port: Port) -> LedgeStats {
+	let port_idx = match game.port_index(port) {
+		Some(idx) => idx,
+		None => return LedgeStats::default(),
+	};
+	let ledge_x = game.start.stage.ledge_x();
+	let finalized = game.frames.finalized();
+	let post = |n: usize, idx: usize| &game.frames.port_data(n, idx).leader.post;
+
+	let mut stats = LedgeStats::default();
+	let mut prev_state = None;
+	let mut prev_hogging = false;
+
+	for n in (0 .. game.frames.len()).filter(|n| finalized[*n]) {
+		let p = post(n, port_idx);
+		let hanging = is_hanging(p);
+		if hanging {
+			stats.frames += 1;
+		}
+		if p.state == State::Common(Common::CLIFF_CATCH) && prev_state != Some(p.state) {
+			stats.grabs += 1;
+		}
+		prev_state = Some(p.state);
+
+		let hogging = hanging && ledge_x.is_some_and(|x| (0 .. game.start.players.len())
+			.any(|idx| idx != port_idx && is_off_stage(post(n, idx), x)));
+		if hogging && !prev_hogging {
+			stats.ledgehogs += 1;
+		}
+		prev_hogging = hogging;
+	}
+
+	stats
+}
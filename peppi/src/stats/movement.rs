@@ -0,0 +1,85 @@
+use serde::Serialize;
+
+use crate::model::{
+	enums::action_state::{Common, State},
+	frame::STICK_DEADZONE,
+	game::{Game, PortFramesOpts},
+	primitives::{Direction, Port},
+};
+
+const DASH: State = State::Common(Common::DASH);
+const TURN: State = State::Common(Common::TURN);
+
+/// Counts of ground-movement techniques used by a single port.
+///
+/// Detection works on runs of identical action states, where a change
+/// of direction also starts a new `DASH` run:
+///
+/// * dash-dance: `DASH` followed by `DASH` in the opposite direction, either
+///   directly or with a single `TURN` run in between
+/// * pivot: `DASH` → `TURN` → anything other than `DASH`
+/// * perfect pivot: a pivot whose `TURN` lasted one frame, with the joystick
+///   released to neutral on that frame
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct MovementStats {
+	pub dash_dances: usize,
+	pub pivots: usize,
+	pub perfect_pivots: usize,
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Run {
+	state: State,
+	direction: Direction,
+	frames: usize,
+	neutral: bool,
+}
+
+fn update(stats: &mut MovementStats, runs: &[Run]) {
+	match runs {
+		[.., a, b] if a.state == DASH && b.state == DASH && a.direction != b.direction =>
+			stats.dash_dances += 1,
+		[.., a, b, c] if a.state == DASH && b.state == TURN => match c.state {
+			DASH => if a.direction != c.direction {
+				stats.dash_dances += 1;
+			},
+			_ => {
+				stats.pivots += 1;
+				if b.frames == 1 && b.neutral {
+					stats.perfect_pivots += 1;
+				}
+			},
+		},
+		_ => (),
+	}
+}
+
+/// Dash-dance and pivot counts for the player in `port` (see `MovementStats`).
+/// Uses the leader's data for Ice Climbers.
+pub fn movement(game: &Game, port: Port) -> MovementStats {
+	let mut stats = MovementStats::default();
+	let mut runs: Vec<Run> = Vec::new();
+
+	let opts = Some(PortFramesOpts { merge_followers: true, finalized_only: true });
+	for f in game.port_frames(port, opts).into_iter().flatten() {
+		let post = &f.data.post;
+		let neutral = f.data.pre.joystick.x.abs() < STICK_DEADZONE;
+		match runs.last_mut() {
+			Some(r) if r.state == post.state && (r.state != DASH || r.direction == post.direction) => {
+				r.frames += 1;
+				r.neutral = r.neutral && neutral;
+			},
+			_ => {
+				runs.push(Run {
+					state: post.state,
+					direction: post.direction,
+					frames: 1,
+					neutral: neutral,
+				});
+				update(&mut stats, &runs);
+			},
+		}
+	}
+
+	stats
+}
@@ -0,0 +1,103 @@
+use std::io::Result;
+
+use serde::Serialize;
+
+use crate::model::{
+	game::Game,
+	primitives::Port,
+};
+
+use super::combo::{self, Combo, COMBO_RESET_FRAMES};
+
+/// How a player got an opening (the first hit of a combo, see `combo::combos`).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+pub enum OpeningType {
+	/// Hit the opponent out of neutral.
+	NeutralWin,
+	/// Hit the opponent while still being comboed by them, before escaping.
+	CounterHit,
+	/// Hit the opponent on the same frame as they hit back.
+	Trade,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+pub struct Opening {
+	pub port: Port,
+	/// frame index of the opening hit
+	pub index: i32,
+	pub r#type: OpeningType,
+}
+
+/// Number of openings of each type for one player.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+pub struct PortNeutral {
+	pub port: Port,
+	pub neutral_wins: usize,
+	pub counter_hits: usize,
+	pub trades: usize,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct NeutralStats {
+	/// in the same order as `Start::players`
+	pub players: [PortNeutral; 2],
+	/// every opening, ordered by frame index
+	pub openings: Vec<Opening>,
+}
+
+fn classify(combo: &Combo, opponent_combos: &[Combo]) -> OpeningType {
+	if opponent_combos.iter().any(|o| o.start == combo.start) {
+		OpeningType::Trade
+	} else if opponent_combos.iter().any(|o| o.start < combo.start && !o.killed
+			&& combo.start <= o.end + COMBO_RESET_FRAMES as i32) {
+		OpeningType::CounterHit
+	} else {
+		OpeningType::NeutralWin
+	}
+}
+
+/// Who won the neutral game how often, in a 1v1 game. Fails for other games.
+///
+/// Every combo (see `combo::combos`) starts with an opening, classified by what the
+/// opponent was doing (in the manner of slippi-js's opening types):
+///
+/// * a trade if the opponent also started a combo on the same frame; both players get one
+/// * a counter-hit if the player was still the victim of one of the opponent's combos, i.e.
+///   within 45 frames of last being punished (and not having died since)
+/// * a neutral win otherwise
+pub fn neutral(game: &Game) -> Result<NeutralStats> {
+	let ports: Vec<Port> = game.start.players.iter().map(|p| p.port).collect();
+	let ports: [Port; 2] = ports.try_into()
+		.map_err(|p: Vec<_>| err!("not a 1v1 game: {} players", p.len()))?;
+	let combos = ports.map(|p| combo::combos(game, p, None));
+
+	let mut openings = Vec::new();
+	let players = [0, 1].map(|n| {
+		let mut stats = PortNeutral {
+			port: ports[n],
+			neutral_wins: 0,
+			counter_hits: 0,
+			trades: 0,
+		};
+		for c in &combos[n] {
+			let r#type = classify(c, &combos[1 - n]);
+			match r#type {
+				OpeningType::NeutralWin => stats.neutral_wins += 1,
+				OpeningType::CounterHit => stats.counter_hits += 1,
+				OpeningType::Trade => stats.trades += 1,
+			}
+			openings.push(Opening {
+				port: ports[n],
+				index: c.start,
+				r#type: r#type,
+			});
+		}
+		stats
+	});
+	openings.sort_by_key(|o| o.index);
+
+	Ok(NeutralStats {
+		players: players,
+		openings: openings,
+	})
+}
@@ -0,0 +1,20 @@
+use crate::model::{
+	game::{Game, PortFramesOpts},
+	primitives::Port,
+};
+
+/// Frame index and percent (damage taken) on each finalized frame for the player in `port`.
+/// Percent drops back to 0 when the player respawns after losing a stock.
+pub fn percent_timeline(game: &Game, port: Port) -> Vec<(i32, f32)> {
+	let opts = Some(PortFramesOpts { merge_followers: true, finalized_only: true });
+	game.port_frames(port, opts).into_iter().flatten()
+		.map(|f| (f.index, f.data.post.damage))
+		.collect()
+}
+
+/// Highest percent reached by the player in `port` over the whole game, on any stock.
+pub fn max_percent(game: &Game, port: Port) -> f32 {
+	percent_timeline(game, port).into_iter()
+		.map(|(_, damage)| damage)
+		.fold(0.0, f32::max)
+}
@@ -0,0 +1,93 @@
+use serde::Serialize;
+
+use crate::model::{
+	frame::{Data, StateFlags},
+	game::{Game, PortFramesOpts},
+	primitives::{Port, Position},
+};
+
+/// Joystick magnitude (per axis) past which an input counts as a smash for SDI purposes.
+const SDI_THRESHOLD: f32 = 0.7;
+
+/// A single SDI input made during hitlag.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+pub struct SdiInput {
+	pub index: i32,
+	pub joystick: Position,
+}
+
+/// One period of hitlag suffered by the defender, and the SDI inputs made during it.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct SdiEvent {
+	/// frame index of the first frame of hitlag
+	pub index: i32,
+	/// number of frames spent in hitlag
+	pub frames: usize,
+	pub inputs: Vec<SdiInput>,
+}
+
+/// Sign of each joystick axis, or 0 if that axis isn't past the SDI threshold.
+fn region(joystick: Position) -> (i8, i8) {
+	let axis = |v: f32| match v {
+		v if v >= SDI_THRESHOLD => 1,
+		v if v <= -SDI_THRESHOLD => -1,
+		_ => 0,
+	};
+	(axis(joystick.x), axis(joystick.y))
+}
+
+/// Whether either axis newly crossed the threshold (from neutral or from the opposite side).
+fn is_smash(region: (i8, i8), last: (i8, i8)) -> bool {
+	(region.0 != 0 && region.0 != last.0) || (region.1 != 0 && region.1 != last.1)
+}
+
+/// Whether the character is in hitlag, using `hitlag` (v3.8+) or else the `HIT_LAG` flag (v2.0+).
+pub(crate) fn in_hitlag(data: &Data) -> bool {
+	match (data.post.hitlag, data.post.flags) {
+		(Some(hitlag), _) => hitlag > 0.0,
+		(_, Some(flags)) => (flags & StateFlags::HIT_LAG).0 != 0,
+		_ => false,
+	}
+}
+
+/// Hitlag periods suffered by the player in `port`, with the SDI inputs made in each.
+///
+/// Heuristic: the player is the defender when they're both in hitlag and in a damage state, which
+/// excludes the attacker's own hitlag. Consecutive such frames make up one hit. Each frame in which
+/// either joystick axis newly passes ±0.7 (having been inside it, or past it in the opposite
+/// direction, on the previous frame) is one SDI input. Holding a direction into hitlag therefore
+/// doesn't count, and neither does sliding along the rim away from an axis, but each new smash does.
+///
+/// Only the leader is considered (not Nana). Replays older than v2.0 have no hitlag information,
+/// so this returns nothing for them.
+pub fn sdi(game: &Game, port: Port) -> Vec<SdiEvent> {
+	let opts = Some(PortFramesOpts { merge_followers: true, finalized_only: true });
+	let mut events: Vec<SdiEvent> = vec![];
+	let mut current: Option<SdiEvent> = None;
+	let mut last_region = (0, 0);
+
+	for f in game.port_frames(port, opts).into_iter().flatten() {
+		let data = f.data;
+		let r = region(data.pre.joystick);
+		if in_hitlag(data) && data.post.is_damaged() {
+			let event = current.get_or_insert_with(|| SdiEvent {
+				index: f.index,
+				frames: 0,
+				inputs: vec![],
+			});
+			event.frames += 1;
+			if is_smash(r, last_region) {
+				event.inputs.push(SdiInput {
+					index: f.index,
+					joystick: data.pre.joystick,
+				});
+			}
+		} else if let Some(event) = current.take() {
+			events.push(event);
+		}
+		last_region = r;
+	}
+
+	events.extend(current);
+	events
+}
@@ -0,0 +1,233 @@
+use serde_json::{json, Value};
+
+use crate::model::{
+	enums::{action_state::{Common, State}, attack::Attack},
+	frame::Post,
+	game::{Game, PortFramesOpts},
+	primitives::Port,
+};
+
+use super::{conversion::{self, Conversion}, grab, ledge, movement, stock, tech};
+
+/// slippi-js's `Frames.FIRST_PLAYABLE`: the first frame on which players can act.
+const FIRST_PLAYABLE: i32 = -39;
+
+/// Finalized leader post-frame data for `port`, in frame order.
+fn posts(game: &Game, port: Port) -> Vec<&Post> {
+	let opts = Some(PortFramesOpts { merge_followers: true, finalized_only: true });
+	game.port_frames(port, opts).into_iter().flatten().map(|f| &f.data.post).collect()
+}
+
+/// slippi-js's `RatioType`.
+fn ratio(count: f32, total: f32) -> Value {
+	json!({
+		"count": count,
+		"total": total,
+		"ratio": (total != 0.0).then(|| count / total),
+	})
+}
+
+fn stocks(game: &Game, port: Port, posts: &[&Post], first: i32) -> Vec<Value> {
+	let post = |index: i32| posts.get((index - first) as usize);
+	stock::stock_timeline(game, port).into_iter().map(|s| {
+		let last = s.end.map_or(first + posts.len() as i32 - 1, |end| end - 1);
+		let percent = post(last).map(|p| p.damage);
+		json!({
+			"playerIndex": port as u8,
+			"startFrame": s.start,
+			"endFrame": s.end,
+			"startPercent": 0.0,
+			"endPercent": s.end.and(percent),
+			"currentPercent": percent.unwrap_or(0.0),
+			"count": s.count,
+			"deathAnimation": s.end.and_then(post).map(|p| u16::from(p.state)),
+		})
+	}).collect()
+}
+
+/// `c`'s moves, as slippi-js `MoveLandedType`s.
+fn moves(attacker: Port, c: &Conversion) -> Vec<Value> {
+	c.moves.iter().map(|m| json!({
+		"playerIndex": attacker as u8,
+		"frame": m.index,
+		"moveId": m.attack.map_or(0, |a| a.0),
+		"hitCount": m.hits,
+		"damage": m.damage,
+	})).collect()
+}
+
+/// slippi-js's `openingType` for each of `conversions` (attacker, conversion), which must
+/// be sorted by start frame: a `"trade"` if another conversion started on the same frame,
+/// a `"counter-attack"` if the attacker was itself being punished at the time, or else a
+/// `"neutral-win"`. Only defined for singles, so `None` (null) otherwise, as in slippi-js.
+fn opening_types(conversions: &[(Port, Conversion)], singles: bool) -> Vec<Option<&'static str>> {
+	conversions.iter().map(|(attacker, c)| singles.then(|| {
+		if conversions.iter().any(|(a, o)| a != attacker && o.start == c.start) {
+			"trade"
+		} else if conversions.iter().any(|(_, o)| o.victim == *attacker
+				&& o.start < c.start && o.end.is_none_or(|end| end > c.start)) {
+			"counter-attack"
+		} else {
+			"neutral-win"
+		}
+	})).collect()
+}
+
+fn throw_count(grabs: &[grab::Grab], throws: [Attack; 2]) -> usize {
+	grabs.iter().filter(|g| g.throw.is_some_and(|t| throws.contains(&t))).count()
+}
+
+fn action_counts(game: &Game, port: Port, posts: &[&Post], opponent_posts: Option<&[&Post]>, first: i32) -> Value {
+	let entered = |states: &[Common]| posts.windows(2)
+		.filter(|w| w[0].state != w[1].state
+			&& matches!(w[1].state, State::Common(s) if states.contains(&s)))
+		.count();
+	// grab attempts, by whether they connected
+	let grab_attempts = |success: bool| posts.windows(2)
+		.filter(|w| w[0].state != w[1].state
+			&& matches!(w[0].state, State::Common(Common::CATCH | Common::CATCH_DASH))
+			&& matches!(w[1].state, State::Common(Common::CATCH_PULL | Common::CATCH_DASH_PULL | Common::CATCH_WAIT)) == success)
+		.count();
+	let l_cancels = |success: bool| posts.iter()
+		.filter(|p| p.l_cancel == Some(Some(success)))
+		.count();
+
+	let grabs = grab::grabs(game, port);
+	let techs = tech::techs(game, port);
+	let ground_techs = |f: &dyn Fn(&tech::Tech) -> bool| techs.iter()
+		.filter(|t| !t.missed && !matches!(t.option, Some(tech::TechOption::Wall | tech::TechOption::WallJump | tech::TechOption::Ceiling)))
+		.filter(|t| f(t))
+		.count();
+	// whether a tech roll moved towards the opponent
+	let is_in = |t: &tech::Tech| {
+		let i = (t.index - first) as usize;
+		let x = posts.get(i).map_or(0.0, |p| p.position.x);
+		let opponent_x = opponent_posts.and_then(|o| o.get(i)).map_or(x, |p| p.position.x);
+		match t.option {
+			Some(tech::TechOption::RollLeft) => opponent_x < x,
+			Some(tech::TechOption::RollRight) => opponent_x > x,
+			_ => false,
+		}
+	};
+	let is_roll = |t: &tech::Tech| matches!(t.option,
+		Some(tech::TechOption::RollLeft | tech::TechOption::RollRight));
+
+	json!({
+		"playerIndex": port as u8,
+		"airDodgeCount": entered(&[Common::ESCAPE_AIR]),
+		"dashDanceCount": movement::movement(game, port).dash_dances,
+		"spotDodgeCount": entered(&[Common::ESCAPE]),
+		"ledgegrabCount": ledge::ledge(game, port).grabs,
+		"rollCount": entered(&[Common::ESCAPE_F, Common::ESCAPE_B]),
+		"lCancelCount": {
+			"success": l_cancels(true),
+			"fail": l_cancels(false),
+		},
+		"grabCount": {
+			"success": grab_attempts(true),
+			"fail": grab_attempts(false),
+		},
+		"throwCount": {
+			"up": throw_count(&grabs, [Attack::UP_THROW, Attack::CARGO_UP_THROW]),
+			"forward": throw_count(&grabs, [Attack::FORWARD_THROW, Attack::CARGO_FORWARD_THROW]),
+			"back": throw_count(&grabs, [Attack::BACK_THROW, Attack::CARGO_BACK_THROW]),
+			"down": throw_count(&grabs, [Attack::DOWN_THROW, Attack::CARGO_DOWN_THROW]),
+		},
+		"groundTechCount": {
+			"away": ground_techs(&|t| is_roll(t) && !is_in(t)),
+			"in": ground_techs(&|t| is_in(t)),
+			"neutral": ground_techs(&|t| t.option == Some(tech::TechOption::InPlace)),
+			"fail": techs.iter().filter(|t| t.missed).count(),
+		},
+		"wallTechCount": {
+			"success": techs.iter().filter(|t| matches!(t.option, Some(tech::TechOption::Wall | tech::TechOption::WallJump))).count(),
+		},
+	})
+}
+
+/// Stats for `game` in the JSON format of slippi-js's `SlippiGame#getStats`, so that peppi
+/// can stand in for it behind tools that consume that format. Player indexes are ports
+/// (P1 = 0), as in slippi-js.
+///
+/// Covers `lastFrame`, `playableFrameCount`, `gameComplete`, `stocks`, `conversions`,
+/// `actionCounts` & `overall`, computed with peppi's own stats (conversions are
+/// `conversion::conversions`), so numbers can differ slightly from slippi-js's. In
+/// particular:
+///
+/// * `actionCounts` lacks wavedash, waveland & attack counts, and counts only successful
+///   wall techs
+/// * `overall` lacks input counts & neutral-game ratios
+pub fn to_slippi_js_json(game: &Game) -> Value {
+	let ports: Vec<Port> = game.start.players.iter().map(|p| p.port).collect();
+	let first = match game.frames.is_empty() {
+		true => 0,
+		_ => game.frames.index(0),
+	};
+	let posts: Vec<Vec<&Post>> = ports.iter().map(|p| posts(game, *p)).collect();
+	let last_frame = posts.first().map(|p| first + p.len() as i32 - 1);
+
+	let mut conversions: Vec<(Port, Conversion)> = ports.iter()
+		.flat_map(|p| conversion::conversions(game, *p).into_iter().map(move |c| (*p, c)))
+		.collect();
+	conversions.sort_by_key(|(_, c)| c.start);
+	let opening_types = opening_types(&conversions, ports.len() == 2);
+
+	let mut stocks_json = Vec::new();
+	let mut actions = Vec::new();
+	let mut overall = Vec::new();
+
+	for (n, &port) in ports.iter().enumerate() {
+		stocks_json.extend(stocks(game, port, &posts[n], first));
+
+		let own: Vec<&Conversion> = conversions.iter()
+			.filter(|(a, _)| *a == port)
+			.map(|(_, c)| c)
+			.collect();
+		let damage: f32 = own.iter().map(|c| c.damage()).sum();
+		let successful = own.iter().filter(|c| c.moves.len() > 1).count();
+
+		let opponent = match posts.len() {
+			2 => Some(&posts[1 - n][..]),
+			_ => None,
+		};
+		actions.push(action_counts(game, port, &posts[n], opponent, first));
+
+		let kills = ports.iter()
+			.filter(|p| **p != port)
+			.flat_map(|p| stock::stock_timeline(game, *p))
+			.filter(|s| s.end.is_some())
+			.count();
+		overall.push(json!({
+			"playerIndex": port as u8,
+			"conversionCount": own.len(),
+			"totalDamage": damage,
+			"killCount": kills,
+			"successfulConversions": ratio(successful as f32, own.len() as f32),
+			"openingsPerKill": ratio(own.len() as f32, kills as f32),
+			"damagePerOpening": ratio(damage, own.len() as f32),
+		}));
+	}
+
+	let conversions: Vec<Value> = conversions.iter().zip(opening_types).map(|((attacker, c), opening_type)| json!({
+		"playerIndex": c.victim as u8,
+		"lastHitBy": *attacker as u8,
+		"startFrame": c.start,
+		"endFrame": c.end,
+		"startPercent": c.start_percent,
+		"currentPercent": c.end_percent,
+		"endPercent": c.end.map(|_| c.end_percent),
+		"moves": moves(*attacker, c),
+		"didKill": c.killed,
+		"openingType": opening_type,
+	})).collect();
+
+	json!({
+		"lastFrame": last_frame,
+		"playableFrameCount": last_frame.map_or(0, |f| (f - FIRST_PLAYABLE).max(0)),
+		"stocks": stocks_json,
+		"conversions": conversions,
+		"actionCounts": actions,
+		"overall": overall,
+		"gameComplete": !game.truncated,
+	})
+}
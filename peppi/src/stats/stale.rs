@@ -0,0 +1,97 @@
+use serde::Serialize;
+
+use crate::model::{
+	enums::{action_state::State, attack::Attack},
+	game::Game,
+	primitives::Port,
+};
+
+/// Number of slots in the stale-move queue.
+pub const QUEUE_LEN: usize = 9;
+
+/// Where a move appears in its user's stale-move queue, as a bitmask of slots
+/// (bit 0 = the most recent move). Fresh moves have no bits set.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct StaleLevel(pub u16);
+
+impl StaleLevel {
+	/// Number of times the move appears in the queue.
+	pub fn count(self) -> u32 {
+		self.0.count_ones()
+	}
+
+	/// Factor applied to the move's damage: each slot the move occupies reduces it by
+	/// 0.09 (most recent) down to 0.01 (oldest), for a minimum of 0.55.
+	pub fn multiplier(self) -> f32 {
+		let reduction: u16 = (0 .. QUEUE_LEN as u16)
+			.filter(|i| self.0 & (1 << i) != 0)
+			.map(|i| QUEUE_LEN as u16 - i)
+			.sum();
+		1.0 - reduction as f32 / 100.0
+	}
+}
+
+/// Every move landed by the player in `port` (leader only), as `(frame index, attack,
+/// staleness when it landed)`.
+///
+/// Follows Melee's model: each move that hits an opponent is pushed onto a queue of the
+/// last `QUEUE_LEN` moves landed, and a move's damage is reduced according to where it
+/// already appears in the queue (see `StaleLevel::multiplier`). The queue is emptied when
+/// the player loses a stock. `Attack::NON_STALING` moves are never stale and aren't queued.
+///
+/// A hit is an increase in an opponent's percent credited to `port` (see `Post::last_hit_by`),
+/// and the move is the attacker's `last_attack_landed`. Since that doesn't change when the
+/// same move lands again, a new move starts when `last_attack_landed` changes or the attacker
+/// re-enters its action state; further hits of a multi-hit move count once.
+pub fn staleness(game: &Game, port: Port) -> Vec<(i32, Attack, StaleLevel)> {
+	let port_idx = match game.port_index(port) {
+		Some(idx) => idx,
+		None => return vec![],
+	};
+	let finalized = game.frames.finalized();
+	let post = |n: usize, idx: usize| &game.frames.port_data(n, idx).leader.post;
+	let players = game.start.players.len();
+
+	let mut moves = Vec::new();
+	let mut queue: Vec<Attack> = Vec::new();
+	// attack, action state & state age of the last hit
+	let mut last_hit: Option<(Attack, State, Option<f32>)> = None;
+	let mut prev: Option<usize> = None;
+
+	for n in (0 .. game.frames.len()).filter(|n| finalized[*n]) {
+		let attacker = post(n, port_idx);
+		let is_hit = prev.is_some_and(|p| (0 .. players).any(|idx| idx != port_idx
+			&& post(n, idx).damage > post(p, idx).damage
+			&& post(n, idx).last_hit_by == Some(port)));
+		if prev.is_some_and(|p| attacker.stocks < post(p, port_idx).stocks) {
+			queue.clear();
+			last_hit = None;
+		}
+		prev = Some(n);
+
+		let attack = match (is_hit, attacker.last_attack_landed) {
+			(true, Some(attack)) => attack,
+			_ => continue,
+		};
+		let is_same_move = last_hit.is_some_and(|(a, state, age)| a == attack
+			&& state == attacker.state
+			&& age.zip(attacker.state_age).is_none_or(|(prev, cur)| cur > prev));
+		last_hit = Some((attack, attacker.state, attacker.state_age));
+		if is_same_move {
+			continue;
+		}
+
+		if attack == Attack::NON_STALING {
+			moves.push((game.frames.index(n), attack, StaleLevel::default()));
+			continue;
+		}
+		let level = queue.iter().enumerate()
+			.filter(|(_, a)| **a == attack)
+			.fold(0, |level, (i, _)| level | 1 << i);
+		moves.push((game.frames.index(n), attack, StaleLevel(level)));
+		queue.insert(0, attack);
+		queue.truncate(QUEUE_LEN);
+	}
+
+	moves
+}
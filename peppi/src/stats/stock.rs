@@ -0,0 +1,46 @@
+use serde::Serialize;
+
+use crate::model::{
+	game::{Game, PortFramesOpts},
+	primitives::Port,
+};
+
+/// A single stock, from the start of the game or respawning, until death.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+pub struct Stock {
+	/// stocks remaining, including this one
+	pub count: u8,
+	/// frame index on which the stock began (the first frame, or entering the respawn platform)
+	pub start: i32,
+	/// frame index on which the player died, or `None` if the game ended first
+	pub end: Option<i32>,
+}
+
+/// Each stock played by the player in `port`, in order. See `Post::is_dead` & `Post::is_respawn`.
+pub fn stock_timeline(game: &Game, port: Port) -> Vec<Stock> {
+	let opts = Some(PortFramesOpts { merge_followers: true, finalized_only: true });
+	let mut stocks = Vec::new();
+	let mut current: Option<Stock> = None;
+	let mut prev: Option<(bool, bool)> = None;
+
+	for f in game.port_frames(port, opts).into_iter().flatten() {
+		let post = &f.data.post;
+		let (dead, respawn) = (post.is_dead(), post.is_respawn());
+		match prev {
+			None => current = Some(Stock { count: post.stocks, start: f.index, end: None }),
+			Some((prev_dead, _)) if dead && !prev_dead => {
+				if let Some(mut stock) = current.take() {
+					stock.end = Some(f.index);
+					stocks.push(stock);
+				}
+			},
+			Some((_, prev_respawn)) if respawn && !prev_respawn =>
+				current = Some(Stock { count: post.stocks, start: f.index, end: None }),
+			_ => (),
+		}
+		prev = Some((dead, respawn));
+	}
+
+	stocks.extend(current);
+	stocks
+}
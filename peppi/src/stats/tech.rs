@@ -0,0 +1,127 @@
+use serde::Serialize;
+
+use crate::model::{
+	enums::action_state::{Common, State},
+	frame::Post,
+	game::{Game, PortFramesOpts},
+	primitives::{Direction, Port},
+};
+
+/// What a player did upon hitting the ground in a knockdown situation.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+pub enum TechOption {
+	InPlace,
+	RollLeft,
+	RollRight,
+	/// regular get-up after a missed tech
+	GetUp,
+	/// get-up attack after a missed tech
+	GetUpAttack,
+	Wall,
+	WallJump,
+	Ceiling,
+}
+
+/// A knockdown situation: either a tech or a missed tech followed by a get-up option.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+pub struct Tech {
+	/// frame index at which the player teched or hit the ground
+	pub index: i32,
+	/// whether the player failed to tech and was knocked down
+	pub missed: bool,
+	/// `None` for missed techs where the player never got up (e.g. hit while down, or KO'd)
+	pub option: Option<TechOption>,
+	/// whether the player was in hitstun or tumble immediately beforehand
+	pub after_hit: bool,
+}
+
+fn common(state: State) -> Option<Common> {
+	match state {
+		State::Common(s) => Some(s),
+		_ => None,
+	}
+}
+
+/// Roll toward or away from the direction the player is facing.
+fn roll(forward: bool, direction: Direction) -> TechOption {
+	match (forward, direction) {
+		(true, Direction::Right) | (false, Direction::Left) => TechOption::RollRight,
+		_ => TechOption::RollLeft,
+	}
+}
+
+fn tech_option(state: Common, direction: Direction) -> Option<TechOption> {
+	match state {
+		Common::PASSIVE => Some(TechOption::InPlace),
+		Common::PASSIVE_STAND_F => Some(roll(true, direction)),
+		Common::PASSIVE_STAND_B => Some(roll(false, direction)),
+		Common::PASSIVE_WALL => Some(TechOption::Wall),
+		Common::PASSIVE_WALL_JUMP => Some(TechOption::WallJump),
+		Common::PASSIVE_CEIL => Some(TechOption::Ceiling),
+		_ => None,
+	}
+}
+
+fn get_up_option(state: Common, direction: Direction) -> Option<TechOption> {
+	match state {
+		Common::DOWN_STAND_U | Common::DOWN_STAND_D => Some(TechOption::GetUp),
+		Common::DOWN_ATTACK_U | Common::DOWN_ATTACK_D => Some(TechOption::GetUpAttack),
+		Common::DOWN_FOWARD_U | Common::DOWN_FOWARD_D => Some(roll(true, direction)),
+		Common::DOWN_BACK_U | Common::DOWN_BACK_D => Some(roll(false, direction)),
+		_ => None,
+	}
+}
+
+/// Techs & missed techs by the player in `port`, in order.
+///
+/// Rolls (including get-up rolls after a missed tech) are reported in absolute
+/// terms, based on the direction the player faced when starting the roll.
+pub fn techs(game: &Game, port: Port) -> Vec<Tech> {
+	let mut techs: Vec<Tech> = Vec::new();
+	// post-frame data from the first frame of the previous action state
+	let mut prev: Option<&Post> = None;
+	// whether the last entry is a missed tech whose get-up option we're still waiting for
+	let mut pending = false;
+
+	let opts = Some(PortFramesOpts { merge_followers: true, finalized_only: true });
+	for f in game.port_frames(port, opts).into_iter().flatten() {
+		let post = &f.data.post;
+		if prev.is_some_and(|p| p.state == post.state) {
+			continue;
+		}
+		let after_hit = prev.is_some_and(|p| p.is_damaged());
+		prev = Some(post);
+
+		let state = match common(post.state) {
+			Some(s) => s,
+			_ => { pending = false; continue; },
+		};
+
+		if let Some(option) = tech_option(state, post.direction) {
+			pending = false;
+			techs.push(Tech {
+				index: f.index,
+				missed: false,
+				option: Some(option),
+				after_hit: after_hit,
+			});
+		} else if state == Common::DOWN_BOUND_U || state == Common::DOWN_BOUND_D {
+			pending = true;
+			techs.push(Tech {
+				index: f.index,
+				missed: true,
+				option: None,
+				after_hit: after_hit,
+			});
+		} else if pending {
+			if let Some(option) = get_up_option(state, post.direction) {
+				techs.last_mut().unwrap().option = Some(option);
+				pending = false;
+			} else if !post.is_downed() {
+				pending = false;
+			}
+		}
+	}
+
+	techs
+}
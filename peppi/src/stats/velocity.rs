@@ -0,0 +1,86 @@
+use serde::Serialize;
+
+use crate::model::{
+	enums::action_state::{Common, State},
+	frame::Post,
+	game::{Game, PortFramesOpts},
+	primitives::{Port, Velocity},
+};
+
+/// Distance (in-game units per frame) by which velocity & position delta may differ
+/// before we flag a frame, to allow for floating-point error.
+pub const TOLERANCE: f32 = 0.01;
+
+/// A frame on which a character's velocity doesn't account for how far it moved.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+pub struct VelocityAnomaly {
+	pub index: i32,
+	/// autogenous + knockback velocity
+	pub velocity: Velocity,
+	/// change in position since the previous frame
+	pub delta: Velocity,
+}
+
+impl VelocityAnomaly {
+	/// Distance between `velocity` & `delta`.
+	pub fn divergence(&self) -> f32 {
+		(self.delta.x - self.velocity.x).hypot(self.delta.y - self.velocity.y)
+	}
+}
+
+/// Whether we expect `post`'s position to follow from its velocity.
+fn is_free_flight(post: &Post) -> bool {
+	post.airborne == Some(true)
+		&& post.hitlag.is_none_or(|h| h == 0.0)
+		&& !post.is_dead()
+		&& !post.is_respawn()
+		&& !post.is_grabbed()
+		// scripted descent onto the stage at the start of the game
+		&& !matches!(post.state, State::Common(Common::ENTRY | Common::ENTRY_START | Common::ENTRY_END))
+}
+
+/// Frames on which the velocity of the player in `port` (leader only) diverges from
+/// its change in position by more than `TOLERANCE`, which may indicate corrupted frame
+/// data. Empty before v3.5, which added velocities.
+///
+/// A character's position on each frame is its previous position plus its stored
+/// (autogenous + knockback) velocity, except where something else moves it. So we
+/// only check frames spent airborne (on this frame & the one before), skipping hitlag
+/// (where velocity is kept but position frozen), death, respawn, being held & the
+/// entry animation. Divergences may still legitimately come from:
+///
+/// * collisions with walls, ceilings & ledges, which stop movement short
+/// * pushes from other characters' bodies
+/// * hitlag before v3.8 (which added `Post::hitlag`): we skip frames with no movement
+///   at all, but not the frames where hitlag starts or ends
+/// * moves that reposition the character directly, such as teleports
+pub fn velocity_consistency(game: &Game, port: Port) -> Vec<VelocityAnomaly> {
+	let opts = Some(PortFramesOpts { finalized_only: true, ..Default::default() });
+	let frames: Vec<_> = game.port_frames(port, opts).into_iter().flatten()
+		.filter(|f| !f.is_follower)
+		.collect();
+
+	frames.windows(2).filter_map(|w| {
+		let (prev, cur) = (&w[0].data.post, &w[1].data.post);
+		let v = cur.velocities?;
+		if !is_free_flight(prev) || !is_free_flight(cur) {
+			return None;
+		}
+		let delta = Velocity {
+			x: cur.position.x - prev.position.x,
+			y: cur.position.y - prev.position.y,
+		};
+		if delta == Velocity::default() {
+			return None;
+		}
+		let anomaly = VelocityAnomaly {
+			index: w[1].index,
+			velocity: Velocity {
+				x: v.autogenous.x + v.knockback.x,
+				y: v.autogenous.y + v.knockback.y,
+			},
+			delta: delta,
+		};
+		(anomaly.divergence() > TOLERANCE).then_some(anomaly)
+	}).collect()
+}
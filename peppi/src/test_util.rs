@@ -0,0 +1,207 @@
+//! Helpers for testing code that uses peppi (requires the `testing` feature).
+
+use serde_json::json;
+
+use crate::{
+	model::{
+		buttons,
+		enums::{action_state, character, ground, stage},
+		frame::{self, DynFrame, StateFlags},
+		game::{self, End, EndMethod, Frames, Game, Player, PlayerType, Start, FIRST_FRAME_INDEX, NORMAL_HANDICAP},
+		metadata,
+		primitives::{Direction, Port, Position, Velocity},
+		slippi::{self, Slippi, Version},
+		triggers,
+	},
+	serde::ser,
+};
+
+/// Index of the last frame of a `minimal_game` ("Go!").
+pub const MINIMAL_GAME_LAST_FRAME: i32 = 0;
+
+fn player(v: Version, port: Port, character: character::External) -> Player {
+	Player {
+		port: port,
+		character: character,
+		r#type: PlayerType::HUMAN,
+		stocks: 4,
+		costume: 0,
+		team: None,
+		handicap: NORMAL_HANDICAP,
+		bitfield: 192,
+		cpu_level: None,
+		offense_ratio: 1.0,
+		defense_ratio: 1.0,
+		model_scale: 1.0,
+		ucf: (v >= Version::UCF).then(game::Ucf::default),
+		name_tag: (v >= Version::NAME_TAGS).then(String::new),
+		netplay: (v >= Version::NETPLAY_NAMES).then(|| game::Netplay {
+			name: String::new(),
+			code: String::new(),
+			suid: (v >= Version::SUID).then(String::new),
+		}),
+	}
+}
+
+fn start(v: Version, stage: stage::Stage, characters: &[character::External]) -> Start {
+	let mut start = Start {
+		slippi: Slippi { version: v },
+		bitfield: [50, 1, 142, 76],
+		is_raining_bombs: false,
+		is_teams: false,
+		item_spawn_frequency: game::ItemFrequency::OFF.0,
+		self_destruct_score: -1,
+		stage: stage,
+		timer: 480,
+		item_spawn_bitfield: [255; 5],
+		damage_ratio: 1.0,
+		players: characters.iter().enumerate()
+			.map(|(n, c)| player(v, Port::try_from(n as u8).unwrap(), *c))
+			.collect(),
+		random_seed: 0,
+		raw_bytes: vec![0; ser::game_start_size(v)],
+		is_pal: (v >= Version::PAL).then_some(false),
+		is_frozen_ps: (v >= slippi::version(2, 0)).then_some(false),
+		scene: (v >= slippi::version(3, 7)).then_some(game::Scene { minor: 2, major: 2 }),
+		language: (v >= Version::LANGUAGE).then_some(game::Language::ENGLISH),
+		r#match: (v >= Version::MATCH_INFO).then(game::Match::default),
+	};
+	let mut raw = std::mem::take(&mut start.raw_bytes);
+	ser::game_start_bytes(&mut raw, &start).unwrap();
+	start.raw_bytes = raw;
+	start
+}
+
+/// A character standing still in its entry animation.
+fn data(v: Version, character: character::Internal, position: Position, age: usize, is_follower: bool) -> frame::Data {
+	let state = action_state::State::Common(action_state::Common::ENTRY);
+	let direction = match position.x > 0.0 {
+		true => Direction::Left,
+		_ => Direction::Right,
+	};
+	frame::Data {
+		pre: frame::Pre {
+			position: position,
+			direction: direction,
+			joystick: Position::default(),
+			cstick: Position::default(),
+			triggers: frame::Triggers {
+				logical: 0.0,
+				physical: triggers::Physical { l: 0.0, r: 0.0 },
+			},
+			random_seed: 0,
+			buttons: frame::Buttons {
+				logical: buttons::Logical(0),
+				physical: buttons::Physical(0),
+			},
+			state: state,
+			raw_analog_x: (v >= slippi::version(1, 2)).then_some(0),
+			damage: (v >= slippi::version(1, 4)).then_some(0.0),
+		},
+		post: frame::Post {
+			character: character,
+			state: state,
+			position: position,
+			direction: direction,
+			damage: 0.0,
+			shield: 60.0,
+			last_attack_landed: None,
+			combo_count: 0,
+			last_hit_by: None,
+			stocks: 4,
+			state_age: (v >= slippi::version(0, 2)).then_some(age as f32),
+			flags: (v >= slippi::version(2, 0)).then_some(match is_follower {
+				true => StateFlags::FOLLOWER,
+				_ => StateFlags(0),
+			}),
+			misc_as: (v >= slippi::version(2, 0)).then_some(0.0),
+			airborne: (v >= slippi::version(2, 0)).then_some(true),
+			ground: (v >= slippi::version(2, 0)).then_some(ground::Ground(u16::MAX)),
+			jumps: (v >= slippi::version(2, 0)).then_some(1),
+			l_cancel: (v >= slippi::version(2, 0)).then_some(None),
+			hurtbox_state: (v >= slippi::version(2, 1)).then_some(frame::HurtboxState::VULNERABLE),
+			velocities: (v >= slippi::version(3, 5)).then_some(frame::Velocities {
+				autogenous: Velocity::default(),
+				knockback: Velocity::default(),
+				autogenous_x: frame::AutogenousXVelocity { air: 0.0, ground: 0.0 },
+			}),
+			hitlag: (v >= slippi::version(3, 8)).then_some(0.0),
+			animation_index: (v >= Version::SUID).then_some(0),
+		},
+	}
+}
+
+fn frames(v: Version, characters: &[character::External]) -> Vec<DynFrame> {
+	(FIRST_FRAME_INDEX ..= MINIMAL_GAME_LAST_FRAME).enumerate().map(|(age, index)| DynFrame {
+		index: index,
+		ports: characters.iter().enumerate().map(|(n, c)| {
+			// spread the players out evenly between x = -40 & 40
+			let x = match characters.len() {
+				1 => 0.0,
+				len => -40.0 + 80.0 * n as f32 / (len - 1) as f32,
+			};
+			let position = Position { x: x, y: 32.0 };
+			let leader = c.internal().expect("unknown character");
+			frame::PortData {
+				leader: data(v, leader, position, age, false),
+				follower: (leader == character::Internal::POPO).then(||
+					Box::new(data(v, character::Internal::NANA, position, age, true))),
+			}
+		}).collect(),
+		start: (v >= Version::ROLLBACK).then_some(frame::Start {
+			random_seed: 0,
+			scene_frame_counter: (v >= slippi::version(3, 10)).then_some(age as u32),
+		}),
+		end: (v >= Version::ITEMS).then_some(frame::End {
+			latest_finalized_frame: (v >= Version::FINALIZED_FRAMES).then_some(index),
+		}),
+		// as parsed, even before items were recorded
+		items: Some(Vec::new()),
+	}).collect()
+}
+
+/// A short but valid game in Slippi version `version`, for tests: `characters` (one per
+/// port, starting at P1) spawn on `stage` and stand still until "Go!" (frame index
+/// `MINIMAL_GAME_LAST_FRAME`), when the first player LRASes. Every field the version
+/// requires is filled in, so the game serializes cleanly and parses back unchanged.
+///
+/// Panics unless there are 1 to 4 `characters`, all of them known.
+pub fn minimal_game(version: Version, stage: stage::Stage, characters: &[character::External]) -> Game {
+	let frames = Frames::from_port_count(characters.len(), frames(version, characters))
+		.expect("need 1 to 4 characters");
+
+	let players: serde_json::Map<String, serde_json::Value> = characters.iter().enumerate()
+		.map(|(n, c)| {
+			let leader = c.internal().unwrap();
+			let mut characters = serde_json::Map::new();
+			// Nana is listed too (first), as in real replays
+			if leader == character::Internal::POPO {
+				characters.insert(character::Internal::NANA.0.to_string(), json!(frames.len()));
+			}
+			characters.insert(leader.0.to_string(), json!(frames.len()));
+			(n.to_string(), json!({ "characters": characters }))
+		})
+		.collect();
+	let metadata_raw = match json!({
+		"startAt": "2001-11-21T00:00:00Z",
+		"lastFrame": MINIMAL_GAME_LAST_FRAME,
+		"players": players,
+		"playedOn": "dolphin",
+	}) {
+		serde_json::Value::Object(map) => map,
+		_ => unreachable!(),
+	};
+
+	Game {
+		start: start(version, stage, characters),
+		end: End {
+			method: EndMethod::NO_CONTEST,
+			lras_initiator: (version >= slippi::version(2, 0)).then_some(Some(Port::P1)),
+		},
+		frames: frames,
+		metadata: metadata::parse(&metadata_raw).unwrap(),
+		metadata_raw: metadata_raw,
+		gecko_codes: None,
+		truncated: false,
+	}
+}
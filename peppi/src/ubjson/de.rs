@@ -1,43 +1,161 @@
 use std::io::{Read, Result, Error, ErrorKind};
 
 use byteorder::{BigEndian, ReadBytesExt};
-use serde_json::{Map, Value};
+use serde_json::{Map, Number, Value};
 
-fn to_utf8<R: Read>(r: &mut R) -> Result<String> {
-	let length = r.read_u8()?;
-	let mut buf = vec![0; length as usize];
+/// Reads an integer of the type given by `marker`.
+fn to_int<R: Read>(r: &mut R, marker: u8) -> Result<Option<i64>> {
+	Ok(Some(match marker {
+		// "i": i8
+		0x69 => r.read_i8()? as i64,
+		// "U": u8
+		0x55 => r.read_u8()? as i64,
+		// "I": i16
+		0x49 => r.read_i16::<BigEndian>()? as i64,
+		// "l": i32
+		0x6c => r.read_i32::<BigEndian>()? as i64,
+		// "L": i64
+		0x4c => r.read_i64::<BigEndian>()?,
+		_ => return Ok(None),
+	}))
+}
+
+fn to_len<R: Read>(r: &mut R, marker: u8) -> Result<usize> {
+	match to_int(r, marker)? {
+		Some(len) => usize::try_from(len).map_err(|_| err!("invalid UBJSON length: {}", len)),
+		None => Err(err!("expected UBJSON integer for length, but got: {}", marker)),
+	}
+}
+
+/// Reads a string whose length is of the type given by `marker`.
+fn to_utf8<R: Read>(r: &mut R, marker: u8) -> Result<String> {
+	let length = to_len(r, marker)?;
+	let mut buf = vec![0; length];
 	r.read_exact(&mut buf)?;
 	String::from_utf8(buf).map_err(|e| Error::new(ErrorKind::InvalidData, e))
 }
 
-fn to_val<R: Read>(r: &mut R) -> Result<Value> {
-	match r.read_u8()? {
+fn float(f: f64) -> Value {
+	Number::from_f64(f).map(Value::Number).unwrap_or(Value::Null)
+}
+
+/// Reads a value of the type given by `marker`.
+fn to_val_of<R: Read>(r: &mut R, marker: u8) -> Result<Value> {
+	if let Some(i) = to_int(r, marker)? {
+		return Ok(Value::Number(Number::from(i)));
+	}
+	match marker {
+		// "Z": null
+		0x5a => Ok(Value::Null),
+		// "T"/"F": bool
+		0x54 => Ok(Value::Bool(true)),
+		0x46 => Ok(Value::Bool(false)),
+		// "d": f32
+		0x64 => Ok(float(r.read_f32::<BigEndian>()? as f64)),
+		// "D": f64
+		0x44 => Ok(float(r.read_f64::<BigEndian>()?)),
+		// "H": high-precision number, as a string
+		0x48 => {
+			let marker = r.read_u8()?;
+			let s = to_utf8(r, marker)?;
+			s.parse::<Number>().map(Value::Number)
+				.map_err(|e| err!("invalid UBJSON high-precision number: {:?}, {:?}", s, e))
+		},
+		// "C": char
+		0x43 => Ok(Value::String((r.read_u8()? as char).to_string())),
 		// "S": str
-		0x53 => match r.read_u8()? {
-			0x55 => Ok(Value::String(to_utf8(r)?)),
-			c => Err(err!("Expected 0x55 for string length, but got: {}", c)),
+		0x53 => {
+			let marker = r.read_u8()?;
+			Ok(Value::String(to_utf8(r, marker)?))
 		},
-		// "l": i32
-		0x6c => Ok(Value::Number(serde_json::Number::from(r.read_i32::<BigEndian>()?))),
+		// "[": array
+		0x5b => Ok(Value::Array(to_array(r)?)),
 		// "{": map
 		0x7b => Ok(Value::Object(to_map(r)?)),
 		c => Err(err!("unexpected UBJSON value type: {}", c)),
 	}
 }
 
-fn to_key<R: Read>(r: &mut R) -> Result<Option<String>> {
-	match r.read_u8()? {
-		0x55 => Ok(Some(to_utf8(r)?)),
-		0x7d => Ok(None),
-		c => Err(err!("unexpected UBJSON key type: {}", c)),
+/// Reads a type marker, skipping no-ops ("N").
+fn to_marker<R: Read>(r: &mut R) -> Result<u8> {
+	loop {
+		match r.read_u8()? {
+			0x4e => continue,
+			c => return Ok(c),
+		}
+	}
+}
+
+fn to_val<R: Read>(r: &mut R) -> Result<Value> {
+	let marker = to_marker(r)?;
+	to_val_of(r, marker)
+}
+
+/// Handles the optional type ("$") & count ("#") headers of an optimized container.
+/// Returns the element type (if fixed) and count (if given), plus the first marker
+/// of the (unoptimized) body, if any.
+fn container_header<R: Read>(r: &mut R) -> Result<(Option<u8>, Option<usize>, Option<u8>)> {
+	let mut marker = to_marker(r)?;
+	let r#type = match marker {
+		// "$": element type
+		0x24 => {
+			let r#type = r.read_u8()?;
+			marker = r.read_u8()?;
+			if marker != 0x23 {
+				return Err(err!("expected UBJSON count after type, but got: {}", marker));
+			}
+			Some(r#type)
+		},
+		_ => None,
+	};
+	match marker {
+		// "#": element count
+		0x23 => {
+			let marker = r.read_u8()?;
+			Ok((r#type, Some(to_len(r, marker)?), None))
+		},
+		_ => Ok((None, None, Some(marker))),
+	}
+}
+
+/// Reads the rest of an array, after the opening "[".
+pub fn to_array<R: Read>(r: &mut R) -> Result<Vec<Value>> {
+	let mut a = Vec::new();
+	match container_header(r)? {
+		(r#type, Some(count), _) => for _ in 0 .. count {
+			a.push(match r#type {
+				Some(t) => to_val_of(r, t)?,
+				None => to_val(r)?,
+			});
+		},
+		(_, None, Some(mut marker)) => while marker != 0x5d { // "]"
+			a.push(to_val_of(r, marker)?);
+			marker = to_marker(r)?;
+		},
+		_ => unreachable!(),
 	}
+	Ok(a)
 }
 
+/// Reads the rest of a map, after the opening "{".
 pub fn to_map<R: Read>(r: &mut R) -> Result<Map<String, Value>> {
 	let mut m = Map::new();
-	while match to_key(r)? {
-		Some(k) => {m.insert(k, to_val(r)?); true},
-		None => false,
-	} {}
+	match container_header(r)? {
+		(r#type, Some(count), _) => for _ in 0 .. count {
+			let marker = to_marker(r)?;
+			let k = to_utf8(r, marker)?;
+			let v = match r#type {
+				Some(t) => to_val_of(r, t)?,
+				None => to_val(r)?,
+			};
+			m.insert(k, v);
+		},
+		(_, None, Some(mut marker)) => while marker != 0x7d { // "}"
+			let k = to_utf8(r, marker)?;
+			m.insert(k, to_val(r)?);
+			marker = to_marker(r)?;
+		},
+		_ => unreachable!(),
+	}
 	Ok(m)
 }
@@ -1,35 +1,77 @@
 use std::io::{Write, Result};
 
 use byteorder::{BigEndian, WriteBytesExt};
-use serde_json::{Map, Value};
+use serde_json::{Map, Number, Value};
 
 fn write_utf8<W: Write>(w: &mut W, s: &str) -> Result<()> {
-	//FIXME: length in bytes?
-	write!(w, "U")?;
-	w.write_u8(s.len().try_into().unwrap())?;
+	match u8::try_from(s.len()) {
+		Ok(len) => {
+			write!(w, "U")?;
+			w.write_u8(len)?;
+		},
+		_ => {
+			write!(w, "l")?;
+			w.write_i32::<BigEndian>(s.len().try_into().map_err(|_| err!("UBJSON string too long: {}", s.len()))?)?;
+		},
+	}
 	write!(w, "{}", s)?;
 	Ok(())
 }
 
-pub fn from_map<W: Write>(w: &mut W, map: &Map<String, Value>) -> Result<()> {
-	for (k, v) in map {
-		write_utf8(w, k)?;
-		match v {
-			Value::String(s) => {
-				write!(w, "S")?;
-				write_utf8(w, s)?;
-			},
-			Value::Number(n) => {
+fn write_number<W: Write>(w: &mut W, n: &Number) -> Result<()> {
+	if let Some(i) = n.as_i64() {
+		match i32::try_from(i) {
+			Ok(i) => {
 				write!(w, "l")?;
-				w.write_i32::<BigEndian>(n.as_i64().unwrap().try_into().unwrap())?;
+				w.write_i32::<BigEndian>(i)?;
+			},
+			_ => {
+				write!(w, "L")?;
+				w.write_i64::<BigEndian>(i)?;
 			},
-			Value::Object(o) => {
-				write!(w, "{{")?;
-				from_map(w, o)?;
-				write!(w, "}}")?;
-			}
-			_ => unimplemented!(),
 		}
+	} else if n.is_u64() {
+		// too big for any UBJSON integer type
+		write!(w, "H")?;
+		write_utf8(w, &n.to_string())?;
+	} else {
+		write!(w, "D")?;
+		w.write_f64::<BigEndian>(n.as_f64().unwrap())?;
+	}
+	Ok(())
+}
+
+fn from_val<W: Write>(w: &mut W, v: &Value) -> Result<()> {
+	match v {
+		Value::Null => write!(w, "Z")?,
+		Value::Bool(true) => write!(w, "T")?,
+		Value::Bool(false) => write!(w, "F")?,
+		Value::Number(n) => write_number(w, n)?,
+		Value::String(s) => {
+			write!(w, "S")?;
+			write_utf8(w, s)?;
+		},
+		Value::Array(a) => {
+			write!(w, "[")?;
+			for v in a {
+				from_val(w, v)?;
+			}
+			write!(w, "]")?;
+		},
+		Value::Object(o) => {
+			write!(w, "{{")?;
+			from_map(w, o)?;
+			write!(w, "}}")?;
+		},
+	}
+	Ok(())
+}
+
+/// Writes the contents of `map`, without the surrounding braces.
+pub fn from_map<W: Write>(w: &mut W, map: &Map<String, Value>) -> Result<()> {
+	for (k, v) in map {
+		write_utf8(w, k)?;
+		from_val(w, v)?;
 	}
 	Ok(())
 }
@@ -1,4 +1,4 @@
-use std::{collections::HashMap, fs, io};
+use std::{collections::{HashMap, HashSet}, fs, io, path::Path, time::Duration};
 
 use chrono::{DateTime, Utc};
 
@@ -6,18 +6,21 @@ use peppi::{
 	model::{
 		buttons::{Logical, Physical},
 		enums::{
-			action_state::{State, Zelda},
-			character::{Internal, External},
+			action_state::{Common, Fox, State, Zelda},
+			attack::Attack,
+			character::{CostumeColor, Internal, External},
 			item,
+			special_move::SpecialMove,
 			stage::Stage,
 		},
-		frame::Buttons,
-		game::{DashBack, End, EndMethod, Frames, Game, Language, Netplay, Player, PlayerType, Scene, Start, ShieldDrop, Ucf},
+		frame::{self, Buttons, ControllerState, DynFrame, Side, StickRegion, StickZone},
+		game::{self, DashBack, End, EndMethod, FrameRow, FrameVisitor, Frames, Game, GameClock, GameHeader, ItemFrequency, Language, Match, MatchSettings, MeaningfulOpts, NUM_SPAWNABLE_ITEMS, Netplay, Player, PlayerSettings, PlayerType, PortFramesOpts, RankedInfo, Scene, Start, ShieldDrop, Team, TeamColor, TeamShade, TimerType, Ucf, Warning, duplicates, is_same_game},
 		item::Item,
 		metadata::{self, Metadata},
 		primitives::{Direction, Port, Position, Velocity},
 		slippi::{Slippi, Version},
 	},
+	comm,
 	serde,
 };
 
@@ -31,6 +34,25 @@ fn game(name: &str) -> Result<Game, String> {
 	read_game(&format!("tests/data/{}.slp", name))
 }
 
+/// Serializes `game` & parses the result.
+fn round_trip_game(game: &Game) -> Result<Game, String> {
+	let bytes = serde::ser::serialize_to_vec(game).map_err(|e| format!("couldn't serialize game: {:?}", e))?;
+	peppi::game(&mut &bytes[..], None, None).map_err(|e| format!("couldn't deserialize game: {:?}", e))
+}
+
+/// Game `name` with frames 5-9 (the 6th-10th) rolled back & replayed.
+fn rolled_back(name: &str) -> Result<Game, String> {
+	let mut game = game(name)?;
+	match &mut game.frames {
+		Frames::P2(frames) => {
+			let replayed: Vec<_> = frames[5 .. 10].to_vec();
+			frames.splice(10 .. 10, replayed);
+		},
+		_ => Err("wrong number of ports")?,
+	}
+	Ok(game)
+}
+
 fn button_seq(game:&Game) -> Result<Vec<Buttons>, String> {
 	match &game.frames {
 		Frames::P2(frames) => {
@@ -117,7 +139,7 @@ fn basic_game() -> Result<(), String> {
 				handicap: 9,
 				bitfield: 192,
 				cpu_level: None,
-				offense_ratio: 0.0,
+				offense_ratio: 1.0,
 				defense_ratio: 1.0,
 				model_scale: 1.0,
 				ucf: Some(Ucf {
@@ -137,7 +159,7 @@ fn basic_game() -> Result<(), String> {
 				handicap: 9,
 				bitfield: 64,
 				cpu_level: Some(1),
-				offense_ratio: 0.0,
+				offense_ratio: 1.0,
 				defense_ratio: 1.0,
 				model_scale: 1.0,
 				ucf: Some(Ucf {
@@ -153,6 +175,7 @@ fn basic_game() -> Result<(), String> {
 		is_frozen_ps: None,
 		scene: None,
 		language: None,
+		r#match: None,
 		raw_bytes: vec![
 			1, 0, 0, 0, 50, 1, 134, 76, 195, 0, 0, 0, 0, 0, 0, 255, 255, 110, 0, 8, 0, 0, 1, 224, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 255, 255, 255, 255, 255, 255, 255, 255, 0, 0, 0, 0, 63, 128, 0, 0, 63, 128, 0, 0, 63, 128, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 9, 0, 4, 3, 0, 0, 0, 0, 9, 0, 120, 0, 192, 0, 4, 1, 0, 0, 0, 0, 0, 0, 0, 0, 63, 128, 0, 0, 63, 128, 0, 0, 63, 128, 0, 0, 2, 1, 4, 0, 0, 1, 0, 0, 9, 0, 120, 0, 64, 0, 4, 1, 0, 0, 0, 0, 0, 0, 0, 0, 63, 128, 0, 0, 63, 128, 0, 0, 63, 128, 0, 0, 26, 3, 4, 0, 0, 255, 0, 0, 9, 0, 120, 0, 64, 0, 4, 1, 0, 0, 0, 0, 0, 0, 0, 0, 63, 128, 0, 0, 63, 128, 0, 0, 63, 128, 0, 0, 26, 3, 4, 0, 0, 255, 0, 0, 9, 0, 120, 0, 64, 0, 4, 1, 0, 0, 0, 0, 0, 0, 0, 0, 63, 128, 0, 0, 63, 128, 0, 0, 63, 128, 0, 0, 33, 3, 4, 0, 0, 255, 0, 0, 9, 0, 120, 0, 64, 0, 4, 0, 0, 0, 0, 0, 0, 0, 0, 0, 63, 128, 0, 0, 63, 128, 0, 0, 63, 128, 0, 0, 33, 3, 4, 0, 0, 255, 0, 0, 9, 0, 120, 0, 64, 0, 4, 0, 0, 0, 0, 0, 0, 0, 0, 0, 63, 128, 0, 0, 63, 128, 0, 0, 63, 128, 0, 0, 226, 176, 35, 114, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0
 		],
@@ -203,6 +226,33 @@ fn ics() -> Result<(), String> {
 	Ok(())
 }
 
+#[test]
+fn ics_port_frames() -> Result<(), String> {
+	let game = game("ics")?;
+	let frame_count = game.frames.len();
+
+	let separate: Vec<_> = game.port_frames(Port::P1, None).ok_or("missing P1")?.collect();
+	assert_eq!(separate.len(), frame_count * 2);
+	assert!(!separate[0].is_follower);
+	assert!(separate[1].is_follower);
+	assert_eq!(separate[0].index, separate[1].index);
+	assert!(separate.iter().all(|f| f.follower.is_none()));
+
+	let merged: Vec<_> = game.port_frames(Port::P1, Some(PortFramesOpts { merge_followers: true, ..Default::default() }))
+		.ok_or("missing P1")?.collect();
+	assert_eq!(merged.len(), frame_count);
+	assert!(merged.iter().all(|f| !f.is_follower && f.follower.is_some()));
+	assert_eq!(merged[0].follower, Some(separate[1].data));
+
+	let jiggs: Vec<_> = game.port_frames(Port::P2, Some(PortFramesOpts { merge_followers: true, ..Default::default() }))
+		.ok_or("missing P2")?.collect();
+	assert_eq!(jiggs.len(), frame_count);
+	assert!(jiggs.iter().all(|f| f.follower.is_none()));
+
+	assert!(game.port_frames(Port::P3, None).is_none());
+	Ok(())
+}
+
 #[test]
 fn ucf() -> Result<(), String> {
 	assert_eq!(game("shield_drop")?.start.players[0].ucf,
@@ -363,6 +413,82 @@ fn console_name() -> Result<(), String> {
 	Ok(())
 }
 
+#[test]
+fn platform_and_console_round_trip() -> Result<(), String> {
+	for (name, platform, console) in [
+		("game", Some("dolphin"), None),
+		("console_name", Some("nintendont"), Some("Station 1")),
+	] {
+		let game = game(name)?;
+		assert_eq!(game.metadata.platform.as_deref(), platform, "{}", name);
+		assert_eq!(game.metadata.console.as_deref(), console, "{}", name);
+
+		let game2 = round_trip_game(&game)?;
+		assert_eq!(game2.metadata.platform, game.metadata.platform, "{}", name);
+		assert_eq!(game2.metadata.console, game.metadata.console, "{}", name);
+	}
+	Ok(())
+}
+
+#[test]
+fn set_metadata() -> Result<(), String> {
+	let mut game = game("game")?;
+	assert_eq!(game.get_metadata("playedOn"), Some(&serde_json::json!("dolphin")));
+	assert_eq!(game.get_metadata("custom"), None);
+
+	game.set_metadata("custom", serde_json::json!({"tournament": "Genesis"})).map_err(|e| e.to_string())?;
+	game.set_metadata("playedOn", serde_json::json!("nintendont")).map_err(|e| e.to_string())?;
+	assert_eq!(game.metadata.platform.as_deref(), Some("nintendont"));
+	assert!(game.set_metadata("startAt", serde_json::json!(1)).is_err());
+	assert_eq!(game.get_metadata("startAt"), Some(&serde_json::json!("2018-06-22T07:52:59Z")));
+
+	let game2 = round_trip_game(&game)?;
+	assert_eq!(game2.get_metadata("custom"), Some(&serde_json::json!({"tournament": "Genesis"})));
+	assert_eq!(game2.metadata.platform.as_deref(), Some("nintendont"));
+	assert_eq!(game2.metadata_raw.keys().collect::<Vec<_>>(),
+		vec!["startAt", "lastFrame", "players", "playedOn", "custom"]);
+
+	Ok(())
+}
+
+#[test]
+fn video_offset() -> Result<(), String> {
+	let mut game = game("game")?;
+	assert_eq!(game.video_offset(), None);
+	assert_eq!(game.video_frame(0), None);
+
+	game.set_video_offset(300);
+	assert_eq!(game.video_frame(-123), Some(300));
+	assert_eq!(game.video_frame(0), Some(423));
+	assert_eq!(game.video_frame(5085), Some(5508));
+
+	let mut game2 = round_trip_game(&game)?;
+	assert_eq!(game2.video_offset(), Some(300));
+
+	game2.set_video_offset(-200);
+	assert_eq!(game2.video_frame(77), Some(0));
+	Ok(())
+}
+
+#[test]
+fn retime() -> Result<(), String> {
+	let mut game = game("game")?;
+	match &mut game.frames {
+		Frames::P2(frames) => frames.truncate(1000),
+		_ => Err("wrong number of ports")?,
+	}
+	let start_at: DateTime<Utc> = "2024-02-29T12:34:56.789Z".parse().unwrap();
+	game.retime(start_at);
+	assert_eq!(game.get_metadata("startAt"), Some(&serde_json::json!("2024-02-29T12:34:56Z")));
+	assert_eq!(game.get_metadata("lastFrame"), Some(&serde_json::json!(876)));
+
+	let game2 = round_trip_game(&game)?;
+	assert_eq!(game2.metadata.date, Some("2024-02-29T12:34:56Z".parse().unwrap()));
+	assert_eq!(game2.metadata.duration, Some(1000));
+	assert_eq!(game2.metadata, game.metadata);
+	Ok(())
+}
+
 #[test]
 fn v2() -> Result<(), String> {
 	let game = game("v2.0")?;
@@ -370,6 +496,42 @@ fn v2() -> Result<(), String> {
 	Ok(())
 }
 
+#[test]
+fn version_constants() {
+	assert_eq!(Version::UCF, Version(1, 0, 0));
+	assert_eq!(Version::NAME_TAGS, Version(1, 3, 0));
+	assert_eq!(Version::PAL, Version(1, 5, 0));
+	assert_eq!(Version::ROLLBACK, Version(2, 2, 0));
+	assert_eq!(Version::ITEMS, Version(3, 0, 0));
+	assert_eq!(Version::FINALIZED_FRAMES, Version(3, 7, 0));
+	assert_eq!(Version::NETPLAY_NAMES, Version(3, 9, 0));
+	assert_eq!(Version::SUID, Version(3, 11, 0));
+	assert_eq!(Version::LANGUAGE, Version(3, 12, 0));
+	assert!(Version(3, 9, 1) >= Version::NETPLAY_NAMES);
+	assert!(Version(2, 1, 9) < Version::ROLLBACK);
+}
+
+#[test]
+fn version_predicates() {
+	let at_boundary = |has: fn(Version) -> bool, since: Version| {
+		let Version(major, minor, _) = since;
+		assert!(has(since), "{}", since);
+		assert!(has(Version(major, minor, 3)), "{}", since);
+		assert!(!has(match minor {
+			0 => Version(major - 1, 99, 0),
+			_ => Version(major, minor - 1, 9),
+		}), "{}", since);
+	};
+	at_boundary(Version::has_hurtbox_state, Version(2, 1, 0));
+	at_boundary(Version::has_rollback, Version(2, 2, 0));
+	at_boundary(Version::has_items, Version(3, 0, 0));
+	at_boundary(Version::has_hitlag, Version(3, 8, 0));
+	at_boundary(Version::has_netplay_names, Version(3, 9, 0));
+
+	assert!(Version(0, 1, 0) < Version::HURTBOX_STATE);
+	assert!(Version(3, 14, 0).has_hitlag());
+}
+
 #[test]
 fn v3_12() -> Result<(), String> {
 	let game = game("v3.12")?;
@@ -396,7 +558,7 @@ fn v3_12() -> Result<(), String> {
 				handicap: 9,
 				bitfield: 192,
 				cpu_level: None,
-				offense_ratio: 0.0,
+				offense_ratio: 1.0,
 				defense_ratio: 1.0,
 				model_scale: 1.0,
 				ucf: Some(Ucf {
@@ -420,7 +582,7 @@ fn v3_12() -> Result<(), String> {
 				handicap: 9,
 				bitfield: 192,
 				cpu_level: None,
-				offense_ratio: 0.0,
+				offense_ratio: 1.0,
 				defense_ratio: 1.0,
 				model_scale: 1.0,
 				ucf: Some(Ucf {
@@ -443,7 +605,8 @@ fn v3_12() -> Result<(), String> {
 			minor: 2,
 			major: 8
 		}),
-		language: Some(Language::ENGLISH)
+		language: Some(Language::ENGLISH),
+		r#match: None,
 	});
 
 	Ok(())
@@ -466,6 +629,25 @@ fn zelda_sheik_transformation() -> Result<(), String> {
 	Ok(())
 }
 
+#[test]
+fn transformations() -> Result<(), String> {
+	let game = game("transform")?;
+	assert_eq!(game.external_character(Port::P4), Some(External::ZELDA));
+	assert_eq!(game.transformations(Port::P4), vec![(278, Internal::SHEIK)]);
+
+	let frame = |index: i32| game.port_frames(Port::P4, None).unwrap().find(|f| f.index == index).unwrap();
+	assert_eq!(frame(277).data.post.character, Internal::ZELDA);
+	assert_eq!(frame(278).data.post.character, Internal::SHEIK);
+
+	assert_eq!(game.external_character(Port::P2), Some(External::CAPTAIN_FALCON));
+	assert_eq!(game.transformations(Port::P2), vec![]);
+	assert_eq!(game.external_character(Port::P1), None);
+
+	// Popo & Nana aren't a transformation
+	assert_eq!(self::game("ics")?.transformations(Port::P1), vec![]);
+	Ok(())
+}
+
 #[test]
 fn items() -> Result<(), String> {
 	let game = game("items")?;
@@ -521,6 +703,44 @@ fn items() -> Result<(), String> {
 	Ok(())
 }
 
+#[test]
+fn item_fuse() -> Result<(), String> {
+	let game = game("items")?;
+	let turnip = (0 .. game.frames.len())
+		.find_map(|n| game.frames.items(n).and_then(|i| i.first().copied()))
+		.ok_or("missing turnip")?;
+	assert_eq!(turnip.r#type, item::Type::PEACH_TURNIP);
+	assert_eq!(turnip.fuse_remaining(), None);
+
+	let bob_omb = Item { r#type: item::Type::BOB_OMB, timer: 42.0, damage: 7, ..turnip };
+	assert_eq!(bob_omb.fuse_remaining(), Some(42.0));
+	assert_eq!(Item { r#type: item::Type::LINK_BOMB, ..bob_omb }.fuse_remaining(), Some(42.0));
+	Ok(())
+}
+
+#[test]
+fn item_charge() -> Result<(), String> {
+	let game = game("items")?;
+	let turnip = (0 .. game.frames.len())
+		.find_map(|n| game.frames.items(n).and_then(|i| i.first().copied()))
+		.ok_or("missing turnip")?;
+	assert!(!turnip.is_charged_projectile());
+	assert_eq!(turnip.charge(), None);
+
+	// the charge is kept by Sheik, not her needles
+	let needle = Item { r#type: item::Type::SHEIK_NEEDLE_1, misc: Some([0, 0, 1, 4]), ..turnip };
+	assert!(!needle.is_charged_projectile());
+	assert_eq!((needle.charge(), needle.is_launched()), (None, None));
+
+	let charge_shot = Item { r#type: item::Type::SAMUS_CHARGE_SHOT, ..needle };
+	assert!(charge_shot.is_charged_projectile());
+	assert_eq!((charge_shot.charge(), charge_shot.is_launched()), (Some(4), Some(true)));
+	let shadow_ball = Item { r#type: item::Type::MEWTWO_SHADOW_BALL, misc: Some([0, 0, 0, 2]), ..turnip };
+	assert_eq!((shadow_ball.charge(), shadow_ball.is_launched()), (Some(2), Some(false)));
+	assert_eq!(Item { misc: None, ..shadow_ball }.charge(), None);
+	Ok(())
+}
+
 #[test]
 fn round_trip() -> Result<(), String> {
 	let game1 = game("v2.0")?;
@@ -546,3 +766,2230 @@ fn round_trip() -> Result<(), String> {
 
 	Ok(())
 }
+
+/// Reader that refuses to seek, like a pipe.
+struct NonSeekable<'a>(&'a [u8]);
+
+impl io::Read for NonSeekable<'_> {
+	fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+		self.0.read(buf)
+	}
+}
+
+impl io::Seek for NonSeekable<'_> {
+	fn seek(&mut self, _: io::SeekFrom) -> io::Result<u64> {
+		Err(io::Error::new(io::ErrorKind::Unsupported, "not seekable"))
+	}
+}
+
+#[test]
+fn non_seekable() -> Result<(), String> {
+	let bytes = fs::read("tests/data/game.slp").unwrap();
+
+	let game = peppi::game(&mut NonSeekable(&bytes), None, None)
+		.map_err(|e| format!("couldn't deserialize game: {:?}", e))?;
+	assert_eq!(game.frames.len(), 5209);
+
+	let skip_frames = Some(serde::de::Opts { skip_frames: true, ..Default::default() });
+	let game = peppi::game(&mut NonSeekable(&bytes), skip_frames, None)
+		.map_err(|e| format!("couldn't deserialize game: {:?}", e))?;
+	assert_eq!(game.end.method, EndMethod::RESOLVED);
+
+	Ok(())
+}
+
+fn contains(haystack: &[u8], needle: &[u8]) -> bool {
+	haystack.windows(needle.len()).any(|w| w == needle)
+}
+
+#[test]
+fn anonymize() -> Result<(), String> {
+	for name in ["v3.12", "netplay"] {
+		let mut game = game(name)?;
+		let mut identifying = Vec::new();
+		for p in &game.start.players {
+			if let Some(netplay) = &p.netplay {
+				identifying.push(encoding_rs::SHIFT_JIS.encode(&netplay.name).0.to_vec());
+				identifying.push(encoding_rs::SHIFT_JIS.encode(&netplay.code).0.to_vec());
+				identifying.extend(netplay.suid.as_ref().map(|s| s.as_bytes().to_vec()));
+			}
+		}
+		for p in game.metadata.players.iter().flatten() {
+			if let Some(netplay) = &p.netplay {
+				identifying.push(netplay.name.as_bytes().to_vec());
+				identifying.push(netplay.code.as_bytes().to_vec());
+			}
+		}
+		assert!(!identifying.is_empty());
+
+		game.anonymize();
+		let mut buf = io::Cursor::new(Vec::new());
+		serde::ser::serialize(&mut buf, &game).map_err(|e| format!("couldn't serialize game: {:?}", e))?;
+		let bytes = buf.into_inner();
+		for id in &identifying {
+			assert!(!contains(&bytes, id), "{}: found {:?}", name, id);
+		}
+
+		let game2 = peppi::game(&mut &bytes[..], None, None)
+			.map_err(|e| format!("couldn't deserialize game: {:?}", e))?;
+		assert_eq!(game2.start.players, game.start.players);
+		assert_eq!(game2.metadata, game.metadata);
+		assert_eq!(game2.frames, game.frames);
+	}
+	Ok(())
+}
+
+#[test]
+fn serialize_short_start() -> Result<(), String> {
+	let serialize = |game: &Game| serde::ser::serialize(&mut io::Cursor::new(Vec::new()), game)
+		.map_err(|e| e.to_string());
+
+	// v0.1 start blocks predate the language field
+	let mut game = game("v0.1")?;
+	game.start.language = Some(Language::ENGLISH);
+	let e = serialize(&game).unwrap_err();
+	assert!(e.contains("language"), "{}", e);
+
+	game.start.language = None;
+	game.start.raw_bytes.truncate(100);
+	let e = serialize(&game).unwrap_err();
+	assert!(e.contains("too short"), "{}", e);
+
+	Ok(())
+}
+
+#[test]
+fn start_stocks() -> Result<(), String> {
+	let mut game = game("game")?;
+	assert_eq!(game.start.players[1].stocks, 4);
+	assert_eq!(game.validate(), vec![]);
+
+	match &mut game.frames {
+		Frames::P2(frames) => frames[0].ports[1].leader.post.stocks = 3,
+		_ => Err("wrong number of ports")?,
+	}
+	let warnings = game.validate();
+	assert_eq!(warnings, vec![Warning::StartStocks { port: Port::P2, expected: 4, actual: 3 }]);
+	assert_eq!(warnings[0].to_string(), "P2: starts with 4 stocks, but 3 on the first frame");
+
+	Ok(())
+}
+
+#[test]
+fn edit_stage() -> Result<(), String> {
+	let mut game = game("items")?;
+	assert_eq!(game.start.stage, Stage::FINAL_DESTINATION);
+	assert_eq!(game.validate(), vec![]);
+
+	game.start.stage = Stage::BATTLEFIELD;
+	assert_eq!(game.validate(), vec![]);
+	let game2 = round_trip_game(&game)?;
+	assert_eq!(game2.start.stage, Stage::BATTLEFIELD);
+	assert_eq!(game2.start, Start { raw_bytes: game2.start.raw_bytes.clone(), ..game.start.clone() });
+	assert_eq!(game2.frames, game.frames);
+
+	match &mut game.frames {
+		Frames::P2(frames) => {
+			let item = frames.iter_mut()
+				.flat_map(|f| f.items.iter_mut().flatten())
+				.next()
+				.ok_or("no items")?;
+			item.r#type = item::Type::GREAT_FOX_LASER;
+		},
+		_ => Err("wrong number of ports")?,
+	}
+	match &game.validate()[..] {
+		[Warning::StageItem { r#type: item::Type::GREAT_FOX_LASER, .. }] => (),
+		w => Err(format!("unexpected warnings: {:?}", w))?,
+	}
+
+	game.start.stage = Stage::CORNERIA;
+	assert_eq!(game.validate(), vec![]);
+
+	Ok(())
+}
+
+#[test]
+fn strict_payload_sizes() -> Result<(), String> {
+	let strict = Some(serde::de::Opts { strict: true, ..Default::default() });
+	for name in ["v0.1", "game", "ics", "v2.0", "items", "netplay", "v3.12"] {
+		let bytes = fs::read(format!("tests/data/{}.slp", name)).unwrap();
+		peppi::game(&mut &bytes[..], strict, None)
+			.map_err(|e| format!("{}: couldn't deserialize game: {:?}", name, e))?;
+	}
+
+	// Declare a 2-byte Game End for a v1.0 replay, and pad the event to match.
+	let mut bytes = fs::read("tests/data/game.slp").unwrap();
+	let raw_len = u32::from_be_bytes(bytes[11 .. 15].try_into().unwrap()) as usize;
+	let payloads_end = 16 + bytes[16] as usize;
+	let payloads = &mut bytes[17 .. payloads_end];
+	let game_end = payloads.chunks_mut(3).find(|p| p[0] == 0x39).ok_or("no Game End size")?;
+	assert_eq!(game_end[1 ..], [0, 1]);
+	game_end[2] = 2;
+	assert_eq!(bytes[15 + raw_len - 2], 0x39);
+	bytes.insert(15 + raw_len, 0);
+	bytes[11 .. 15].copy_from_slice(&(raw_len as u32 + 1).to_be_bytes());
+
+	let game = peppi::game(&mut &bytes[..], None, None)
+		.map_err(|e| format!("couldn't deserialize game: {:?}", e))?;
+	assert_eq!(game.end.method, EndMethod::RESOLVED);
+
+	match peppi::game(&mut &bytes[..], strict, None) {
+		Err(e) => assert!(format!("{:?}", e).contains("wrong payload size for GameEnd"), "{:?}", e),
+		Ok(_) => Err("expected strict parse to fail")?,
+	}
+
+	Ok(())
+}
+
+#[test]
+fn payload_size_overrides() -> Result<(), String> {
+	let original = game("game")?;
+
+	// Declare a bogus Frame Post size, as a modded build might.
+	let mut bytes = fs::read("tests/data/game.slp").unwrap();
+	let payloads_end = 16 + bytes[16] as usize;
+	let payloads = &mut bytes[17 .. payloads_end];
+	let frame_post = payloads.chunks_mut(3).find(|p| p[0] == 0x38).ok_or("no Frame Post size")?;
+	let size = u16::from_be_bytes([frame_post[1], frame_post[2]]);
+	frame_post[1 ..].copy_from_slice(&(size + 8).to_be_bytes());
+	assert!(peppi::game(&mut &bytes[..], None, None).is_err());
+
+	let opts = serde::de::Opts {
+		payload_sizes: serde::de::PayloadSizes::from([(serde::de::Event::FramePost, size)]),
+		..Default::default()
+	};
+	let game = peppi::game(&mut &bytes[..], Some(opts), None)
+		.map_err(|e| format!("couldn't deserialize game: {:?}", e))?;
+	assert_eq!(game.start, original.start);
+	assert_eq!(game.frames, original.frames);
+
+	// `Opts` is `Copy`, so it can be reused
+	let header = peppi::game(&mut &bytes[..], Some(serde::de::Opts { skip_frames: true, ..opts }), None)
+		.map_err(|e| format!("couldn't deserialize game: {:?}", e))?;
+	assert_eq!(header.end, original.end);
+
+	Ok(())
+}
+
+#[test]
+fn finalized_frames() -> Result<(), String> {
+	let mut game = game("game")?;
+	match &mut game.frames {
+		Frames::P2(frames) => {
+			frames.truncate(6);
+			for (f, index) in frames.iter_mut().zip([0, 1, 2, 1, 2, 3]) {
+				f.index = index;
+				f.ports[0].leader.post.damage = index as f32 * 10.0;
+			}
+			// the rolled-back frames had different outcomes
+			frames[1].ports[0].leader.post.damage = 99.0;
+			frames[2].ports[0].leader.post.damage = 99.0;
+		},
+		_ => Err("wrong number of ports")?,
+	}
+	assert_eq!(game.frames.finalized(), vec![true, false, false, true, true, true]);
+
+	let opts = Some(PortFramesOpts { finalized_only: true, ..Default::default() });
+	let damage: Vec<_> = game.port_frames(Port::P1, opts).ok_or("missing P1")?
+		.map(|f| (f.index, f.data.post.damage))
+		.collect();
+	assert_eq!(damage, vec![(0, 0.0), (1, 10.0), (2, 20.0), (3, 30.0)]);
+	assert_eq!(game.port_frames(Port::P1, None).ok_or("missing P1")?.count(), 6);
+
+	Ok(())
+}
+
+#[test]
+fn header() -> Result<(), String> {
+	for name in ["game", "v2.0", "netplay"] {
+		let game = game(name)?;
+		let header = game.header();
+		assert_eq!(header.duration, game.frames.len());
+		assert_eq!(Some(header.duration), game.metadata.duration);
+		assert_eq!(header.start, game.start);
+		assert_eq!(header.end, game.end);
+		assert_eq!(header.metadata, game.metadata);
+		assert_eq!(header.metadata_raw, game.metadata_raw);
+	}
+
+	let headers: Vec<GameHeader> = ["ics", "items"].iter()
+		.map(|name| game(name).map(|g| g.header()))
+		.collect::<Result<_, _>>()?;
+	assert_eq!(headers.iter().map(|h| h.duration).collect::<Vec<_>>(), vec![344, 7375]);
+
+	Ok(())
+}
+
+#[test]
+fn attacks() -> Result<(), String> {
+	assert_eq!(Attack::from(13), Attack::NAIR);
+	assert_eq!(u8::from(Attack::FORWARD_THROW), 53);
+	assert_eq!(Attack::NAIR.short_name(), Some("nair"));
+	assert_eq!(Attack(10).short_name(), Some("fsmash"));
+	assert_eq!(Attack::BACK_THROW.short_name(), Some("bthrow"));
+	assert_eq!(Attack::BEAM_SWORD_JAB.short_name(), None);
+	assert_eq!(String::try_from(Attack(20)).unwrap(), "UP_SPECIAL");
+	assert_eq!(Attack::try_from("DAIR").unwrap(), Attack::DAIR);
+	assert!(String::try_from(Attack(200)).is_err());
+	assert!(Attack::DAIR.is_aerial() && !Attack::DAIR.is_special());
+	assert!(Attack::KIRBY_HAT_FOX_NEUTRAL_SPECIAL.is_special());
+	assert!(Attack::UP_THROW.is_throw() && !Attack::PUMMEL.is_throw());
+
+	let game = game("game")?;
+	let mut landed: Vec<_> = game.port_frames(Port::P1, None).ok_or("missing P1")?
+		.filter_map(|f| f.data.post.last_attack_landed)
+		.collect();
+	landed.dedup();
+	assert!(!landed.is_empty());
+	for attack in landed {
+		assert!(String::try_from(attack).is_ok(), "unknown attack: {:?}", attack);
+	}
+
+	Ok(())
+}
+
+#[test]
+fn serialize_to_vec() -> Result<(), String> {
+	for name in ["v0.1", "game", "ics", "v2.0", "items", "netplay", "v3.12", "nintendont"] {
+		let game = game(name)?;
+		let size = game.unparse_size().map_err(|e| e.to_string())?;
+		let bytes = serde::ser::serialize_to_vec(&game).map_err(|e| format!("couldn't serialize game: {:?}", e))?;
+		assert_eq!(bytes.len(), size, "{}", name);
+
+		let mut buf = io::Cursor::new(Vec::new());
+		serde::ser::serialize(&mut buf, &game).map_err(|e| format!("couldn't serialize game: {:?}", e))?;
+		assert_eq!(bytes, buf.into_inner(), "{}", name);
+	}
+	Ok(())
+}
+
+#[test]
+fn unparse_size() -> Result<(), String> {
+	let written = |game: &Game| -> Result<usize, String> {
+		let mut buf = io::Cursor::new(Vec::new());
+		serde::ser::serialize(&mut buf, game).map_err(|e| format!("couldn't serialize game: {:?}", e))?;
+		Ok(buf.into_inner().len())
+	};
+
+	for name in ["v0.1", "game", "ics", "v2.0", "items", "netplay", "v3.12", "nintendont"] {
+		let game = game(name)?;
+		assert_eq!(game.unparse_size().map_err(|e| e.to_string())?, written(&game)?, "{}", name);
+	}
+
+	// still exact after edits that change the metadata & frame counts
+	let mut edited = game("items")?;
+	edited.set_metadata("note", serde_json::json!("edited")).map_err(|e| e.to_string())?;
+	match &mut edited.frames {
+		Frames::P2(frames) => frames.truncate(100),
+		_ => Err("wrong number of ports")?,
+	}
+	assert_eq!(edited.unparse_size().map_err(|e| e.to_string())?, written(&edited)?);
+
+	Ok(())
+}
+
+#[test]
+fn serialize_frame_start_mismatch() -> Result<(), String> {
+	// v3.12 requires frame start events
+	let mut v3 = game("v3.12")?;
+	match &mut v3.frames {
+		Frames::P2(frames) => frames[10].start = None,
+		_ => Err("wrong number of ports")?,
+	}
+	let e = serde::ser::serialize_to_vec(&v3).expect_err("serialized frame without start");
+	assert_eq!(e.to_string(), "frame -113: missing frame start (required since v2.2.0)");
+
+	// v2.0 predates them
+	let mut v2 = game("v2.0")?;
+	match &mut v2.frames {
+		Frames::P2(frames) => frames[0].start = Some(peppi::model::frame::Start { random_seed: 0, scene_frame_counter: None }),
+		_ => Err("wrong number of ports")?,
+	}
+	let e = serde::ser::serialize_to_vec(&v2).expect_err("serialized unsupported frame start");
+	assert_eq!(e.to_string(), "frame -123: unexpected frame start (not supported before v2.2.0)");
+
+	Ok(())
+}
+
+#[test]
+fn serialize_frame_end_mismatch() -> Result<(), String> {
+	// v3.12 requires frame end events
+	let mut v3 = game("v3.12")?;
+	match &mut v3.frames {
+		Frames::P2(frames) => frames[10].end = None,
+		_ => Err("wrong number of ports")?,
+	}
+	let e = serde::ser::serialize_to_vec(&v3).expect_err("serialized frame without end");
+	assert_eq!(e.to_string(), "frame -113: missing frame end (required since v3.0.0)");
+
+	// v2.0 predates them
+	let mut v2 = game("v2.0")?;
+	match &mut v2.frames {
+		Frames::P2(frames) => frames[0].end = Some(peppi::model::frame::End { latest_finalized_frame: None }),
+		_ => Err("wrong number of ports")?,
+	}
+	let e = serde::ser::serialize_to_vec(&v2).expect_err("serialized unsupported frame end");
+	assert_eq!(e.to_string(), "frame -123: unexpected frame end (not supported before v3.0.0)");
+
+	Ok(())
+}
+
+#[test]
+fn too_many_players() -> Result<(), String> {
+	// a human in the fifth of the Game Start block's six player slots
+	let mut bytes = fs::read("tests/data/game.slp").unwrap();
+	let game_start = 16 + bytes[16] as usize;
+	assert_eq!(bytes[game_start + 1 + 100 + 36 * 4 + 1], 3);
+	bytes[game_start + 1 + 100 + 36 * 4 + 1] = 0;
+	let e = peppi::game(&mut &bytes[..], None, None).expect_err("parsed 5-player start");
+	assert_eq!(e.to_string(), "parse error @0x17e: player in unsupported slot 5 (max players: 4)");
+
+	let mut game = game("game")?;
+	let p1 = game.start.players[0].clone();
+	game.start.players.push(Player { port: Port::P3, ..p1.clone() });
+	game.start.players.push(Player { port: Port::P4, ..p1.clone() });
+	game.start.players.push(Player { port: Port::P4, ..p1.clone() });
+	let e = serde::ser::serialize_to_vec(&game).expect_err("serialized 5 players");
+	assert_eq!(e.to_string(), "too many players: 5 (max: 4)");
+
+	game.start.players.pop();
+	game.start.players[2].port = Port::P1;
+	let e = serde::ser::serialize_to_vec(&game).expect_err("serialized duplicate ports");
+	assert_eq!(e.to_string(), "multiple players in port P1");
+
+	Ok(())
+}
+
+#[test]
+fn unparse_errors() -> Result<(), String> {
+	use std::error::Error;
+	use peppi::serde::ser::UnparseError;
+
+	let mut long_tag = game("v2.0")?;
+	long_tag.start.players[0].name_tag = Some("ABCDEFGHIJKLMNOPQRST".to_string());
+	let e = serde::ser::serialize_to_vec(&long_tag).expect_err("serialized overlong name tag");
+	assert!(matches!(e, UnparseError::FieldTooLong { field: "name tag", len: 20, max: 16, .. }), "{:?}", e);
+
+	let mut v3 = game("v3.12")?;
+	match &mut v3.frames {
+		Frames::P2(frames) => frames[0].start = None,
+		_ => Err("wrong number of ports")?,
+	}
+	let e = serde::ser::serialize_to_vec(&v3).expect_err("serialized frame without start");
+	assert!(matches!(e, UnparseError::MissingField { field: "frame start", frame: Some(-123), .. }), "{:?}", e);
+	assert!(e.source().is_none());
+
+	let mut v2 = game("v2.0")?;
+	match &mut v2.frames {
+		Frames::P2(frames) => frames[0].start = Some(peppi::model::frame::Start { random_seed: 0, scene_frame_counter: None }),
+		_ => Err("wrong number of ports")?,
+	}
+	let e = serde::ser::serialize_to_vec(&v2).expect_err("serialized unsupported frame start");
+	assert!(matches!(e, UnparseError::VersionMismatch { field: "frame start", .. }), "{:?}", e);
+
+	let mut buf = [0u8; 100];
+	let e = serde::ser::serialize(&mut io::Cursor::new(&mut buf[..]), &game("game")?)
+		.expect_err("serialized into a full buffer");
+	assert!(matches!(&e, UnparseError::Io(io_err) if io_err.kind() == io::ErrorKind::WriteZero), "{:?}", e);
+	assert!(e.source().is_some());
+
+	Ok(())
+}
+
+#[test]
+fn teams() -> Result<(), String> {
+	let mut game = game("game")?;
+	assert_eq!(game.teams(), None);
+
+	let player = game.start.players[0].clone();
+	let team = |color| Some(Team { color, shade: TeamShade::NORMAL });
+	game.start.is_teams = true;
+	game.start.players = [
+		(Port::P1, TeamColor::BLUE),
+		(Port::P2, TeamColor::RED),
+		(Port::P3, TeamColor::RED),
+		(Port::P4, TeamColor::BLUE),
+	].into_iter().map(|(port, color)| Player { port, team: team(color), ..player.clone() }).collect();
+	assert_eq!(game.teams(), Some([vec![Port::P2, Port::P3], vec![Port::P1, Port::P4]]));
+
+	game.start.players[3].team = team(TeamColor::GREEN);
+	assert_eq!(game.teams(), None);
+
+	game.start.players[3].team = team(TeamColor::RED);
+	assert_eq!(game.teams(), Some([vec![Port::P2, Port::P3, Port::P4], vec![Port::P1]]));
+	Ok(())
+}
+
+#[test]
+fn frames_from_port_count() -> Result<(), String> {
+	let mut game = game("game")?;
+	game.metadata_raw.remove("players");
+	let player = game.start.players[0].clone();
+	let template: Vec<_> = match &game.frames {
+		Frames::P2(frames) => frames.iter().take(200).cloned().collect(),
+		_ => Err("wrong number of ports")?,
+	};
+
+	for n in 1 ..= 4 {
+		let frames = template.iter().map(|f| DynFrame {
+			index: f.index,
+			ports: vec![f.ports[0].clone(); n],
+			start: f.start,
+			end: f.end,
+			items: f.items.clone(),
+		}).collect();
+		game.frames = Frames::from_port_count(n, frames).map_err(|e| format!("{:?}", e))?;
+		game.start.players = (0 .. n as u8)
+			.map(|p| Player { port: Port::try_from(p).unwrap(), ..player.clone() })
+			.collect();
+
+		let game2 = round_trip_game(&game)?;
+		assert_eq!(game2.frames, game.frames, "{} ports", n);
+		assert_eq!(game2.start.players.len(), n);
+	}
+
+	let uneven = vec![
+		DynFrame { index: -123, ports: vec![template[0].ports[0].clone(); 2], ..Default::default() },
+		DynFrame { index: -122, ports: vec![template[1].ports[0].clone(); 3], ..Default::default() },
+	];
+	let err = Frames::from_port_count(2, uneven).unwrap_err();
+	assert_eq!(err.to_string(), "frame -122: expected 2 ports, but got 3");
+	assert!(Frames::from_port_count(5, vec![]).is_err());
+
+	Ok(())
+}
+
+#[test]
+fn ubjson_metadata() -> Result<(), String> {
+	let original = fs::read("tests/data/game.slp").unwrap();
+	let raw_len = u32::from_be_bytes(original[11 .. 15].try_into().unwrap()) as usize;
+
+	// hand-written metadata, using types Slippi itself doesn't (yet)
+	let mut bytes = original[.. 15 + raw_len].to_vec();
+	bytes.extend_from_slice(b"U\x08metadata{");
+	bytes.extend_from_slice(b"U\x01a[$i#U\x03\x01\x02\xff"); // optimized array of i8
+	bytes.extend_from_slice(b"U\x01bd\x3f\xc0\x00\x00"); // f32
+	bytes.extend_from_slice(b"U\x01cTU\x01dZN"); // bool, null, no-op
+	bytes.extend_from_slice(b"U\x01e[SU\x02hiI\x01\x00[]]"); // nested arrays
+	bytes.extend_from_slice(b"U\x01fCx"); // char
+	bytes.extend_from_slice(b"U\x01gHU\x1412345678901234567890"); // high-precision
+	bytes.extend_from_slice(b"U\x01h{#U\x01U\x01kl\x00\x00\x00\x07"); // optimized map
+	bytes.extend_from_slice(b"}}");
+
+	let game = peppi::game(&mut &bytes[..], None, None)
+		.map_err(|e| format!("couldn't deserialize game: {:?}", e))?;
+	let expected = serde_json::json!({
+		"a": [1, 2, -1],
+		"b": 1.5,
+		"c": true,
+		"d": null,
+		"e": ["hi", 256, []],
+		"f": "x",
+		"g": 12345678901234567890u64,
+		"h": {"k": 7},
+	});
+	assert_eq!(serde_json::Value::Object(game.metadata_raw.clone()), expected);
+
+	let game2 = round_trip_game(&game)?;
+	assert_eq!(game2.metadata_raw, game.metadata_raw);
+
+	Ok(())
+}
+
+#[test]
+fn costume_colors() -> Result<(), String> {
+	let colors = |game: &Game| game.start.players.iter().map(|p| p.costume_color()).collect::<Vec<_>>();
+	assert_eq!(colors(&game("game")?), vec![Some(CostumeColor::Black), Some(CostumeColor::Default)]);
+	assert_eq!(colors(&game("v2.0")?), vec![Some(CostumeColor::Green), Some(CostumeColor::Red)]);
+
+	let player = |character, costume| Player { character, costume, ..Default::default() };
+	assert_eq!(player(External::GANONDORF, 4).costume_color(), Some(CostumeColor::Purple));
+	assert_eq!(player(External::YOSHI, 5).costume_color(), Some(CostumeColor::Cyan));
+	assert_eq!(player(External::FOX, 4).costume_color(), None);
+	assert_eq!(player(External::SANDBAG, 0).costume_color(), Some(CostumeColor::Default));
+	Ok(())
+}
+
+#[test]
+fn unknown_ids() -> Result<(), String> {
+	let mut game = game("items")?;
+	game.start.stage = Stage(0x7f);
+	game.start.players[0].character = External(0x7e);
+	match &mut game.frames {
+		Frames::P2(frames) => {
+			let item = frames.iter_mut()
+				.flat_map(|f| f.items.iter_mut().flatten())
+				.next()
+				.ok_or("no items")?;
+			item.r#type = item::Type(0x7fff);
+			let post = &mut frames[0].ports[1].leader.post;
+			post.character = Internal(0x7d);
+			post.state = State::from(0x7ffc, post.character);
+			assert_eq!(post.state, State::Unknown(0x7ffc));
+		},
+		_ => Err("wrong number of ports")?,
+	}
+
+	let game2 = round_trip_game(&game)?;
+	assert_eq!(game2.start.stage, Stage(0x7f));
+	assert_eq!(game2.start.players[0].character, External(0x7e));
+	assert_eq!(game2.frames, game.frames);
+	Ok(())
+}
+
+#[test]
+fn sample_frames() -> Result<(), String> {
+	let game = game("game")?;
+	assert_eq!(game.frames.len(), 5209);
+
+	let sample = game.sample_frames(5);
+	assert_eq!(sample, vec![0, 1302, 2604, 3906, 5208]);
+	assert_eq!(game.frames.index(sample[4]), 5085);
+
+	assert_eq!(game.sample_frames(0), Vec::<usize>::new());
+	assert_eq!(game.sample_frames(1), vec![0]);
+	assert_eq!(game.sample_frames(10_000).len(), 5209);
+
+	// rolled-back frames are skipped
+	let mut game = game;
+	match &mut game.frames {
+		Frames::P2(frames) => {
+			frames.truncate(6);
+			for (f, index) in frames.iter_mut().zip([0, 1, 2, 1, 2, 3]) {
+				f.index = index;
+			}
+		},
+		_ => Err("wrong number of ports")?,
+	}
+	assert_eq!(game.sample_frames(3), vec![0, 3, 5]);
+	assert_eq!(game.sample_frames(10), vec![0, 3, 4, 5]);
+	Ok(())
+}
+
+#[test]
+fn state_runs() -> Result<(), String> {
+	// Ice Climbers' runs are Popo's alone
+	let leaders = Some(PortFramesOpts { merge_followers: true, ..Default::default() });
+	for name in ["game", "ics"] {
+		let game = game(name)?;
+		for port in [Port::P1, Port::P2] {
+			let runs = game.state_runs(port);
+			assert!(runs.len() < game.frames.len() / 4, "{} {:?}", name, port);
+			assert!(runs.windows(2).all(|w| w[0].0 != w[1].0 && w[0].2 + 1 == w[1].1), "{} {:?}", name, port);
+
+			let decompressed: Vec<State> = runs.iter()
+				.flat_map(|(state, start, end)| (*start ..= *end).map(move |_| *state))
+				.collect();
+			let states: Vec<State> = game.port_frames(port, leaders).ok_or("missing port")?
+				.map(|f| f.data.post.state)
+				.collect();
+			assert_eq!(decompressed, states, "{} {:?}", name, port);
+		}
+		assert!(game.state_runs(Port::P3).is_empty());
+	}
+	Ok(())
+}
+
+#[test]
+fn frame_pairs() -> Result<(), String> {
+	let game = game("netplay")?;
+	let finalized = game.frames.finalized().into_iter().filter(|f| *f).count();
+	let pairs: Vec<_> = game.frame_pairs(Port::P1).collect();
+	assert_eq!(pairs.len(), finalized - 1);
+	assert_eq!(game.frame_pairs(Port::P3).count(), 0);
+	Ok(())
+}
+
+#[test]
+fn versus_frames() -> Result<(), String> {
+	let v2 = game("v2.0")?;
+	let pairs: Vec<_> = v2.versus_frames(Port::P2).map_err(|e| e.to_string())?.collect();
+	assert_eq!(pairs.len(), v2.frames.len());
+	let last = v2.frames.len() - 1;
+	assert_eq!(pairs[last].0.post.damage, v2.frames.port_data(last, 1).leader.post.damage);
+	assert_eq!(pairs[last].1.post.damage, v2.frames.port_data(last, 0).leader.post.damage);
+	assert_eq!(pairs[last].0.post.character, Internal::FOX);
+	assert_eq!(pairs[last].1.post.character, Internal::JIGGLYPUFF);
+
+	assert!(v2.versus_frames(Port::P3).is_err());
+	#[cfg(feature = "testing")] {
+		let ffa = peppi::test_util::minimal_game(Version(3, 12, 0), Stage::BATTLEFIELD,
+			&[External::FOX, External::FALCO, External::MARTH]);
+		assert!(ffa.versus_frames(Port::P1).is_err());
+	}
+	Ok(())
+}
+
+#[test]
+fn extract_metadata() -> Result<(), String> {
+	for name in ["game", "v0.1", "netplay", "console_name"] {
+		let path = format!("tests/data/{}.slp", name);
+		let mut f = fs::File::open(&path).map_err(|e| format!("couldn't open `{}`: {:?}", path, e))?;
+		let metadata = peppi::extract_metadata(&mut f)
+			.map_err(|e| format!("couldn't extract metadata: {:?}", e))?;
+		assert_eq!(metadata, game(name)?.metadata, "{}", name);
+	}
+
+	// in-progress replays don't declare the raw length
+	let mut bytes = fs::read("tests/data/game.slp").unwrap();
+	bytes[11..15].fill(0);
+	assert!(peppi::extract_metadata(&mut io::Cursor::new(bytes)).is_err());
+	Ok(())
+}
+
+#[test]
+fn port_of_code() -> Result<(), String> {
+	let netplay = game("v3.12")?;
+	assert_eq!(netplay.port_of_code("XX＃111"), Some(Port::P1));
+	assert_eq!(netplay.port_of_code("yyyy#222"), Some(Port::P2));
+	assert_eq!(netplay.port_of_code("XX#222"), None);
+	assert_eq!(netplay.port_of_code(""), None);
+
+	// predates connect codes
+	assert_eq!(game("v2.0")?.port_of_code("XX#111"), None);
+	Ok(())
+}
+
+#[test]
+fn random_seeds() -> Result<(), String> {
+	const SEED: u32 = 0x1234_5678;
+	let mut game = game("netplay")?;
+	game.start.random_seed = SEED;
+	match &mut game.frames {
+		Frames::P2(frames) => for f in frames.iter_mut() {
+			f.start.as_mut().ok_or("missing frame start")?.random_seed = SEED;
+			for p in &mut f.ports {
+				p.leader.pre.random_seed = SEED;
+			}
+		},
+		_ => Err("wrong number of ports")?,
+	}
+	assert_eq!(game.validate(), vec![]);
+
+	let game2 = round_trip_game(&game)?;
+	assert_eq!(game2.start.random_seed, SEED);
+	assert_eq!(game2.frames, game.frames);
+
+	// a rollback to frame 1, replayed with a different seed
+	match &mut game.frames {
+		Frames::P2(frames) => {
+			frames.truncate(6);
+			for (f, index) in frames.iter_mut().zip([0, 1, 2, 1, 2, 3]) {
+				f.index = index;
+			}
+			frames[3].start.as_mut().unwrap().random_seed = 1;
+			// only the first replayed frame must match
+			frames[4].start.as_mut().unwrap().random_seed = 2;
+		},
+		_ => Err("wrong number of ports")?,
+	}
+	assert_eq!(game.validate(), vec![Warning::RollbackSeed { index: 1, expected: SEED, actual: 1 }]);
+	Ok(())
+}
+
+#[test]
+fn frame_counts() -> Result<(), String> {
+	let netplay = game("netplay")?;
+	assert_eq!(netplay.raw_frame_count(), 128);
+	assert_eq!(netplay.logical_frame_count(), 128);
+	assert!(!netplay.has_rollback());
+
+	let netplay = rolled_back("netplay")?;
+	let bytes = serde::ser::serialize_to_vec(&netplay).map_err(|e| e.to_string())?;
+
+	let opts = peppi::serde::collect::Opts { rollbacks: true, ..Default::default() };
+	let rollback = peppi::game(&mut &bytes[..], None, Some(opts)).map_err(|e| format!("{:?}", e))?;
+	assert_eq!(rollback.raw_frame_count(), 133);
+	assert_eq!(rollback.logical_frame_count(), 128);
+	assert!(rollback.has_rollback());
+	assert_eq!(rollback.header().duration, 128);
+
+	let finalized = peppi::game(&mut &bytes[..], None, None).map_err(|e| format!("{:?}", e))?;
+	assert_eq!(finalized.raw_frame_count(), 128);
+	assert!(!finalized.has_rollback());
+	Ok(())
+}
+
+#[test]
+fn first_input_frame() -> Result<(), String> {
+	let console = game("console_name")?;
+	assert_eq!(console.first_input_frame(Port::P1), Some(-36));
+	assert_eq!(console.first_input_frame(Port::P2), Some(409));
+	assert_eq!(console.first_input_frame(Port::P3), None);
+
+	// only the c-stick is used
+	let cstick = game("cstick_udlr")?;
+	assert_eq!(cstick.first_input_frame(Port::P1), Some(14));
+
+	// Nana's stick is off-center from the start, but Popo's isn't
+	let ics = game("ics")?;
+	assert_ne!(ics.frames.port_data(0, 0).follower.as_ref().unwrap().pre.joystick, Position::default());
+	assert_eq!(ics.first_input_frame(Port::P1), Some(-19));
+
+	// inputs on rolled-back frames don't count
+	let mut rolled_back = rolled_back("console_name")?;
+	match &mut rolled_back.frames {
+		Frames::P2(frames) => frames[5].ports[1].leader.pre.buttons.physical = Physical::A,
+		_ => Err("wrong number of ports")?,
+	}
+	assert_eq!(rolled_back.first_input_frame(Port::P2), Some(409));
+	Ok(())
+}
+
+#[test]
+fn time_remaining() -> Result<(), String> {
+	let mut game = game("v2.0")?;
+	assert_eq!(game.start.timer_type(), TimerType::DECREASING);
+	assert_eq!(game.start.timer, 480);
+
+	// clock doesn't start until "Go!"
+	assert_eq!(game.time_remaining_at(-123), Some(Duration::from_secs(480)));
+	assert_eq!(game.time_remaining_at(0), Some(Duration::from_secs(480)));
+	assert_eq!(game.time_remaining_at(60), Some(Duration::from_secs(479)));
+
+	// game ended with 5:03.08 on the clock
+	let last = game.frames.index(game.frames.len() - 1);
+	assert_eq!(last, 10615);
+	assert_eq!(game.time_remaining_at(last), Some(Duration::from_secs(18185) / 60));
+	assert_eq!(game.time_remaining_at(last).unwrap().as_secs(), 303);
+
+	assert_eq!(game.time_remaining_at(480 * 60 + 100), Some(Duration::ZERO));
+
+	game.start.bitfield[0] &= !0b11;
+	assert_eq!(game.start.timer_type(), TimerType::NONE);
+	assert_eq!(game.time_remaining_at(0), None);
+	Ok(())
+}
+
+#[test]
+fn truncate_at_time() -> Result<(), String> {
+	// 8-minute timer; ended by game with 5:03.08 remaining
+	let mut game = game("v2.0")?;
+	assert!(game.truncate_at_time(Duration::from_secs(300)).is_err());
+	assert!(game.truncate_at_time(Duration::from_secs(481)).is_err());
+	assert_eq!(game.frames.len(), 10739);
+
+	game.truncate_at_time(Duration::from_secs(360)).map_err(|e| e.to_string())?;
+	let last = 2 * 60 * 60;
+	assert_eq!(game.frames.len(), (last + 124) as usize);
+	assert_eq!(game.frames.index(game.frames.len() - 1), last);
+	assert_eq!(game.time_remaining_at(last), Some(Duration::from_secs(360)));
+	assert_eq!(game.end, End { method: EndMethod::UNRESOLVED, lras_initiator: Some(None) });
+	assert_eq!(game.metadata.duration, Some(game.frames.len()));
+
+	let game2 = round_trip_game(&game)?;
+	assert_eq!(game2.frames.len(), game.frames.len());
+	assert_eq!(game2.end, game.end);
+	assert_eq!(game2.metadata.duration, Some(7324));
+	let characters = game2.metadata.players.clone().ok_or("missing metadata.players")?.into_iter()
+		.map(|p| p.characters)
+		.collect::<Vec<_>>();
+	assert_eq!(characters, vec![
+		Some(HashMap::from([(Internal::JIGGLYPUFF, 7324)])),
+		Some(HashMap::from([(Internal::FOX, 7324)])),
+	]);
+
+	// the clock value reported for the last frame can be fed straight back in
+	let mut game = game2;
+	game.truncate_at_time(game.time_remaining_at(1000).unwrap()).map_err(|e| e.to_string())?;
+	assert_eq!(game.frames.index(game.frames.len() - 1), 1000);
+
+	game.start.bitfield[0] &= !0b11;
+	assert!(game.truncate_at_time(Duration::from_secs(420)).is_err());
+	Ok(())
+}
+
+#[test]
+fn match_settings() -> Result<(), String> {
+	let mut game = game("game")?;
+	let settings = game.start.match_settings();
+	assert!(settings.is_standard());
+	assert_eq!(settings, MatchSettings {
+		players: vec![PlayerSettings::standard(Port::P1), PlayerSettings::standard(Port::P2)],
+		..Default::default()
+	});
+
+	let handicap = MatchSettings {
+		damage_ratio: 1.5,
+		players: vec![
+			PlayerSettings { handicap: 3, offense_ratio: 0.5, defense_ratio: 1.25, ..PlayerSettings::standard(Port::P1) },
+			PlayerSettings { model_scale: 2.0, ..PlayerSettings::standard(Port::P2) },
+			PlayerSettings::standard(Port::P3),
+		],
+		..Default::default()
+	};
+	game.start.set_match_settings(&handicap).unwrap();
+	assert_eq!(game.start.players[0].defense_ratio, 1.25);
+	assert_eq!(game.start.players[0].handicap, 3);
+
+	let game2 = round_trip_game(&game)?;
+	let settings2 = game2.start.match_settings();
+	assert!(!settings2.is_standard());
+	assert_eq!(settings2.damage_ratio, 1.5);
+	assert_eq!(settings2.players, handicap.players[.. 2]);
+	assert_eq!(game2.start.players[0].handicap, 3);
+	assert_eq!(game2.start, Start { raw_bytes: game2.start.raw_bytes.clone(), ..game.start.clone() });
+
+	Ok(())
+}
+
+#[test]
+fn stage_and_characters() -> Result<(), String> {
+	let two_player = game("game")?;
+	assert_eq!(two_player.stage(), Stage::YOSHIS_STORY);
+	assert_eq!(two_player.characters(), vec![
+		(Port::P1, External::MARTH),
+		(Port::P2, External::FOX),
+	]);
+
+	let transform = game("transform")?;
+	assert_eq!(transform.characters(), vec![
+		(Port::P2, External::CAPTAIN_FALCON),
+		(Port::P4, External::ZELDA),
+	]);
+
+	Ok(())
+}
+
+fn transcode(bytes: &[u8], target: Version) -> Result<Vec<u8>, String> {
+	let mut out = Vec::new();
+	serde::ser::transcode(&mut &bytes[..], &mut out, target)
+		.map_err(|e| format!("couldn't transcode: {:?}", e))?;
+	Ok(out)
+}
+
+#[test]
+fn transcoding() -> Result<(), String> {
+	let bytes = fs::read("tests/data/v3.12.slp").unwrap();
+	let original = game("v3.12")?;
+
+	let v3_9 = transcode(&bytes, Version::NETPLAY_NAMES)?;
+	let v3_7 = transcode(&v3_9, Version(3, 7, 0))?;
+	let game = peppi::game(&mut &v3_7[..], None, None)
+		.map_err(|e| format!("couldn't deserialize game: {:?}", e))?;
+	assert_eq!(game.start.slippi.version, Version(3, 7, 0));
+	assert_eq!(game.start.raw_bytes[3 ..], original.start.raw_bytes[3 .. 420]);
+	assert_eq!(game.start.language, None);
+	assert_eq!(game.start.scene, original.start.scene);
+	for (p, o) in game.start.players.iter().zip(&original.start.players) {
+		assert_eq!(p, &Player { netplay: None, ..o.clone() });
+	}
+	assert_eq!(game.end, original.end);
+	assert_eq!(game.metadata_raw, original.metadata_raw);
+	assert_eq!(game.gecko_codes, original.gecko_codes);
+
+	// raw length is unknown, so skipping frames can't seek ahead
+	let skip_frames = Some(serde::de::Opts { skip_frames: true, ..Default::default() });
+	let header = peppi::game(&mut &v3_7[..], skip_frames, None)
+		.map_err(|e| format!("couldn't deserialize game: {:?}", e))?;
+	assert_eq!(header.end, original.end);
+
+	assert_eq!(game.frames.len(), original.frames.len());
+	match (&game.frames, &original.frames) {
+		(Frames::P2(frames), Frames::P2(orig)) => {
+			for (f, o) in frames.iter().zip(orig) {
+				assert_eq!(f.start.unwrap().scene_frame_counter, None);
+				assert_eq!(f.items, o.items);
+				for (p, o) in f.ports.iter().zip(&o.ports) {
+					assert_eq!(p.leader.pre, o.leader.pre);
+					assert_eq!(p.leader.post.hitlag, None);
+					assert_eq!(p.leader.post.animation_index, None);
+					assert_eq!(p.leader.post.position, o.leader.post.position);
+				}
+			}
+		},
+		_ => Err("wrong number of ports")?,
+	}
+
+	// pre-3.0 replays have no items or frame start/end events
+	let v2_0 = transcode(&fs::read("tests/data/items.slp").unwrap(), Version(2, 0, 0))?;
+	let game = peppi::game(&mut &v2_0[..], None, None)
+		.map_err(|e| format!("couldn't deserialize game: {:?}", e))?;
+	assert_eq!(game.start.scene, None);
+	assert_eq!(game.gecko_codes, None);
+	match &game.frames {
+		Frames::P2(frames) => assert!(frames.iter().all(|f| f.start.is_none() && f.end.is_none()
+			&& f.items.iter().flatten().next().is_none())),
+		_ => Err("wrong number of ports")?,
+	}
+
+	// can't make up data that isn't there
+	assert!(transcode(&fs::read("tests/data/game.slp").unwrap(), Version(3, 7, 0)).is_err());
+
+	Ok(())
+}
+
+#[test]
+fn stick_region() -> Result<(), String> {
+	let game = game("game")?;
+	let mut pre = match &game.frames {
+		Frames::P2(frames) => frames[0].ports[0].leader.pre,
+		_ => Err("wrong number of ports")?,
+	};
+
+	let mut region = |x, y| {
+		pre.joystick = Position { x, y };
+		pre.stick_region()
+	};
+
+	assert_eq!(region(0.0, 0.0), StickRegion::DeadZone);
+	assert_eq!(region(0.275, -0.275), StickRegion::DeadZone);
+	assert_eq!(region(0.2875, 0.0), StickRegion::E);
+	assert_eq!(region(0.2875, -0.2875), StickRegion::SE);
+	assert_eq!(region(0.0, -1.0), StickRegion::S);
+	assert_eq!(region(-0.7, -0.7), StickRegion::SW);
+	assert_eq!(region(-1.0, 0.1), StickRegion::W);
+	assert_eq!(region(-0.5, 0.8), StickRegion::NW);
+	assert_eq!(region(0.0, 0.3), StickRegion::N);
+	assert_eq!(region(0.9875, 0.3), StickRegion::NE);
+
+	assert_eq!(StickRegion::SW.axes(), (-1, -1));
+	assert_eq!(StickRegion::N.axes(), (0, 1));
+
+	// either side of the dead zone (22/80) & smash thresholds (64/80 across, 53/80 up & down)
+	for sign in [1.0, -1.0] {
+		assert_eq!(StickZone::from_x(sign * 0.275), StickZone::DeadZone);
+		assert_eq!(StickZone::from_x(sign * 0.2875), StickZone::Tilt);
+		assert_eq!(StickZone::from_x(sign * 0.7875), StickZone::Tilt);
+		assert_eq!(StickZone::from_x(sign * 0.8), StickZone::Smash);
+		assert_eq!(StickZone::from_y(sign * 0.275), StickZone::DeadZone);
+		assert_eq!(StickZone::from_y(sign * 0.2875), StickZone::Tilt);
+		assert_eq!(StickZone::from_y(sign * 0.65), StickZone::Tilt);
+		assert_eq!(StickZone::from_y(sign * 0.6625), StickZone::Smash);
+	}
+	// 0.6625 up is a smash, but not across
+	pre.joystick = Position { x: 0.6625, y: 0.6625 };
+	assert_eq!(pre.stick_zones(), (StickZone::Tilt, StickZone::Smash));
+	pre.joystick = Position { x: -1.0, y: 0.0 };
+	assert_eq!(pre.stick_zones(), (StickZone::Smash, StickZone::DeadZone));
+
+	// P1 starts out neutral, and uses diagonals later on
+	let regions: Vec<_> = game.port_frames(Port::P1, None).ok_or("missing P1")?
+		.map(|f| f.data.pre.stick_region())
+		.collect();
+	assert_eq!(regions[0], StickRegion::DeadZone);
+	assert!(regions.contains(&StickRegion::NE));
+	Ok(())
+}
+
+#[test]
+fn joystick_angle() -> Result<(), String> {
+	let game = game("game")?;
+	let mut pre = match &game.frames {
+		Frames::P2(frames) => frames[0].ports[0].leader.pre,
+		_ => Err("wrong number of ports")?,
+	};
+
+	let mut stick = |x, y| {
+		pre.joystick = Position { x, y };
+		(pre.joystick_magnitude(), pre.joystick_angle_deg())
+	};
+
+	assert_eq!(stick(0.0, 0.0), (0.0, None));
+	assert_eq!(stick(0.2, -0.2).1, None);
+	assert_eq!(stick(1.0, 0.0), (1.0, Some(0.0)));
+	assert_eq!(stick(0.0, 1.0), (1.0, Some(90.0)));
+	assert_eq!(stick(-1.0, 0.0), (1.0, Some(180.0)));
+	assert_eq!(stick(0.0, -1.0), (1.0, Some(270.0)));
+
+	let (magnitude, angle) = stick(0.7, 0.7);
+	assert!((magnitude - 0.98995).abs() < 1e-4);
+	assert!((angle.unwrap() - 45.0).abs() < 1e-4);
+	let (_, angle) = stick(-0.7, -0.7);
+	assert!((angle.unwrap() - 225.0).abs() < 1e-4);
+	let (_, angle) = stick(0.3, -0.3);
+	assert!((angle.unwrap() - 315.0).abs() < 1e-4);
+
+	Ok(())
+}
+
+#[test]
+fn input_notation() -> Result<(), String> {
+	let game = game("game")?;
+	let mut pre = match &game.frames {
+		Frames::P2(frames) => frames[0].ports[0].leader.pre,
+		_ => Err("wrong number of ports")?,
+	};
+
+	let mut notation = |x, y, buttons, side| {
+		pre.joystick = Position { x, y };
+		pre.buttons.logical = buttons;
+		pre.notation(side)
+	};
+
+	assert_eq!(notation(0.0, 0.0, Logical::NONE, Side::P1), "5");
+	assert_eq!(notation(0.2, -0.1, Logical::NONE, Side::P1), "5");
+	assert_eq!(notation(0.0, 0.0, Logical::A, Side::P1), "5[A]");
+	assert_eq!(notation(0.0, -1.0, Logical::A, Side::P1), "2[A]");
+	assert_eq!(notation(0.7, -0.7, Logical::NONE, Side::P1), "3");
+	assert_eq!(notation(0.7, -0.7, Logical::NONE, Side::P2), "1");
+	assert_eq!(notation(-1.0, 0.0, Logical::B | Logical::Z, Side::P1), "4[BZ]");
+	assert_eq!(notation(-0.5, 0.8, Logical::NONE, Side::P2), "9");
+	assert_eq!(notation(0.0, 1.0, Logical::X | Logical::R | Logical::TRIGGER_ANALOG, Side::P1), "8[XR]");
+
+	Ok(())
+}
+
+#[test]
+fn ranked_info() -> Result<(), String> {
+	let mut game = game("v3.12")?;
+	assert_eq!(game.ranked_info(), None);
+	assert!(!game.is_ranked());
+
+	// fake a v3.14 online replay
+	game.start.slippi.version = Version(3, 14, 0);
+	game.start.raw_bytes.resize(760, 0);
+	game.start.scene = Some(Scene { minor: 2, major: 8 });
+	game.start.r#match = Some(Match {
+		id: "mode.ranked-2023-06-25T21:21:08.85-0".to_string(),
+		game: 2,
+		tiebreaker: 0,
+	});
+	let ranked = round_trip_game(&game)?;
+	assert_eq!(ranked.start.r#match, game.start.r#match);
+	assert_eq!(ranked.ranked_info(), Some(RankedInfo {
+		is_ranked: true,
+		match_id: "mode.ranked-2023-06-25T21:21:08.85-0".to_string(),
+		game_number: 2,
+		tiebreaker_number: 0,
+	}));
+	assert!(ranked.is_ranked());
+
+	game.start.r#match = Some(Match {
+		id: "mode.direct-2023-06-26T01:02:03.04-0".to_string(),
+		game: 1,
+		tiebreaker: 0,
+	});
+	let direct = round_trip_game(&game)?;
+	assert_eq!(direct.ranked_info().map(|r| r.is_ranked), Some(false));
+	assert!(!direct.is_ranked());
+
+	// offline games have no ranked context, even with a match ID
+	game.start.scene = Some(Scene { minor: 2, major: 2 });
+	assert_eq!(game.ranked_info(), None);
+
+	Ok(())
+}
+
+#[test]
+fn dedup() -> Result<(), String> {
+	let hash = |game: &Game| game.content_hash().map_err(|e| format!("couldn't hash game: {:?}", e));
+
+	let original = game("game")?;
+	let mut reupload = game("game")?;
+	reupload.metadata_raw.insert("startAt".to_string(), serde_json::json!("2022-01-01T00:00:00Z"));
+	reupload.metadata.date = Some("2022-01-01T00:00:00Z".parse::<DateTime<Utc>>().unwrap());
+	assert_ne!(reupload.metadata_raw, original.metadata_raw);
+	assert!(is_same_game(&original, &reupload));
+	assert_eq!(hash(&original)?, hash(&reupload)?);
+
+	let other = game("ics")?;
+	assert!(!is_same_game(&original, &other));
+	assert_ne!(hash(&original)?, hash(&other)?);
+
+	let mut edited = game("game")?;
+	match &mut edited.frames {
+		Frames::P2(frames) => frames[100].ports[0].leader.post.damage += 1.0,
+		_ => Err("wrong number of ports")?,
+	}
+	assert!(!is_same_game(&original, &edited));
+	assert_ne!(hash(&original)?, hash(&edited)?);
+
+	let hashes = [&original, &other, &edited, &reupload].map(|g| hash(g).unwrap());
+	assert_eq!(duplicates(&hashes), vec![vec![0, 3]]);
+
+	Ok(())
+}
+
+#[test]
+fn item_and_self_destruct_rules() -> Result<(), String> {
+	let items = game("items")?;
+	let settings = items.start.match_settings();
+	assert_eq!(settings.item_spawn_frequency, ItemFrequency::OFF);
+	assert_eq!(settings.self_destruct_score, -1);
+
+	// turn items on in the raw Game Start block (`raw` header, payload sizes, event code)
+	let mut bytes = fs::read("tests/data/items.slp").unwrap();
+	let game_start = 16 + bytes[16] as usize;
+	assert_eq!(bytes[game_start], 0x36);
+	bytes[game_start + 1 + 15] = 2;
+	let items_on = peppi::game(&mut &bytes[..], None, None)
+		.map_err(|e| format!("couldn't deserialize game: {:?}", e))?;
+	assert_eq!(items_on.start.match_settings().item_spawn_frequency, ItemFrequency::MEDIUM);
+
+	let mut game = items_on;
+	game.start.set_match_settings(&MatchSettings {
+		item_spawn_frequency: ItemFrequency::VERY_HIGH,
+		self_destruct_score: 1,
+		..game.start.match_settings()
+	}).unwrap();
+	let game2 = round_trip_game(&game)?;
+	let settings2 = game2.start.match_settings();
+	assert_eq!(settings2.item_spawn_frequency, ItemFrequency::VERY_HIGH);
+	assert_eq!(settings2.self_destruct_score, 1);
+
+	let invalid = MatchSettings { self_destruct_score: 2, ..settings2.clone() };
+	assert!(game.start.set_match_settings(&invalid).is_err());
+	let invalid = MatchSettings { item_spawn_frequency: ItemFrequency(5), ..settings2 };
+	assert!(game.start.set_match_settings(&invalid).is_err());
+	assert_eq!(game.start.self_destruct_score, 1);
+
+	Ok(())
+}
+
+#[test]
+fn item_switches() -> Result<(), String> {
+	// recorded with every item switched on, as in all our fixtures (including the
+	// first byte's unused high bits)
+	let items = game("items")?;
+	assert_eq!(items.start.item_spawn_bitfield, [255; 5]);
+	let all = items.start.match_settings().items;
+	assert_eq!(all, (0 .. NUM_SPAWNABLE_ITEMS).map(item::Type).collect());
+
+	// items that aren't on the item switch, like the turnips Peach pulls in this
+	// replay, aren't listed
+	let spawned: HashSet<item::Type> = (0 .. items.frames.len())
+		.flat_map(|n| items.frames.items(n).into_iter().flatten())
+		.map(|i| i.r#type)
+		.collect();
+	assert_eq!(spawned, [item::Type::PEACH_TURNIP].into_iter().collect());
+	assert!(spawned.is_disjoint(&all));
+
+	// unchanged items leave the raw bitfield alone, including unused bits
+	let mut unmodified = items.start.clone();
+	unmodified.set_match_settings(&unmodified.match_settings()).unwrap();
+	assert_eq!(unmodified.item_spawn_bitfield, [255; 5]);
+
+	// only Poké Balls, Bob-ombs & Heart Containers
+	let mut switched = game("items")?;
+	let mut settings = switched.start.match_settings();
+	settings.items = [item::Type::POKE_BALL, item::Type::BOB_OMB, item::Type::HEART_CONTAINER].into_iter().collect();
+	switched.start.set_match_settings(&settings).unwrap();
+	assert_eq!(switched.start.match_settings().items, settings.items);
+	// plus the unused bits, which are still set
+	assert_eq!(switched.start.item_spawn_bitfield.iter().map(|b| b.count_ones()).sum::<u32>(), 3 + 5);
+
+	let game2 = round_trip_game(&switched)?;
+	assert_eq!(game2.start.item_spawn_bitfield, switched.start.item_spawn_bitfield);
+	assert_eq!(game2.start.match_settings().items, settings.items);
+
+	let bitfield = switched.start.item_spawn_bitfield;
+	settings.items.insert(item::Type::PEACH_TURNIP);
+	assert!(switched.start.set_match_settings(&settings).is_err());
+	assert_eq!(switched.start.item_spawn_bitfield, bitfield);
+
+	Ok(())
+}
+
+/// Offset & code of each event in the raw element of a replay.
+fn event_offsets(bytes: &[u8]) -> Vec<(usize, u8)> {
+	let raw_len = u32::from_be_bytes(bytes[11 .. 15].try_into().unwrap()) as usize;
+	let payloads_end = 16 + bytes[16] as usize;
+	let sizes: HashMap<u8, usize> = bytes[17 .. payloads_end].chunks(3)
+		.map(|p| (p[0], u16::from_be_bytes([p[1], p[2]]) as usize))
+		.collect();
+	let mut offsets = vec![];
+	let mut pos = payloads_end;
+	while pos < 15 + raw_len {
+		offsets.push((pos, bytes[pos]));
+		pos += 1 + sizes[&bytes[pos]];
+	}
+	offsets
+}
+
+#[test]
+fn truncated() -> Result<(), String> {
+	for name in ["game", "items"] {
+		let original = game(name)?;
+		assert!(!original.truncated);
+		let bytes = fs::read(format!("tests/data/{}.slp", name)).unwrap();
+
+		// 1000th frame's first post-frame update (after both pre-frame updates)
+		let (pos, _) = event_offsets(&bytes).into_iter()
+			.filter(|(_, code)| *code == 0x38)
+			.nth(2 * 1000)
+			.unwrap();
+
+		let game = peppi::game(&mut &bytes[.. pos], None, None)
+			.map_err(|e| format!("{}: couldn't deserialize truncated game: {:?}", name, e))?;
+		assert!(game.truncated);
+		assert_eq!(game.start, original.start);
+		assert_eq!(game.end.method, EndMethod::UNRESOLVED);
+		assert!(game.metadata_raw.is_empty());
+		assert_eq!(game.frames.len(), 1000);
+		match (&game.frames, &original.frames) {
+			(Frames::P2(f), Frames::P2(o)) => assert_eq!(f[..], o[.. 1000]),
+			_ => Err("wrong number of ports")?,
+		}
+
+		// ending mid-event is an error, as for corrupted payload sizes
+		assert!(peppi::game(&mut &bytes[.. pos + 10], None, None).is_err(), "{}", name);
+	}
+
+	// missing only the metadata
+	let bytes = fs::read("tests/data/game.slp").unwrap();
+	let raw_len = u32::from_be_bytes(bytes[11 .. 15].try_into().unwrap()) as usize;
+	let no_metadata = peppi::game(&mut &bytes[.. 15 + raw_len], None, None)
+		.map_err(|e| format!("couldn't deserialize truncated game: {:?}", e))?;
+	assert!(no_metadata.truncated);
+	assert_eq!(no_metadata.end, game("game")?.end);
+	assert_eq!(no_metadata.frames.len(), 5209);
+	assert!(peppi::game(&mut &bytes[.. 15 + raw_len + 20], None, None).is_err());
+
+	// nothing to recover
+	assert!(peppi::game(&mut &bytes[.. 100], None, None).is_err());
+
+	Ok(())
+}
+
+#[test]
+fn playback_queue() -> Result<(), String> {
+	let marth = game("game")?;
+	let console = game("console_name")?;
+	let queue = comm::playback_queue(&[
+		comm::QueueEntry { path: Path::new("game.slp"), game: &marth, start: -123, end: 500 },
+		comm::QueueEntry { path: Path::new("console_name.slp"), game: &console, start: 0, end: 60 },
+	]);
+	assert_eq!(queue, serde_json::json!({
+		"mode": "queue",
+		"replay": "",
+		"isRealTimeMode": false,
+		"outputOverlayFiles": true,
+		"queue": [
+			{
+				"path": "game.slp",
+				"startFrame": -123,
+				"endFrame": 500,
+				"gameStartAt": "2018-06-22T07:52:59Z",
+				"gameStation": "",
+			},
+			{
+				"path": "console_name.slp",
+				"startFrame": 0,
+				"endFrame": 60,
+				"gameStartAt": console.get_metadata("startAt").unwrap(),
+				"gameStation": "Station 1",
+			},
+		],
+	}));
+	Ok(())
+}
+
+#[test]
+fn serialize_unset_lras_initiator() -> Result<(), String> {
+	let mut v2 = game("v2.0")?;
+	v2.end = End { method: EndMethod::TIME, lras_initiator: None };
+	let reparsed = round_trip_game(&v2)?;
+	assert_eq!(reparsed.end, End { method: EndMethod::TIME, lras_initiator: Some(None) });
+	Ok(())
+}
+
+#[test]
+#[cfg(feature = "testing")]
+fn minimal_game() -> Result<(), String> {
+	use peppi::test_util::{minimal_game, MINIMAL_GAME_LAST_FRAME};
+
+	for v in [Version(0, 1, 0), Version(1, 0, 0), Version(2, 0, 1), Version(2, 2, 0), Version(3, 0, 0), Version(3, 7, 0), Version(3, 12, 0), Version(3, 14, 0)] {
+		let minimal = minimal_game(v, Stage::FINAL_DESTINATION, &[External::FOX, External::ICE_CLIMBERS]);
+		assert_eq!(minimal.start.slippi.version, v);
+		assert_eq!(minimal.frames.index(minimal.frames.len() - 1), MINIMAL_GAME_LAST_FRAME);
+		assert!(minimal.validate().is_empty(), "v{}", v);
+
+		let reparsed = round_trip_game(&minimal).map_err(|e| format!("v{}: {}", v, e))?;
+		assert_eq!(reparsed, minimal, "v{}", v);
+	}
+
+	let solo = minimal_game(Version(3, 12, 0), Stage::BATTLEFIELD, &[External::MARTH]);
+	assert_eq!(solo.stage(), Stage::BATTLEFIELD);
+	assert!(matches!(solo.frames, Frames::P1(_)));
+
+	// metadata lists both Ice Climbers, as in real replays (see `ics`)
+	let ics = minimal_game(Version(3, 12, 0), Stage::BATTLEFIELD, &[External::ICE_CLIMBERS]);
+	let characters = ics.metadata.players.unwrap()[0].characters.clone().unwrap();
+	let frames = ics.frames.len();
+	assert_eq!(characters, HashMap::from([(Internal::NANA, frames), (Internal::POPO, frames)]));
+	Ok(())
+}
+
+#[test]
+#[cfg(feature = "testing")]
+fn serialize_default_pre_damage() -> Result<(), String> {
+	let mut synthetic = peppi::test_util::minimal_game(Version(1, 4, 0), Stage::FINAL_DESTINATION,
+		&[External::FOX, External::ICE_CLIMBERS]);
+	match &mut synthetic.frames {
+		Frames::P2(frames) => for (n, f) in frames.iter_mut().enumerate() {
+			for p in &mut f.ports {
+				p.leader.pre.damage = None;
+				p.leader.post.damage = 10.0 + n as f32;
+				if let Some(nana) = &mut p.follower {
+					nana.pre.damage = None;
+					nana.post.damage = 20.0 + n as f32;
+				}
+			}
+		},
+		_ => Err("wrong number of ports")?,
+	}
+
+	let reparsed = round_trip_game(&synthetic)?;
+	// the previous frame's post-frame damage, or this frame's for the first frame
+	for n in 0 .. reparsed.frames.len() {
+		let expected = 10.0 + n.saturating_sub(1) as f32;
+		assert_eq!(reparsed.frames.port_data(n, 0).leader.pre.damage, Some(expected));
+		assert_eq!(reparsed.frames.port_data(n, 1).leader.pre.damage, Some(expected));
+		let nana = reparsed.frames.port_data(n, 1).follower.as_ref().unwrap();
+		assert_eq!(nana.pre.damage, Some(expected + 10.0));
+	}
+
+	// explicit values are kept
+	match &mut synthetic.frames {
+		Frames::P2(frames) => frames[5].ports[0].leader.pre.damage = Some(99.0),
+		_ => Err("wrong number of ports")?,
+	}
+	let reparsed = round_trip_game(&synthetic)?;
+	assert_eq!(reparsed.frames.port_data(5, 0).leader.pre.damage, Some(99.0));
+
+	// frames 5-9 rolled back (with different damage) & replayed: replayed frames follow
+	// the frame before them, not the rolled-back frame written just before
+	match &mut synthetic.frames {
+		Frames::P2(frames) => {
+			frames[5].ports[0].leader.pre.damage = None;
+			let mut rolled_back: Vec<_> = frames[5 .. 10].to_vec();
+			for (n, f) in rolled_back.iter_mut().enumerate() {
+				f.ports[0].leader.pre.damage = None;
+				f.ports[0].leader.post.damage = 50.0 + n as f32;
+			}
+			frames.splice(5 .. 5, rolled_back);
+		},
+		_ => Err("wrong number of ports")?,
+	}
+	let reparsed = round_trip_game(&synthetic)?;
+	assert_eq!(reparsed.frames.len(), synthetic.frames.len() - 5);
+	for n in 1 .. reparsed.frames.len() {
+		let expected = reparsed.frames.port_data(n - 1, 0).leader.post.damage;
+		assert_eq!(reparsed.frames.port_data(n, 0).leader.pre.damage, Some(expected));
+	}
+
+	Ok(())
+}
+
+#[test]
+fn serialize_item_order() -> Result<(), String> {
+	use serde::ser::{ItemOrder, Opts};
+
+	let items = game("items")?;
+	let mut default = Vec::new();
+	serde::ser::serialize(&mut io::Cursor::new(&mut default), &items).map_err(|e| e.to_string())?;
+	for item_order in [ItemOrder::BeforePost, ItemOrder::AfterPost] {
+		let mut buf = Vec::new();
+		let opts = Opts { item_order };
+		serde::ser::serialize_with_opts(&mut io::Cursor::new(&mut buf), &items, Some(&opts)).map_err(|e| e.to_string())?;
+		assert_eq!(buf == default, item_order == ItemOrder::BeforePost);
+		assert_eq!(buf.len(), default.len());
+		let reparsed = peppi::game(&mut &buf[..], None, None).map_err(|e| format!("{:?}: {:?}", item_order, e))?;
+		assert_eq!(reparsed, items, "{:?}", item_order);
+	}
+	Ok(())
+}
+
+#[test]
+fn report() -> Result<(), String> {
+	let report = game("game")?.report();
+	let lines: Vec<&str> = report.lines().collect();
+	assert_eq!(lines[0], "YOSHIS_STORY (Slippi v1.0.0)");
+	assert_eq!(lines[1], "2018-06-22 07:52:59 UTC, 01:26.81 (5209 frames), clock: 06:35.25");
+	assert_eq!(lines[2], "P1: MARTH (Black): 4 stocks, 7% (winner)");
+	assert!(lines[3].starts_with("P2: FOX (Default): 0 stocks"), "{}", lines[3]);
+	assert_eq!(lines[4], "Result: RESOLVED");
+
+	let report = game("v3.12")?.report();
+	assert!(report.contains("P1: MARTH (Black) xxxxxx (XX#111): 4 stocks, 0% (winner)"), "{}", report);
+	assert!(report.ends_with("Result: NO_CONTEST (LRAS by P2)"), "{}", report);
+	Ok(())
+}
+
+#[test]
+fn indexed_seek_frame() -> Result<(), String> {
+	for name in ["game", "ics", "v3.12"] {
+		let game = game(name)?;
+		let mut buf = Vec::new();
+		serde::indexed::write(&mut buf, &game).map_err(|e| e.to_string())?;
+		let mut file = serde::indexed::PeppiFile::open(io::Cursor::new(buf)).map_err(|e| e.to_string())?;
+
+		assert_eq!(file.len(), game.frames.len());
+		assert_eq!(file.start, game.start);
+		assert_eq!(file.end, game.end);
+		assert_eq!(file.metadata_raw, game.metadata_raw);
+
+		// the middle frame, then the rest in reverse so none follows its predecessor
+		let mid = game.frames.len() / 2;
+		for n in std::iter::once(mid).chain((0 .. game.frames.len()).rev()) {
+			let frame = file.seek_frame(n).map_err(|e| e.to_string())?;
+			assert_eq!(frame.index, game.frames.index(n), "{}: frame {}", name, n);
+			for (idx, port) in frame.ports.iter().enumerate() {
+				assert_eq!(port, game.frames.port_data(n, idx), "{}: frame {}", name, n);
+			}
+			assert_eq!(frame.start.as_ref(), game.frames.start(n));
+			assert_eq!(frame.items.as_ref(), game.frames.items(n));
+		}
+	}
+
+	Ok(())
+}
+
+#[test]
+fn rebuild_metadata() -> Result<(), String> {
+	// no `players` recorded by this replay's metadata
+	let mut transform = game("transform")?;
+	transform.rebuild_metadata().map_err(|e| e.to_string())?;
+	let players = transform.metadata.players.as_ref().unwrap();
+	assert_eq!(players.iter().map(|p| p.port).collect::<Vec<_>>(), vec![Port::P2, Port::P4]);
+	assert_eq!(players[1].characters, Some(HashMap::from([
+		(Internal::ZELDA, 401),
+		(Internal::SHEIK, 3133),
+	])));
+	assert_eq!(transform.get_metadata("lastFrame"), Some(&3410.into()));
+
+	// recomputing matches what Slippi recorded
+	for name in ["ics", "netplay"] {
+		let mut game = game(name)?;
+		let expected = game.metadata_raw.clone();
+		game.rebuild_metadata().map_err(|e| e.to_string())?;
+		assert_eq!(game.metadata_raw, expected);
+	}
+
+	Ok(())
+}
+
+#[test]
+fn parse_limited() -> Result<(), String> {
+	let bytes = fs::read("tests/data/netplay.slp").unwrap();
+
+	let game = peppi::parse_limited(&mut &bytes[..], 128).map_err(|e| e.to_string())?;
+	assert_eq!(game.frames.len(), 128);
+
+	let e = peppi::parse_limited(&mut &bytes[..], 127).unwrap_err();
+	assert_eq!(e.error.to_string(), "too many frames (max: 127)");
+
+	Ok(())
+}
+
+#[test]
+fn game_clock() -> Result<(), String> {
+	let game = game("game")?;
+	let clock = |frame| game.clock_at(frame).unwrap().to_string();
+	// 8-minute timer, which starts at "Go!"
+	assert_eq!(clock(-123), "08:00.00");
+	assert_eq!(clock(0), "08:00.00");
+	assert_eq!(clock(1), "07:59.98");
+	assert_eq!(clock(2 * 60 * 60 + 45 * 60 + 30), "05:14.50");
+	assert_eq!(clock(8 * 60 * 60 - 1), "00:00.01");
+	assert_eq!(clock(8 * 60 * 60), "00:00.00");
+	assert_eq!(clock(9 * 60 * 60), "00:00.00");
+
+	let c = GameClock(5 * 3600 + 14 * 60 + 30);
+	assert_eq!((c.minutes(), c.seconds(), c.centiseconds()), (5, 14, 50));
+	Ok(())
+}
+
+#[test]
+fn controller_frames() -> Result<(), String> {
+	let game = game("buttons_lrzs")?;
+	let frames = game.controller_frames(Port::P1);
+	assert_eq!(frames.len(), game.frames.len());
+	assert!(game.controller_frames(Port::P4).is_empty());
+
+	// starting from frame index -123
+	let pre = game.frames.port_data(0, 0).leader.pre;
+	assert_eq!(frames[0].joystick, pre.joystick);
+
+	let n = frames.iter().position(|f| f.buttons == Physical::L).unwrap();
+	let pre = game.frames.port_data(n, 0).leader.pre;
+	assert_eq!(frames[n], ControllerState {
+		joystick: pre.joystick,
+		cstick: pre.cstick,
+		l: pre.triggers.physical.l,
+		r: pre.triggers.physical.r,
+		buttons: Physical::L,
+	});
+
+	Ok(())
+}
+
+#[test]
+fn write_full_json() -> Result<(), String> {
+	let netplay = rolled_back("netplay")?;
+
+	let mut buf = Vec::new();
+	netplay.write_full_json(&mut buf).map_err(|e| e.to_string())?;
+	let json: serde_json::Value = serde_json::from_slice(&buf).map_err(|e| e.to_string())?;
+
+	let frames = json["frames"].as_array().unwrap();
+	assert_eq!(frames.len(), netplay.logical_frame_count());
+	assert_eq!(frames[0]["index"], -123);
+	assert_eq!(frames[127]["index"], 4);
+	assert_eq!(frames[10]["ports"][1]["leader"]["post"]["stocks"], 4);
+	assert_eq!(json["start"]["slippi"]["version"], serde_json::json!([3, 7, 0]));
+	assert_eq!(json["metadata"]["playedOn"], "dolphin");
+	Ok(())
+}
+
+#[derive(Default)]
+struct EventCounter {
+	frames: usize,
+	posts: usize,
+	follower_posts: usize,
+	items: usize,
+	/// whether we're between `on_frame_start` & `on_frame_end`
+	in_frame: bool,
+}
+
+impl FrameVisitor for EventCounter {
+	fn on_frame_start(&mut self, _: i32, _: Option<&frame::Start>) -> io::Result<()> {
+		assert!(!self.in_frame);
+		self.in_frame = true;
+		Ok(())
+	}
+
+	fn on_post(&mut self, _: i32, _: Port, is_follower: bool, _: &frame::Post) -> io::Result<()> {
+		assert!(self.in_frame);
+		match is_follower {
+			true => self.follower_posts += 1,
+			false => self.posts += 1,
+		}
+		Ok(())
+	}
+
+	fn on_item(&mut self, _: i32, _: &Item) -> io::Result<()> {
+		self.items += 1;
+		Ok(())
+	}
+
+	fn on_frame_end(&mut self, _: i32, _: Option<&frame::End>) -> io::Result<()> {
+		assert!(self.in_frame);
+		self.in_frame = false;
+		self.frames += 1;
+		Ok(())
+	}
+}
+
+#[test]
+fn frame_visitor() -> Result<(), String> {
+	for name in ["game", "ics", "items"] {
+		let g = game(name)?;
+		let mut counter = EventCounter::default();
+		g.accept(&mut counter).unwrap();
+		assert_eq!(counter.frames, g.frames.len());
+		assert_eq!(counter.posts, g.start.players.len() * g.frames.len());
+		let items: usize = (0 .. g.frames.len()).filter_map(|n| g.frames.items(n)).map(Vec::len).sum();
+		assert_eq!(counter.items, items);
+	}
+
+	let ics = game("ics")?;
+	let mut counter = EventCounter::default();
+	ics.accept(&mut counter).unwrap();
+	assert!(counter.follower_posts > 0);
+
+	Ok(())
+}
+
+/// Event codes of `FrameVisitor` callbacks, in order.
+#[derive(Default)]
+struct EventLog(Vec<u8>);
+
+impl FrameVisitor for EventLog {
+	fn on_frame_start(&mut self, _: i32, start: Option<&frame::Start>) -> io::Result<()> {
+		if start.is_some() {
+			self.0.push(serde::de::Event::FrameStart as u8);
+		}
+		Ok(())
+	}
+
+	fn on_pre(&mut self, _: i32, _: Port, _: bool, _: &frame::Pre) -> io::Result<()> {
+		self.0.push(serde::de::Event::FramePre as u8);
+		Ok(())
+	}
+
+	fn on_post(&mut self, _: i32, _: Port, _: bool, _: &frame::Post) -> io::Result<()> {
+		self.0.push(serde::de::Event::FramePost as u8);
+		Ok(())
+	}
+
+	fn on_item(&mut self, _: i32, _: &Item) -> io::Result<()> {
+		self.0.push(serde::de::Event::Item as u8);
+		Ok(())
+	}
+
+	fn on_frame_end(&mut self, _: i32, end: Option<&frame::End>) -> io::Result<()> {
+		if end.is_some() {
+			self.0.push(serde::de::Event::FrameEnd as u8);
+		}
+		Ok(())
+	}
+}
+
+#[test]
+fn frame_visitor_order() -> Result<(), String> {
+	use serde::de::Event;
+	let frame_events = [Event::FrameStart, Event::FramePre, Event::Item, Event::FramePost, Event::FrameEnd]
+		.map(|e| e as u8);
+	for name in ["game", "ics", "items"] {
+		let bytes = fs::read(format!("tests/data/{}.slp", name)).unwrap();
+		let recorded: Vec<u8> = event_offsets(&bytes).into_iter()
+			.map(|(_, code)| code)
+			.filter(|code| frame_events.contains(code))
+			.collect();
+		let mut log = EventLog::default();
+		game(name)?.accept(&mut log).unwrap();
+		assert_eq!(log.0, recorded, "{}", name);
+	}
+	Ok(())
+}
+
+/// Reads a little-endian, C-order `.npy` file: its dtype, shape & raw data.
+#[cfg(feature = "npy")]
+fn read_npy(path: &Path) -> (String, Vec<usize>, Vec<u8>) {
+	let bytes = fs::read(path).unwrap();
+	assert_eq!(&bytes[.. 8], b"\x93NUMPY\x01\x00");
+	let len = u16::from_le_bytes([bytes[8], bytes[9]]) as usize;
+	assert_eq!((10 + len) % 64, 0);
+	let header = std::str::from_utf8(&bytes[10 .. 10 + len]).unwrap();
+	assert!(header.ends_with('\n'));
+	assert!(header.contains("'fortran_order': False"));
+
+	let field = |key: &str| {
+		let start = header.find(key).unwrap() + key.len();
+		&header[start ..]
+	};
+	let descr = field("'descr': '").split('\'').next().unwrap().to_string();
+	let shape = field("'shape': (").split(')').next().unwrap()
+		.split(',')
+		.map(str::trim)
+		.filter(|s| !s.is_empty())
+		.map(|s| s.parse().unwrap())
+		.collect();
+	(descr, shape, bytes[10 + len ..].to_vec())
+}
+
+#[cfg(feature = "npy")]
+fn f32s(data: &[u8]) -> Vec<f32> {
+	data.chunks(4).map(|c| f32::from_le_bytes(c.try_into().unwrap())).collect()
+}
+
+#[test]
+#[cfg(feature = "npy")]
+fn write_npy() -> Result<(), String> {
+	let dir = std::env::temp_dir().join(format!("peppi-npy-{}", std::process::id()));
+	fs::create_dir_all(&dir).unwrap();
+
+	let game = game("v3.12")?;
+	game.write_npy(&dir, Port::P2).map_err(|e| format!("couldn't write npy: {:?}", e))?;
+	let posts: Vec<_> = game.port_frames(Port::P2, None).ok_or("missing P2")?
+		.map(|f| f.data.post)
+		.collect();
+	let n = posts.len();
+
+	let (descr, shape, data) = read_npy(&dir.join("position.npy"));
+	assert_eq!((descr.as_str(), shape), ("<f4", vec![n, 2]));
+	let positions = f32s(&data);
+	assert_eq!(positions[200 .. 202], [posts[100].position.x, posts[100].position.y]);
+
+	let (descr, shape, data) = read_npy(&dir.join("velocity.npy"));
+	assert_eq!((descr.as_str(), shape), ("<f4", vec![n, 4]));
+	let v = posts[100].velocities.unwrap();
+	assert_eq!(f32s(&data)[400 .. 404], [v.autogenous.x, v.autogenous.y, v.knockback.x, v.knockback.y]);
+
+	let (descr, shape, data) = read_npy(&dir.join("damage.npy"));
+	assert_eq!((descr.as_str(), shape), ("<f4", vec![n]));
+	assert_eq!(f32s(&data), posts.iter().map(|p| p.damage).collect::<Vec<_>>());
+
+	let (descr, shape, data) = read_npy(&dir.join("state.npy"));
+	assert_eq!((descr.as_str(), shape), ("<u2", vec![n]));
+	let states: Vec<u16> = data.chunks(2).map(|c| u16::from_le_bytes([c[0], c[1]])).collect();
+	assert_eq!(states, posts.iter().map(|p| u16::from(p.state)).collect::<Vec<_>>());
+
+	assert!(game.write_npy(&dir, Port::P3).is_err());
+	fs::remove_dir_all(&dir).unwrap();
+	Ok(())
+}
+
+#[test]
+#[cfg(feature = "testing")]
+fn active_ports() -> Result<(), String> {
+	use peppi::{model::primitives::PortMask, test_util::minimal_game};
+
+	let solo = minimal_game(Version(3, 12, 0), Stage::BATTLEFIELD, &[External::FOX]);
+	assert_eq!(solo.active_ports(), PortMask::P1);
+	assert_eq!(solo.active_ports().len(), 1);
+
+	let ports = game("game")?.active_ports();
+	assert_eq!(ports, PortMask::P1 | PortMask::P2);
+	assert!(ports.contains(Port::P2) && !ports.contains(Port::P3));
+
+	let ffa = minimal_game(Version(3, 12, 0), Stage::BATTLEFIELD,
+		&[External::FOX, External::FALCO, External::MARTH, External::SHEIK]);
+	assert_eq!(ffa.active_ports().iter().collect::<Vec<_>>(), vec![Port::P1, Port::P2, Port::P3, Port::P4]);
+
+	let ports = game("transform")?.active_ports();
+	assert_eq!(ports.iter().collect::<Vec<_>>(), vec![Port::P2, Port::P4]);
+
+	let mut p1_p3 = minimal_game(Version(3, 12, 0), Stage::BATTLEFIELD, &[External::FOX, External::FALCO]);
+	p1_p3.start.players[1].port = Port::P3;
+	let ports = p1_p3.active_ports();
+	assert_eq!(ports, PortMask::P1 | PortMask::P3);
+	assert_eq!((ports.len(), ports.is_empty()), (2, false));
+	assert_eq!(ports.iter().collect::<Vec<_>>(), vec![Port::P1, Port::P3]);
+	assert!(!ports.contains(Port::P2));
+
+	assert!(PortMask::default().is_empty());
+	assert_eq!(PortMask::from(Port::P4), PortMask::P4);
+
+	Ok(())
+}
+
+#[test]
+fn meaningful_games() -> Result<(), String> {
+	assert!(game("game")?.is_meaningful(None));
+	assert!(game("v2.0")?.is_meaningful(None));
+
+	// a 5-second game in which nobody touches their controller
+	let mut idle = game("game")?;
+	match &mut idle.frames {
+		Frames::P2(frames) => {
+			frames.truncate(123 + 300);
+			for f in frames.iter_mut() {
+				for p in &mut f.ports {
+					p.leader.pre.joystick = Position::default();
+					p.leader.pre.cstick = Position::default();
+					p.leader.pre.buttons.physical = Physical::NONE;
+				}
+			}
+		},
+		_ => Err("wrong number of ports")?,
+	}
+	assert!(!idle.is_meaningful(None));
+
+	// still not meaningful when it's long enough, since nobody played
+	let short = Some(MeaningfulOpts { min_frames: 300, ..Default::default() });
+	assert!(!idle.is_meaningful(short));
+	let lenient = MeaningfulOpts { min_frames: 300, require_activity: false };
+	assert!(idle.is_meaningful(Some(lenient)));
+	assert!(!idle.is_meaningful(Some(MeaningfulOpts { min_frames: 301, ..lenient })));
+
+	// Nana's inputs are the game's own, so Ice Climbers whose Popo sits still didn't play
+	let mut ics = game("ics")?;
+	match &mut ics.frames {
+		Frames::P2(frames) => {
+			frames.truncate(123 + 100);
+			for f in frames.iter_mut() {
+				let popo = &mut f.ports[0].leader;
+				popo.pre.joystick = Position::default();
+				popo.pre.cstick = Position::default();
+				popo.pre.buttons.physical = Physical::NONE;
+			}
+		},
+		_ => Err("wrong number of ports")?,
+	}
+	assert!(ics.frames.port_data(123, 0).follower.as_ref().is_some_and(|nana| nana.pre.joystick != Position::default()));
+	assert!(!ics.is_meaningful(Some(MeaningfulOpts { min_frames: 100, ..Default::default() })));
+	assert!(ics.is_meaningful(Some(MeaningfulOpts { min_frames: 100, require_activity: false })));
+
+	Ok(())
+}
+
+#[test]
+fn trim_to_action() -> Result<(), String> {
+	// both players land from their entry platforms by frame -40, then act at -39
+	let mut game = game("game")?;
+	assert_eq!(game.go_frame(), -39);
+	for port in [Port::P1, Port::P2] {
+		let runs = game.state_runs(port);
+		assert_eq!(runs[4], (State::Common(Common::LANDING), runs[4].1, -40), "{:?}", port);
+		assert_eq!(runs[5].1, -39, "{:?}", port);
+	}
+
+	// Puff walks at -33, before Popo moves (at -19)
+	let ics = self::game("ics")?;
+	assert_eq!(ics.go_frame(), -33);
+	assert_eq!(ics.first_input_frame(Port::P1), Some(-19));
+
+	let len = game.frames.len();
+	game.trim_to_action();
+	assert_eq!(game.frames.len(), len - 84);
+	assert_eq!(game.frames.index(0), -39);
+	let is_entry = |s: &State| matches!(s, State::Common(Common::ENTRY | Common::ENTRY_START | Common::ENTRY_END));
+	assert!(game.state_runs(Port::P1).iter().all(|(s, ..)| !is_entry(s)));
+
+	// trimmed games aren't valid replays
+	let mut buf = io::Cursor::new(Vec::new());
+	let err = serde::ser::serialize(&mut buf, &game).unwrap_err();
+	assert!(matches!(err, serde::ser::UnparseError::FrameIndex { frame: -39, expected: -123 }), "{:?}", err);
+	assert!(buf.into_inner().is_empty());
+
+	// nobody ever acts
+	let mut idle = self::game("game")?;
+	match &mut idle.frames {
+		Frames::P2(frames) => for f in frames.iter_mut() {
+			for p in &mut f.ports {
+				p.leader.post.state = State::Common(Common::WAIT);
+			}
+		},
+		_ => Err("wrong number of ports")?,
+	}
+	assert_eq!(idle.go_frame(), 5086);
+
+	Ok(())
+}
+
+#[test]
+fn frame_rows() -> Result<(), String> {
+	for name in ["game", "ics", "transform"] {
+		let g = game(name)?;
+		let rows = g.rows();
+		assert_eq!(rows.len(), g.logical_frame_count() * g.active_ports().len(), "{}", name);
+	}
+
+	let transform = game("transform")?;
+	let rows = transform.rows();
+	assert_eq!(rows[0 .. 2].iter().map(|r| (r.index, r.port)).collect::<Vec<_>>(),
+		vec![(-123, Port::P2), (-123, Port::P4)]);
+	assert_eq!(rows[3], FrameRow {
+		index: -122,
+		port: Port::P4,
+		data: transform.frames.port_data(1, 1).leader,
+	});
+
+	// rolled-back frames are skipped
+	let netplay = rolled_back("netplay")?;
+	assert!(netplay.has_rollback());
+	assert_eq!(netplay.rows().len(), 128 * 2);
+
+	Ok(())
+}
+
+#[test]
+fn position_heatmap() -> Result<(), String> {
+	// Yoshi's Story: x from -175.7 to 173.6, y from -91 to 168
+	let mut game = game("game")?;
+	assert_eq!(game.stage(), Stage::YOSHIS_STORY);
+	let heatmap = game.position_heatmap(Port::P1, 8, 4);
+	assert_eq!(heatmap.len(), 4);
+	assert!(heatmap.iter().all(|row| row.len() == 8));
+	assert_eq!(heatmap.iter().flatten().sum::<u32>() as usize, game.logical_frame_count());
+
+	match &mut game.frames {
+		Frames::P2(frames) => {
+			for (n, f) in frames.iter_mut().enumerate() {
+				f.ports[0].leader.post.position = match n % 4 {
+					0 => Position { x: 0.0, y: 0.0 }, // stage center
+					1 => Position { x: -170.0, y: 160.0 }, // top left
+					2 => Position { x: 500.0, y: -500.0 }, // past the bottom right corner
+					_ => Position { x: 100.0, y: 50.0 },
+				};
+			}
+		},
+		_ => Err("wrong number of ports")?,
+	}
+	let heatmap = game.position_heatmap(Port::P1, 8, 4);
+	let len = game.frames.len() as u32;
+	let count = |m: u32| (0 .. len).filter(|n| n % 4 == m).count() as u32;
+	assert_eq!(heatmap[1][4], count(0));
+	assert_eq!(heatmap[3][0], count(1));
+	assert_eq!(heatmap[0][7], count(2));
+	// cells are 43.7 wide & 64.75 tall, e.g. x = 100 is (100 + 175.7) / 43.7 = 6.3 cells in
+	assert_eq!(heatmap[2][6], count(3));
+	assert_eq!(heatmap.iter().flatten().sum::<u32>(), len);
+
+	assert_eq!(game.position_heatmap(Port::P3, 8, 4), Vec::<Vec<u32>>::new());
+	assert_eq!(game.position_heatmap(Port::P1, 0, 0), Vec::<Vec<u32>>::new());
+	game.start.stage = Stage(99);
+	assert_eq!(game.position_heatmap(Port::P1, 8, 4), Vec::<Vec<u32>>::new());
+
+	Ok(())
+}
+
+#[test]
+fn special_moves() -> Result<(), String> {
+	let game = game("v2.0")?;
+	let fox = game.port_frames(Port::P2, None).unwrap()
+		.map(|f| f.data.post)
+		.filter(|p| p.special_move(p.character) == Some(SpecialMove::Reflector))
+		.collect::<Vec<_>>();
+	assert!(!fox.is_empty());
+	assert!(fox.iter().all(|p| matches!(p.state, State::Fox(s) if
+		(Fox::REFLECTOR_GROUND_STARTUP.0 ..= Fox::REFLECTOR_AIR_CHANGE_DIRECTION.0).contains(&s.0))));
+
+	// the same state id means something else for another character
+	let shine = fox[0];
+	assert_eq!(shine.special_move(Internal::MARTH), Some(SpecialMove::DancingBlade));
+	assert_eq!(shine.special_move(Internal::MARIO), None);
+
+	// Jigglypuff never shines
+	assert!(game.port_frames(Port::P1, None).unwrap()
+		.all(|f| f.data.post.special_move(f.data.post.character) != Some(SpecialMove::Reflector)));
+
+	let wait = frame::Post { state: State::Common(Common::WAIT), ..shine };
+	assert_eq!(wait.special_move(Internal::FOX), None);
+
+	Ok(())
+}
+
+#[test]
+fn metadata_date() -> Result<(), String> {
+	let parse = |json: serde_json::Value| metadata::parse(json.as_object().unwrap());
+
+	let m = parse(serde_json::json!({"startAt": "2018-06-22T07:52:59Z"})).unwrap();
+	assert_eq!(m.date, Some("2018-06-22T07:52:59Z".parse::<DateTime<Utc>>().unwrap()));
+
+	// older Slippi versions leave off the time zone
+	let m = parse(serde_json::json!({"startAt": "2018-06-22T07:52:59"})).unwrap();
+	assert_eq!(m.date, Some("2018-06-22T07:52:59Z".parse::<DateTime<Utc>>().unwrap()));
+
+	assert_eq!(parse(serde_json::json!({})).unwrap().date, None);
+	assert!(parse(serde_json::json!({"startAt": "yesterday"})).is_err());
+	assert!(parse(serde_json::json!({"startAt": 1})).is_err());
+
+	Ok(())
+}
+
+#[test]
+fn remap_ports() -> Result<(), String> {
+	let original = game("game")?;
+	let mut swapped = game("game")?;
+	swapped.remap_ports([Some(Port::P2), Some(Port::P1), None, None]).map_err(|e| e.to_string())?;
+	let characters = |g: &Game| g.start.players.iter().map(|p| (p.port, p.character)).collect::<Vec<_>>();
+	assert_eq!(characters(&swapped), vec![(Port::P1, External::FOX), (Port::P2, External::MARTH)]);
+	assert_eq!(swapped.metadata.players.as_ref().unwrap().iter().map(|p| p.port).collect::<Vec<_>>(), vec![Port::P1, Port::P2]);
+
+	let n = 1000;
+	assert_eq!(swapped.frames.port_data(n, 0).leader.pre, original.frames.port_data(n, 1).leader.pre);
+	assert_eq!(swapped.frames.port_data(n, 1).leader.pre, original.frames.port_data(n, 0).leader.pre);
+	let hit_by = |g: &Game, idx: usize| (0 .. g.frames.len())
+		.filter_map(|n| g.frames.port_data(n, idx).leader.post.last_hit_by)
+		.collect::<Vec<_>>();
+	let (fox_hit_by, marth_hit_by) = (hit_by(&swapped, 0), hit_by(&swapped, 1));
+	assert!(!fox_hit_by.is_empty() && fox_hit_by.iter().all(|p| *p == Port::P2));
+	assert!(!marth_hit_by.is_empty() && marth_hit_by.iter().all(|p| *p == Port::P1));
+
+	let reparsed = round_trip_game(&swapped)?;
+	assert_eq!(reparsed.start, swapped.start);
+	assert_eq!(reparsed.metadata, swapped.metadata);
+	assert!(reparsed.frames == swapped.frames);
+
+	// swapping back restores the original
+	let mut restored = reparsed;
+	restored.remap_ports([Some(Port::P2), Some(Port::P1), None, None]).map_err(|e| e.to_string())?;
+	assert_eq!(restored.start, original.start);
+	assert_eq!(restored.metadata_raw, original.metadata_raw);
+	assert!(restored.frames == original.frames);
+
+	// non-contiguous ports
+	let mut nintendont = game("nintendont")?;
+	assert_eq!(round_trip_game(&nintendont)?.frames.len(), nintendont.frames.len());
+	nintendont.remap_ports([None, Some(Port::P1), None, Some(Port::P2)]).map_err(|e| e.to_string())?;
+	let reparsed = round_trip_game(&nintendont)?;
+	assert_eq!(reparsed.start.players.iter().map(|p| p.port).collect::<Vec<_>>(), vec![Port::P1, Port::P2]);
+	assert!(reparsed.frames == nintendont.frames);
+
+	let mut game = game("game")?;
+	let e = game.remap_ports([Some(Port::P2), None, None, None]).unwrap_err();
+	assert_eq!(e.to_string(), "multiple players mapped to port P2");
+	assert!(game.frames == original.frames);
+	assert_eq!(game.start, original.start);
+
+	Ok(())
+}
+
+#[test]
+fn unparse_trace() -> Result<(), String> {
+	use serde::{de::Event, ser::TraceKind};
+
+	for name in ["v0.1", "game", "ics", "v3.12"] {
+		let game = game(name)?;
+		let mut buf = Vec::new();
+		let (len, trace) = serde::ser::unparse_trace(&mut buf, &game).map_err(|e| e.to_string())?;
+		assert_eq!(len, buf.len(), "{}", name);
+		assert_eq!(buf, serde::ser::serialize_to_vec(&game).map_err(|e| e.to_string())?, "{}", name);
+
+		// contiguous, & covering everything written
+		assert_eq!(trace.iter().map(|t| t.size).sum::<usize>(), len, "{}", name);
+		for w in trace.windows(2) {
+			assert_eq!(w[0].offset + w[0].size, w[1].offset, "{}", name);
+		}
+
+		let count = |kind: TraceKind| trace.iter().filter(|t| t.kind == kind).count();
+		assert_eq!(trace.first().map(|t| t.kind), Some(TraceKind::RawHeader));
+		assert_eq!(trace.last().map(|t| t.kind), Some(TraceKind::Metadata));
+		assert_eq!(count(TraceKind::Event(Event::GameStart)), 1);
+		assert_eq!(count(TraceKind::Event(Event::GameEnd)), 1);
+		let characters: usize = (0 .. game.frames.len()).map(|n| (0 .. game.start.players.len())
+			.map(|idx| 1 + game.frames.port_data(n, idx).follower.is_some() as usize)
+			.sum::<usize>()).sum();
+		assert_eq!(count(TraceKind::Event(Event::FramePre)), characters, "{}", name);
+		assert_eq!(count(TraceKind::Event(Event::FramePost)), characters, "{}", name);
+
+		// the raw element round-trips, so its events are where the original's are
+		let original = fs::read(format!("tests/data/{}.slp", name)).unwrap();
+		let events: Vec<(usize, u8)> = trace.iter().filter_map(|t| match t.kind {
+			TraceKind::Event(e) => Some((t.offset, e as u8)),
+			TraceKind::MessageSplitter => Some((t.offset, 0x10)),
+			_ => None,
+		}).collect();
+		assert_eq!(events, event_offsets(&original), "{}", name);
+	}
+
+	// spec sizes (command byte + payload)
+	let size = |name: &str, event: Event| -> Result<Vec<usize>, String> {
+		let mut buf = Vec::new();
+		let (_, trace) = serde::ser::unparse_trace(&mut buf, &game(name)?).map_err(|e| e.to_string())?;
+		let mut sizes: Vec<usize> = trace.iter()
+			.filter(|t| t.kind == TraceKind::Event(event))
+			.map(|t| t.size)
+			.collect();
+		sizes.dedup();
+		Ok(sizes)
+	};
+	assert_eq!(size("game", Event::GameEnd)?, [2]);
+	assert_eq!(size("v3.12", Event::GameEnd)?, [3]);
+	assert_eq!(size("v3.12", Event::FrameStart)?, [13]);
+	assert_eq!(size("v3.12", Event::FrameEnd)?, [9]);
+
+	Ok(())
+}
+
+/// Serializes `game` as segments split at `splits`, & concatenates them.
+fn segmented(game: &Game, splits: &[usize]) -> Result<Vec<u8>, serde::ser::UnparseError> {
+	let mut bytes = Vec::new();
+	let mut resume = None;
+	let mut start = 0;
+	for end in splits.iter().copied().chain([game.frames.len()]) {
+		let mut segment = Vec::new();
+		resume = Some(serde::ser::serialize_segment(&mut segment, game, start .. end, resume, None)?);
+		bytes.extend(segment);
+		start = end;
+	}
+	Ok(bytes)
+}
+
+#[test]
+fn serialize_segment() -> Result<(), String> {
+	use serde::ser::{Resume, UnparseError};
+
+	for name in ["game", "ics", "v3.12"] {
+		let game = game(name)?;
+		let split = game.frames.len() / 2;
+		let mut first = Vec::new();
+		let resume = serde::ser::serialize_segment(&mut first, &game, 0 .. split, None, None)
+			.map_err(|e| e.to_string())?;
+		assert_eq!(resume.index, game.frames.index(split), "{}", name);
+
+		let bytes = segmented(&game, &[split]).map_err(|e| e.to_string())?;
+		let parsed = peppi::game(&mut &bytes[..], None, None).map_err(|e| e.to_string())?;
+		assert!(!parsed.truncated, "{}", name);
+		assert_eq!(parsed.start, game.start, "{}", name);
+		assert_eq!(parsed.end, game.end, "{}", name);
+		assert_eq!(parsed.metadata_raw, game.metadata_raw, "{}", name);
+		assert!(parsed.frames == game.frames, "{}", name);
+
+		// same as serializing it whole, but for the raw length (unknown up front)
+		let whole = serde::ser::serialize_to_vec(&game).map_err(|e| e.to_string())?;
+		assert_eq!(bytes[11 .. 15], [0, 0, 0, 0], "{}", name);
+		assert_eq!(bytes[15 ..], whole[15 ..], "{}", name);
+	}
+
+	let game = game("v3.12")?;
+	let index = |n: usize| game.frames.index(n);
+	let resume = |n: usize| Some(Resume { index: index(n), latest_finalized_frame: Some(index(n - 1)) });
+	let err = |range, resume| serde::ser::serialize_segment(&mut Vec::new(), &game, range, resume, None).unwrap_err();
+	// skipping a frame
+	let e = err(51 .. 100, resume(50));
+	assert!(matches!(e, UnparseError::FrameIndex { frame, expected } if frame == index(51) && expected == index(50)), "{:?}", e);
+	// rewriting a finalized frame
+	let e = err(49 .. 100, resume(50));
+	assert!(matches!(e, UnparseError::FrameIndex { frame, expected } if frame == index(49) && expected == index(50)), "{:?}", e);
+	// the first segment must start at the first frame
+	let e = err(1 .. 100, None);
+	assert!(matches!(e, UnparseError::FrameIndex { frame: -122, expected: -123 }), "{:?}", e);
+
+	// a segment may start with a rollback to a frame that wasn't finalized yet
+	let mut game = game;
+	match &mut game.frames {
+		Frames::P2(frames) => {
+			for f in frames.iter_mut() {
+				f.end.as_mut().unwrap().latest_finalized_frame = Some(f.index - 4);
+			}
+			let mut replayed: Vec<_> = frames[7 .. 11].to_vec();
+			for f in &mut replayed {
+				f.end = frames[10].end;
+			}
+			frames.splice(11 .. 11, replayed);
+		},
+		_ => Err("wrong number of ports")?,
+	}
+	let bytes = segmented(&game, &[11]).map_err(|e| e.to_string())?;
+	let opts = serde::collect::Opts { rollbacks: true, ..Default::default() };
+	let parsed = peppi::game(&mut &bytes[..], None, Some(opts)).map_err(|e| e.to_string())?;
+	assert!(parsed.frames == game.frames);
+	assert_eq!(parsed.frames.index(11), parsed.frames.index(7));
+
+	// ... but finalized frames can't become unfinalized
+	let latest = game.frames.index(10) - 3;
+	let resume = Some(Resume { index: game.frames.index(10) + 1, latest_finalized_frame: Some(latest) });
+	let e = serde::ser::serialize_segment(&mut Vec::new(), &game, 12 .. 100, resume, None).unwrap_err();
+	assert!(matches!(e, UnparseError::FrameIndex { frame, expected } if frame == latest - 1 && expected == latest), "{:?}", e);
+
+	Ok(())
+}
+
+#[test]
+fn migrate() -> Result<(), String> {
+	for name in ["ics", "items", "transform", "v3.12"] {
+		// keep it quick: the first 20 seconds are plenty
+		let mut game = game(name)?;
+		match &mut game.frames {
+			Frames::P1(f) => f.truncate(1200),
+			Frames::P2(f) => f.truncate(1200),
+			Frames::P3(f) => f.truncate(1200),
+			Frames::P4(f) => f.truncate(1200),
+		}
+		let json = serde_json::to_value(&game).unwrap();
+		assert_eq!(json["schema_version"], serde_json::json!(game::SCHEMA_VERSION));
+
+		// v1 is the same, minus `schema_version`
+		let mut v1 = json.clone();
+		v1.as_object_mut().unwrap().remove("schema_version");
+		for value in [json.clone(), v1] {
+			let migrated = game::migrate(value).map_err(|e| format!("{}: {}", name, e))?;
+			assert_eq!(serde_json::to_value(&migrated).unwrap(), json, "{}", name);
+			assert_eq!(migrated.start.players, game.start.players, "{}", name);
+			assert_eq!(migrated.metadata, game.metadata, "{}", name);
+			// including character-specific states
+			for n in 0 .. game.frames.len() {
+				for idx in 0 .. game.start.players.len() {
+					let (a, b) = (migrated.frames.port_data(n, idx), game.frames.port_data(n, idx));
+					assert_eq!(a.leader.post.state, b.leader.post.state, "{}", name);
+					assert_eq!(a.leader.pre, b.leader.pre, "{}", name);
+					assert_eq!(a.follower.as_ref().map(|f| f.post.state), b.follower.as_ref().map(|f| f.post.state), "{}", name);
+				}
+			}
+		}
+	}
+
+	let mut buf = Vec::new();
+	let mut nintendont = game("nintendont")?;
+	match &mut nintendont.frames {
+		Frames::P2(f) => f.truncate(1200),
+		_ => Err("wrong number of ports")?,
+	}
+	nintendont.write_full_json(&mut buf).map_err(|e| e.to_string())?;
+	let migrated = game::migrate(serde_json::from_slice(&buf).unwrap()).map_err(|e| e.to_string())?;
+	assert_eq!(migrated.frames.len(), nintendont.frames.len());
+	assert_eq!(migrated.start.players, nintendont.start.players);
+
+	let e = game::migrate(serde_json::json!({"schema_version": 99})).unwrap_err();
+	assert_eq!(e.to_string(), format!("unsupported schema version: 99 (max: {})", game::SCHEMA_VERSION));
+	let e = game::migrate(serde_json::json!({"start": {}})).unwrap_err();
+	assert!(e.to_string().starts_with("start: missing field"), "{}", e);
+
+	Ok(())
+}
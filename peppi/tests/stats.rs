@@ -0,0 +1,800 @@
+use std::{fs, io};
+
+use peppi::{
+	model::{
+		enums::action_state::{Common, State},
+		frame::{Data, StateFlags},
+		game::{Frames, Game},
+		primitives::{Direction, Port, Position},
+	},
+	stats,
+};
+
+fn game(name: &str) -> Result<Game, String> {
+	let mut buf = io::BufReader::new(
+		fs::File::open(format!("tests/data/{}.slp", name)).unwrap());
+	peppi::game(&mut buf, None, None).map_err(|e| format!("couldn't deserialize game: {:?}", e))
+}
+
+/// Mutable access to the leader data of the `port_idx`th port on each frame of a 2-port game.
+fn leaders(game: &mut Game, port_idx: usize) -> Vec<&mut Data> {
+	match &mut game.frames {
+		Frames::P2(frames) => frames.iter_mut().map(|f| &mut f.ports[port_idx].leader).collect(),
+		_ => panic!("wrong number of ports"),
+	}
+}
+
+/// Overwrites `(state, direction, joystick x)` for consecutive frames starting at `start`.
+fn script(game: &mut Game, port_idx: usize, start: usize, seq: &[(Common, Direction, f32)]) {
+	for (data, (state, direction, x)) in leaders(game, port_idx).into_iter().skip(start).zip(seq) {
+		data.post.state = State::Common(*state);
+		data.post.direction = *direction;
+		data.pre.joystick.x = *x;
+	}
+}
+
+#[test]
+fn movement() -> Result<(), String> {
+	let game = game("dash_back")?;
+	assert_eq!(stats::movement::movement(&game, Port::P1).dash_dances, 22);
+	assert_eq!(stats::movement::movement(&game, Port::P2), Default::default());
+	assert_eq!(stats::movement::movement(&game, Port::P3), Default::default());
+
+	use Direction::*;
+	let mut game = game;
+	let (w, d, t) = (Common::WAIT, Common::DASH, Common::TURN);
+	script(&mut game, 1, 100, &[
+		(w, Left, 0.0),
+		(d, Right, 1.0), (d, Right, 1.0),
+		(t, Right, -1.0), (d, Left, -1.0), // dash-dance via turn
+		(d, Right, 1.0), // dash-dance without turn
+		(t, Left, -0.5), (t, Left, -0.5), (w, Left, 0.0), // pivot
+		(d, Right, 1.0), (t, Left, 0.0), (w, Left, 0.0), // perfect pivot
+	]);
+	assert_eq!(stats::movement::movement(&game, Port::P2), stats::movement::MovementStats {
+		dash_dances: 2,
+		pivots: 2,
+		perfect_pivots: 1,
+	});
+
+	Ok(())
+}
+
+#[test]
+fn death_directions() -> Result<(), String> {
+	use stats::death::{death_directions, BlastZone::*};
+
+	let v2_0 = game("v2.0")?;
+	assert_eq!(death_directions(&v2_0, Port::P1), vec![
+		(1579, TopStar),
+		(5566, TopStar),
+		(9157, TopStar),
+	]);
+	assert_eq!(death_directions(&v2_0, Port::P2), vec![
+		(4189, TopStar),
+		(5347, Left),
+		(7319, TopScreen),
+		(10615, Right),
+	]);
+
+	let game = game("game")?;
+	assert_eq!(death_directions(&game, Port::P1), vec![]);
+	assert_eq!(death_directions(&game, Port::P2), vec![
+		(1876, Right),
+		(2578, Bottom),
+		(3715, Right),
+		(5085, Bottom),
+	]);
+
+	Ok(())
+}
+
+#[test]
+fn percent() -> Result<(), String> {
+	use stats::percent::{max_percent, percent_timeline};
+
+	let game = game("v2.0")?;
+	let timeline = percent_timeline(&game, Port::P2);
+	assert_eq!(timeline.len(), game.frames.len());
+	assert_eq!(timeline[0], (-123, 0.0));
+
+	// Fox dies at 153% and respawns at 0%
+	let respawn = timeline.iter().position(|(idx, _)| *idx == 4365).unwrap();
+	assert_eq!(timeline[respawn - 1], (4364, 153.18));
+	assert_eq!(timeline[respawn], (4365, 0.0));
+	assert_eq!(max_percent(&game, Port::P2), 153.18);
+
+	// game ends mid-stock
+	assert_eq!(timeline.last(), Some(&(10615, 130.92)));
+	assert_eq!(max_percent(&game, Port::P3), 0.0);
+
+	Ok(())
+}
+
+#[test]
+fn techs() -> Result<(), String> {
+	use stats::tech::{techs, Tech, TechOption::*};
+
+	let tech = |index, missed, option| Tech { index, missed, option, after_hit: true };
+
+	let v2_0 = game("v2.0")?;
+	assert_eq!(techs(&v2_0, Port::P1), vec![tech(3465, false, Some(RollLeft))]);
+	let p2 = techs(&v2_0, Port::P2);
+	assert_eq!(p2.len(), 18);
+	assert_eq!(p2[..2], [tech(2330, true, None), tech(2436, false, Some(InPlace))]);
+	// wall-jump tech out of an up-B, with no preceding hit
+	assert_eq!(p2[6], Tech { after_hit: false, ..tech(2990, false, Some(WallJump)) });
+	assert_eq!(p2[8..12], [
+		tech(6107, true, Some(GetUp)),
+		tech(6577, false, Some(InPlace)),
+		tech(6687, false, Some(InPlace)),
+		tech(6925, true, Some(RollRight)),
+	]);
+
+	let game = game("items")?;
+	assert_eq!(techs(&game, Port::P1), vec![]);
+	assert_eq!(techs(&game, Port::P2)[0], tech(870, true, Some(GetUpAttack)));
+
+	Ok(())
+}
+
+#[test]
+fn sdi() -> Result<(), String> {
+	use stats::sdi::{sdi, SdiInput};
+
+	let mut v2_0 = game("v2.0")?;
+	let p2 = sdi(&v2_0, Port::P2);
+	assert_eq!(p2.len(), 48);
+	assert_eq!(p2.iter().map(|e| e.inputs.len()).sum::<usize>(), 10);
+	let e = p2.iter().find(|e| e.index == 5286).unwrap();
+	assert_eq!(e.frames, 11);
+	assert_eq!(e.inputs.iter().map(|i| i.index).collect::<Vec<_>>(), vec![5286, 5287]);
+
+	// two hits of a multi-hit move, SDI'd left, right, left, up, then held
+	let (n, hl, un) = (Common::WAIT, StateFlags::HIT_LAG, StateFlags::default());
+	let seq = [
+		(n, un, -1.0, 0.0), // already holding left
+		(Common::DAMAGE_N_1, hl, -1.0, 0.0),
+		(Common::DAMAGE_N_1, hl, 1.0, 0.0),
+		(Common::DAMAGE_N_1, hl, -1.0, 0.0),
+		(Common::DAMAGE_N_1, un, 0.0, 0.0),
+		(Common::DAMAGE_N_2, hl, 0.0, 0.0),
+		(Common::DAMAGE_N_2, hl, 0.0, 0.75),
+		(Common::DAMAGE_N_2, hl, 0.0, 0.8),
+		(Common::DAMAGE_N_2, un, 0.0, 0.0),
+	];
+	for (data, (state, flags, x, y)) in leaders(&mut v2_0, 0).into_iter().skip(300).zip(seq) {
+		data.post.state = State::Common(state);
+		data.post.flags = Some(flags);
+		data.pre.joystick = Position { x, y };
+	}
+	let p1 = sdi(&v2_0, Port::P1);
+	let hits: Vec<_> = p1.iter().filter(|e| (178 ..= 186).contains(&e.index)).collect();
+	assert_eq!(hits.len(), 2);
+	assert_eq!((hits[0].index, hits[0].frames), (178, 3));
+	assert_eq!(hits[0].inputs, vec![
+		SdiInput { index: 179, joystick: Position { x: 1.0, y: 0.0 } },
+		SdiInput { index: 180, joystick: Position { x: -1.0, y: 0.0 } },
+	]);
+	assert_eq!((hits[1].index, hits[1].frames, hits[1].inputs.len()), (182, 3, 1));
+
+	// `hitlag` takes precedence over the flag when present (v3.8+)
+	for data in leaders(&mut v2_0, 0).into_iter().skip(301).take(3) {
+		data.post.hitlag = Some(0.0);
+	}
+	assert!(sdi(&v2_0, Port::P1).iter().all(|e| e.index != 178));
+
+	// only Popo is considered: Nana (not in hitlag) doesn't split his hit in two
+	let mut ics = game("ics")?;
+	let seq = [
+		(n, un, 0.0),
+		(Common::DAMAGE_N_1, hl, 1.0),
+		(Common::DAMAGE_N_1, hl, -1.0),
+		(Common::DAMAGE_N_1, hl, 1.0),
+		(n, un, 0.0),
+	];
+	for (data, (state, flags, x)) in leaders(&mut ics, 0).into_iter().skip(100).zip(seq) {
+		data.post.state = State::Common(state);
+		data.post.flags = Some(flags);
+		data.pre.joystick = Position { x, y: 0.0 };
+	}
+	let popo = sdi(&ics, Port::P1);
+	assert_eq!(popo.len(), 1);
+	assert_eq!((popo[0].index, popo[0].frames, popo[0].inputs.len()), (ics.frames.index(101), 3, 3));
+
+	Ok(())
+}
+
+#[test]
+fn stocks() -> Result<(), String> {
+	use stats::stock::{stock_timeline, Stock};
+
+	let v2_0 = game("v2.0")?;
+	let post = |index: i32| match &v2_0.frames {
+		Frames::P2(frames) => frames[(index + 123) as usize].ports[1].leader.post,
+		_ => panic!("wrong number of ports"),
+	};
+
+	// Fox is star-KO'd, then respawns
+	assert!(!post(4188).is_dead());
+	assert!(post(4189).is_dead());
+	assert!(post(4364).is_dead() && !post(4364).is_respawn());
+	assert!(!post(4365).is_dead() && post(4365).is_respawn());
+	assert_eq!((post(4364).stocks, post(4365).stocks), (3, 3));
+
+	assert_eq!(stock_timeline(&v2_0, Port::P2), vec![
+		Stock { count: 4, start: -123, end: Some(4189) },
+		Stock { count: 3, start: 4365, end: Some(5347) },
+		Stock { count: 2, start: 5407, end: Some(7319) },
+		Stock { count: 1, start: 7448, end: Some(10615) },
+	]);
+	// game ends mid-stock
+	assert_eq!(stock_timeline(&v2_0, Port::P1).last(), Some(&Stock { count: 1, start: 9333, end: None }));
+	assert_eq!(stock_timeline(&v2_0, Port::P3), vec![]);
+
+	Ok(())
+}
+
+#[test]
+fn kills() -> Result<(), String> {
+	use peppi::model::enums::attack::Attack;
+	use stats::kill::{kills, Kill};
+
+	// Marth edgeguards Fox: hit below the ledge at 46.2%, & dies off the bottom 44 frames later
+	let marth_fox = kills(&game("game")?);
+	assert_eq!(marth_fox.iter().map(|k| k.index).collect::<Vec<_>>(), vec![1876, 2578, 3715, 5085]);
+	assert_eq!(marth_fox[1], Kill {
+		killer: Port::P1,
+		victim: Port::P2,
+		attack: Some(Attack::DOWN_TILT),
+		index: 2578,
+		percent: 46.2,
+	});
+
+	// P2 self-destructs twice, long after they were last hit
+	let nintendont = game("nintendont")?;
+	let deaths = stats::death::death_directions(&nintendont, Port::P2);
+	assert_eq!(deaths.len(), 4);
+	let p2_kills: Vec<_> = kills(&nintendont).into_iter()
+		.filter(|k| k.victim == Port::P2)
+		.map(|k| (k.index, k.killer))
+		.collect();
+	assert_eq!(p2_kills, vec![(3856, Port::P4), (11912, Port::P4)]);
+
+	// every death in items.slp is a self-destruct
+	assert_eq!(kills(&game("items")?), vec![]);
+
+	Ok(())
+}
+
+#[test]
+fn combos() -> Result<(), String> {
+	let game = game("game")?;
+	let combos = stats::combo::combos(&game, Port::P1, None);
+	assert_eq!(combos.len(), 13);
+	assert_eq!(combos[0], stats::combo::Combo {
+		victim: Port::P2,
+		start: 81,
+		end: 310,
+		hits: 4,
+		start_percent: 0.0,
+		end_percent: 39.0,
+		killed: false,
+	});
+	assert_eq!(combos.iter().filter(|c| c.killed).count(), 4);
+
+	let ranges: Vec<_> = combos.iter().map(|c| (c.start, c.end)).collect();
+	assert_eq!(stats::combo::clip_ranges(&game, Port::P1, 0, 0), ranges);
+
+	// the 5th & 6th combos are 50 frames apart, so they merge into one clip
+	let clips = stats::combo::clip_ranges(&game, Port::P1, 0, 55);
+	assert_eq!(clips[3..5], [(1144, 1231), (1235, 1491)]);
+
+	// clamped to the game's first & last frames
+	assert_eq!(stats::combo::clip_ranges(&game, Port::P2, 0, 0), vec![(4620, 4632)]);
+	assert_eq!(stats::combo::clip_ranges(&game, Port::P2, 10_000, 10_000), vec![(-123, 5085)]);
+
+	// only Popo counts as a victim, frame-for-frame with the attacker
+	let ics = self::game("ics")?;
+	assert_eq!(stats::combo::combos(&ics, Port::P2, None), vec![stats::combo::Combo {
+		victim: Port::P1,
+		start: 114,
+		end: 128,
+		hits: 1,
+		start_percent: 0.0,
+		end_percent: 10.0,
+		killed: false,
+	}]);
+
+	Ok(())
+}
+
+#[test]
+fn combos_game_counter() -> Result<(), String> {
+	let game = game("v2.0")?;
+	let summary = |opts| stats::combo::combos(&game, Port::P1, opts).into_iter()
+		.filter(|c| c.start < 3000)
+		.map(|c| (c.start, c.hits))
+		.collect::<Vec<_>>();
+
+	// frame gaps: the hits at 1403 & 1429 count as one combo (even though the game reset its
+	// counter in between), but the hit at 2525 comes too long after the one at 2410
+	assert_eq!(summary(None), vec![
+		(47, 1), (790, 6), (1403, 2), (1951, 1), (2306, 1),
+		(2410, 1), (2525, 1), (2694, 1), (2803, 1), (2904, 1),
+	]);
+
+	// game counter: the other way around
+	let opts = Some(stats::combo::Opts { use_game_counter: true });
+	assert_eq!(summary(opts), vec![
+		(47, 1), (790, 6), (1403, 1), (1429, 1), (1951, 1),
+		(2306, 1), (2410, 2), (2694, 1), (2803, 1), (2904, 1),
+	]);
+
+	// Ice Climbers: Popo's counter is read alongside Puff's frame of the same index, with Nana's
+	// (left at 0) ignored. Hits at 200 & 210 count as one combo, split from the hit at 230 when
+	// the counter resets at 220.
+	let mut ics = self::game("ics")?;
+	for (n, data) in leaders(&mut ics, 0).into_iter().enumerate().skip(200).take(60) {
+		data.post.combo_count = match n {
+			200 .. 210 => 1,
+			210 .. 220 => 2,
+			220 .. 230 => 0,
+			_ => 1,
+		};
+	}
+	for (n, data) in leaders(&mut ics, 1).into_iter().enumerate().skip(200) {
+		data.post.damage = match n {
+			200 .. 210 => 5.0,
+			210 .. 230 => 10.0,
+			_ => 15.0,
+		};
+		data.post.last_hit_by = Some(Port::P1);
+	}
+	let index = |n| ics.frames.index(n);
+	let combos: Vec<_> = stats::combo::combos(&ics, Port::P1, opts).into_iter()
+		.map(|c| (c.start, c.end, c.hits))
+		.collect();
+	assert_eq!(combos, vec![(index(200), index(210), 2), (index(230), index(230), 1)]);
+
+	Ok(())
+}
+
+#[test]
+fn grabs() -> Result<(), String> {
+	use peppi::model::enums::attack::Attack;
+
+	// Fox up-throws three times, following up the last with an up-air
+	let v2 = game("v2.0")?;
+	let grabs = stats::grab::grabs(&v2, Port::P2);
+	assert_eq!(grabs.iter().map(|g| g.index).collect::<Vec<_>>(), vec![509, 4718, 8347]);
+	assert!(grabs.iter().all(|g| g.victim == Port::P1 && g.throw == Some(Attack::UP_THROW)));
+	let uthrow_uair = grabs[2];
+	assert_eq!(uthrow_uair.teched, None);
+	assert!((uthrow_uair.follow_up_damage - 23.29).abs() < 0.01, "{:?}", uthrow_uair);
+
+	// Marth's up-throw, followed by a missed tech
+	let marth = game("game")?;
+	let grab = stats::grab::grabs(&marth, Port::P1)[0];
+	assert_eq!((grab.index, grab.throw, grab.teched), (133, Some(Attack::UP_THROW), Some(false)));
+	assert!(stats::grab::grabs(&marth, Port::P2).is_empty());
+
+	Ok(())
+}
+
+#[test]
+fn ledge() -> Result<(), String> {
+	use stats::ledge::LedgeStats;
+
+	// Marth spends a lot of this game on the ledge, twice while Fox is trying to recover
+	let game = game("game")?;
+	assert_eq!(stats::ledge::ledge(&game, Port::P1), LedgeStats {
+		grabs: 12,
+		frames: 336,
+		ledgehogs: 2,
+	});
+	assert_eq!(stats::ledge::ledge(&game, Port::P2), LedgeStats::default());
+	assert_eq!(stats::ledge::ledge(&game, Port::P3), LedgeStats::default());
+
+	Ok(())
+}
+
+#[test]
+fn corpus() -> Result<(), String> {
+	use std::time::Duration;
+	use peppi::model::enums::{character::External, stage::Stage};
+	use stats::corpus::Record;
+
+	let dir = std::env::temp_dir().join(format!("peppi-corpus-{}", std::process::id()));
+	fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+	for name in ["game", "v2.0", "items"] {
+		fs::copy(format!("tests/data/{}.slp", name), dir.join(format!("{}.slp", name)))
+			.map_err(|e| e.to_string())?;
+	}
+	fs::write(dir.join("garbage.slp"), b"not a replay").map_err(|e| e.to_string())?;
+	fs::write(dir.join("notes.txt"), b"ignored").map_err(|e| e.to_string())?;
+
+	let summary = stats::corpus::summarize_dir(&dir).map_err(|e| e.to_string());
+	fs::remove_dir_all(&dir).map_err(|e| e.to_string())?;
+	let summary = summary?;
+
+	assert_eq!(summary.games, 3);
+	assert_eq!(summary.skipped, vec![dir.join("garbage.slp")]);
+
+	assert_eq!(summary.characters[&External::FOX], Record { wins: 0, losses: 2 });
+	assert_eq!(summary.characters[&External::MARTH], Record { wins: 1, losses: 1 });
+	assert_eq!(summary.characters[&External::PEACH], Record { wins: 1, losses: 0 });
+	assert_eq!(summary.characters.len(), 4);
+
+	assert_eq!(summary.matchups[&(External::FOX, External::MARTH)], 1);
+	assert_eq!(summary.matchups[&(External::MARTH, External::PEACH)], 1);
+	assert_eq!(summary.matchups.len(), 3);
+
+	assert_eq!(summary.stages[&Stage::YOSHIS_STORY], 2);
+	assert_eq!(summary.stages[&Stage::FINAL_DESTINATION], 1);
+
+	assert_eq!(summary.frames, 5209 + 10739 + 7375);
+	assert_eq!(summary.playtime, Duration::from_secs(5209 + 10739 + 7375) / 60);
+
+	Ok(())
+}
+
+#[test]
+fn staleness() -> Result<(), String> {
+	use peppi::model::enums::attack::Attack;
+	use stats::stale::StaleLevel;
+
+	let game = game("v2.0")?;
+	let moves = stats::stale::staleness(&game, Port::P1);
+	assert_eq!(moves[0], (47, Attack::BAIR, StaleLevel::default()));
+	assert_eq!(moves[0].2.multiplier(), 1.0);
+
+	// a multi-hit dair only enters the queue once
+	assert_eq!(moves.iter().filter(|(index, _, _)| (790 ..= 815).contains(index)).count(), 1);
+
+	// using a move again makes it staler
+	let level = |index: i32| moves.iter().find(|m| m.0 == index).map(|m| (m.1, m.2));
+	let (fair, first) = level(2803).ok_or("missing fair")?;
+	let (again, second) = level(2904).ok_or("missing fair")?;
+	assert_eq!((fair, again), (Attack::FAIR, Attack::FAIR));
+	assert!(second.count() > first.count());
+	assert!(second.multiplier() < first.multiplier());
+
+	// until the next stock
+	assert_eq!(level(8988).map(|(_, l)| l.count() > 0), Some(true));
+	assert_eq!(level(9812), Some((Attack::BAIR, StaleLevel::default())));
+
+	assert!(stats::stale::staleness(&game, Port::P3).is_empty());
+	Ok(())
+}
+
+#[test]
+fn slippi_js_json() -> Result<(), String> {
+	let game = game("game")?;
+	let stats = stats::slippi_js::to_slippi_js_json(&game);
+
+	assert_eq!(stats["lastFrame"], 5085);
+	assert_eq!(stats["playableFrameCount"], 5085 + 39);
+	assert_eq!(stats["gameComplete"], true);
+
+	// Fox (P2) loses all four stocks; Marth (P1) none
+	let stocks = stats["stocks"].as_array().ok_or("missing stocks")?;
+	let fox: Vec<_> = stocks.iter().filter(|s| s["playerIndex"] == 1).collect();
+	assert_eq!(fox.iter().map(|s| s["endFrame"].as_i64()).collect::<Vec<_>>(),
+		vec![Some(1876), Some(2578), Some(3715), Some(5085)]);
+	assert_eq!(fox.iter().map(|s| s["count"].as_u64()).collect::<Vec<_>>(),
+		vec![Some(4), Some(3), Some(2), Some(1)]);
+	assert_eq!(fox[0]["startFrame"], -123);
+	assert_eq!(fox[0]["startPercent"], 0.0);
+	assert!(fox[0]["endPercent"].as_f64().is_some_and(|p| p > 100.0));
+	assert!(stocks.iter().any(|s| s["playerIndex"] == 0 && s["endFrame"].is_null()));
+
+	// conversions are `conversion::conversions`, field for field
+	let conversions = stats["conversions"].as_array().ok_or("missing conversions")?;
+	let marth = stats::conversion::conversions(&game, Port::P1);
+	let fox = stats::conversion::conversions(&game, Port::P2);
+	assert_eq!(conversions.len(), marth.len() + fox.len());
+	for (json, c) in conversions.iter().filter(|c| c["lastHitBy"] == 0).zip(&marth) {
+		assert_eq!(json["playerIndex"], 1);
+		assert_eq!(json["startFrame"], c.start);
+		assert_eq!(json["endFrame"].as_i64(), c.end.map(i64::from));
+		assert_eq!(json["startPercent"], c.start_percent);
+		assert_eq!(json["currentPercent"], c.end_percent);
+		assert_eq!(json["didKill"], c.killed);
+		let moves = json["moves"].as_array().ok_or("missing moves")?;
+		assert_eq!(moves.len(), c.moves.len());
+		for (json, m) in moves.iter().zip(&c.moves) {
+			assert_eq!(json["playerIndex"], 0);
+			assert_eq!(json["frame"], m.index);
+			assert_eq!(json["moveId"], m.attack.map_or(0, |a| a.0));
+			assert_eq!(json["hitCount"], m.hits);
+			assert_eq!(json["damage"], m.damage);
+		}
+	}
+	assert_eq!(conversions[0]["startFrame"], 81);
+	assert_eq!(conversions[0]["moves"][0]["moveId"], 17);
+	assert_eq!(conversions.iter().filter(|c| c["didKill"] == true).count(), 4);
+
+	// Marth's last conversion starts while Fox's is still going
+	let counter = conversions.iter().filter(|c| c["openingType"] == "counter-attack").collect::<Vec<_>>();
+	assert_eq!(counter.len(), 1);
+	assert_eq!(counter[0]["lastHitBy"], 0);
+	assert_eq!(counter[0]["startFrame"], 4650);
+	assert!(fox.iter().any(|c| c.start < 4650 && c.end.is_none_or(|end| end > 4650)));
+	assert!(conversions.iter().all(|c| c["openingType"] == "counter-attack" || c["openingType"] == "neutral-win"));
+
+	assert_eq!(stats["actionCounts"][0]["ledgegrabCount"], 12);
+	// grab attempts: five connect (and are thrown), & Marth whiffs at 306 & 1680
+	assert_eq!(stats["actionCounts"][0]["grabCount"], serde_json::json!({"success": 5, "fail": 2}));
+	assert_eq!(stats["actionCounts"][1]["grabCount"], serde_json::json!({"success": 0, "fail": 0}));
+	let throws = &stats["actionCounts"][0]["throwCount"];
+	assert_eq!(["up", "forward", "back", "down"].iter().map(|t| throws[*t].as_u64().unwrap()).sum::<u64>(), 5);
+	for whiff in [306, 1680] {
+		let run = game.state_runs(Port::P1).into_iter().find(|(_, start, _)| *start == whiff).unwrap();
+		assert_eq!(run.0, State::Common(Common::CATCH));
+	}
+	assert_eq!(stats["overall"][0]["killCount"], 4);
+	assert_eq!(stats["overall"][0]["conversionCount"],
+		conversions.iter().filter(|c| c["lastHitBy"] == 0).count());
+	assert!(stats["overall"][1]["openingsPerKill"]["ratio"].is_null());
+
+	Ok(())
+}
+
+#[test]
+fn velocity_consistency() -> Result<(), String> {
+	for name in ["v3.12", "netplay"] {
+		let game = game(name)?;
+		for p in &game.start.players {
+			assert_eq!(stats::velocity::velocity_consistency(&game, p.port), vec![], "{}: {:?}", name, p.port);
+		}
+	}
+
+	// nudging a character mid-air shows up on that frame & the next
+	let mut game = game("v3.12")?;
+	let airborne: Vec<_> = leaders(&mut game, 0).iter()
+		.map(|d| d.post.airborne == Some(true) && u16::from(d.post.state) < Common::ENTRY.0)
+		.collect();
+	let n = (1 .. airborne.len() - 1).find(|n| airborne[n - 1 ..= n + 1].iter().all(|a| *a)).unwrap();
+	leaders(&mut game, 0)[n].post.position.y += 5.0;
+	let anomalies = stats::velocity::velocity_consistency(&game, Port::P1);
+	assert_eq!(anomalies.iter().map(|a| a.index).collect::<Vec<_>>(),
+		vec![game.frames.index(n), game.frames.index(n + 1)]);
+	assert!((anomalies[0].divergence() - 5.0).abs() < 0.01);
+
+	Ok(())
+}
+
+#[test]
+fn neutral() -> Result<(), String> {
+	use stats::neutral::{OpeningType::*, PortNeutral};
+
+	// a quiet game, apart from the hits below
+	let mut game = game("game")?;
+	for idx in 0 .. 2 {
+		for data in leaders(&mut game, idx) {
+			data.post.state = State::Common(Common::WAIT);
+			data.post.damage = 0.0;
+			data.post.last_hit_by = None;
+			data.post.stocks = 4;
+		}
+	}
+	// (frame index, attacker's port index)
+	let hits = [
+		(1000, 0), // P1 wins neutral
+		(1020, 1), // P2 hits back before the combo is over
+		(1200, 0), (1200, 1), // trade
+		(1500, 1), // P2 wins neutral
+	];
+	let first = game.frames.index(0);
+	for (index, attacker) in hits {
+		let (victim, port) = (1 - attacker, [Port::P1, Port::P2][attacker]);
+		for data in leaders(&mut game, victim).into_iter().skip((index - first) as usize) {
+			data.post.damage += 10.0;
+			data.post.last_hit_by = Some(port);
+		}
+	}
+
+	let neutral = stats::neutral::neutral(&game).map_err(|e| e.to_string())?;
+	assert_eq!(neutral.players, [
+		PortNeutral { port: Port::P1, neutral_wins: 1, counter_hits: 0, trades: 1 },
+		PortNeutral { port: Port::P2, neutral_wins: 1, counter_hits: 1, trades: 1 },
+	]);
+	assert_eq!(neutral.openings.iter().map(|o| (o.index, o.port, o.r#type)).collect::<Vec<_>>(), vec![
+		(1000, Port::P1, NeutralWin),
+		(1020, Port::P2, CounterHit),
+		(1200, Port::P1, Trade),
+		(1200, Port::P2, Trade),
+		(1500, Port::P2, NeutralWin),
+	]);
+
+	game.start.players.truncate(1);
+	assert!(stats::neutral::neutral(&game).is_err());
+	Ok(())
+}
+
+#[test]
+fn aerial_time() -> Result<(), String> {
+	use stats::aerial::{aerial_time, AerialStats};
+
+	// Jigglypuff spends most of the game in the air, unlike Fox
+	let v2_0 = game("v2.0")?;
+	let puff = aerial_time(&v2_0, Port::P1);
+	let fox = aerial_time(&v2_0, Port::P2);
+	for s in [puff, fox] {
+		assert_eq!(s.airborne_frames + s.grounded_frames, v2_0.frames.len());
+		assert_eq!(s.unknown_frames, 0);
+	}
+	assert_eq!(puff, AerialStats { airborne_frames: 7384, grounded_frames: 3355, unknown_frames: 0, jumps: 170 });
+	assert!(fox.airborne_frames < puff.airborne_frames);
+
+	// `airborne` & `jumps` were added in v2.0
+	let v0_1 = game("v0.1")?;
+	assert_eq!(aerial_time(&v0_1, Port::P1), AerialStats { unknown_frames: v0_1.frames.len(), ..Default::default() });
+	assert_eq!(aerial_time(&v0_1, Port::P3), AerialStats::default());
+
+	Ok(())
+}
+
+#[test]
+fn crossups() -> Result<(), String> {
+	use stats::crossup::{crossups, Crossup};
+
+	// a quiet game in which P1 stands at x = -5 & P2 at x = 0
+	let mut game = game("game")?;
+	for (idx, x) in [(0, -5.0), (1, 0.0)] {
+		for data in leaders(&mut game, idx) {
+			data.post.state = State::Common(Common::WAIT);
+			data.post.damage = 0.0;
+			data.post.last_hit_by = None;
+			data.post.stocks = 4;
+			data.post.position = Position { x, y: 0.0 };
+		}
+	}
+	// P1 hits P2 in the back at frame 1000 (P2 facing right, away from P1),
+	// then in the front at frame 1500
+	let first = game.frames.index(0);
+	for (n, data) in leaders(&mut game, 1).into_iter().enumerate() {
+		let index = first + n as i32;
+		data.post.direction = match index < 1499 {
+			true => Direction::Right,
+			_ => Direction::Left,
+		};
+		data.post.damage = match index {
+			i if i >= 1500 => 20.0,
+			i if i >= 1000 => 10.0,
+			_ => 0.0,
+		};
+		data.post.last_hit_by = (index >= 1000).then_some(Port::P1);
+	}
+
+	assert_eq!(stats::combo::combos(&game, Port::P1, None).iter().map(|c| c.start).collect::<Vec<_>>(), vec![1000, 1500]);
+	assert_eq!(crossups(&game, Port::P1), vec![Crossup {
+		victim: Port::P2,
+		index: 1000,
+		direction: Direction::Right,
+		offset: -5.0,
+	}]);
+	assert_eq!(crossups(&game, Port::P2), vec![]);
+	assert_eq!(crossups(&game, Port::P3), vec![]);
+
+	Ok(())
+}
+
+#[test]
+fn conversions() -> Result<(), String> {
+	use peppi::model::enums::attack::Attack;
+	use stats::conversion::{conversions, Conversion, Move};
+
+	// a quiet game in which P1 jabs twice & side-tilts P2, who then recovers, &
+	// later up-smashes P2 to death
+	let mut game = game("game")?;
+	let first = game.frames.index(0);
+	for (n, data) in leaders(&mut game, 0).into_iter().enumerate() {
+		let index = first + n as i32;
+		let (state, start, attack) = match index {
+			995 ..= 1005 => (Common::ATTACK_11, 995, Some(Attack::JAB_1)),
+			1010 ..= 1020 => (Common::ATTACK_S_3_S, 1010, Some(Attack::SIDE_TILT)),
+			1995 ..= 2010 => (Common::ATTACK_HI_4, 1995, Some(Attack::UP_SMASH)),
+			_ => (Common::WAIT, first, None),
+		};
+		data.post.state = State::Common(state);
+		data.post.state_age = Some((index - start) as f32);
+		data.post.last_attack_landed = attack;
+		data.post.damage = 0.0;
+		data.post.stocks = 4;
+	}
+	for (n, data) in leaders(&mut game, 1).into_iter().enumerate() {
+		let index = first + n as i32;
+		data.post.state = State::Common(match index {
+			1000 .. 1030 => Common::DAMAGE_HI_1,
+			2000 .. 2050 => Common::DAMAGE_FLY_HI,
+			2050 .. 2100 => Common::DEAD_UP,
+			_ => Common::WAIT,
+		});
+		data.post.damage = match index {
+			i if i >= 2050 => 0.0,
+			i if i >= 2000 => 40.0,
+			i if i >= 1012 => 18.0,
+			i if i >= 1001 => 8.0,
+			i if i >= 1000 => 5.0,
+			_ => 0.0,
+		};
+		data.post.stocks = match index >= 2050 {
+			true => 3,
+			_ => 4,
+		};
+	}
+
+	let convs = conversions(&game, Port::P1);
+	assert_eq!(convs, vec![
+		Conversion {
+			victim: Port::P2,
+			start: 1000,
+			end: Some(1075),
+			start_percent: 0.0,
+			end_percent: 18.0,
+			moves: vec![
+				Move { index: 1000, attack: Some(Attack::JAB_1), hits: 2, damage: 8.0 },
+				Move { index: 1012, attack: Some(Attack::SIDE_TILT), hits: 1, damage: 10.0 },
+			],
+			killed: false,
+		},
+		Conversion {
+			victim: Port::P2,
+			start: 2000,
+			end: Some(2050),
+			start_percent: 18.0,
+			end_percent: 40.0,
+			moves: vec![
+				Move { index: 2000, attack: Some(Attack::UP_SMASH), hits: 1, damage: 22.0 },
+			],
+			killed: true,
+		},
+	]);
+	assert_eq!(convs[0].opening_move().and_then(|m| m.attack), Some(Attack::JAB_1));
+	assert_eq!(convs[0].damage(), 18.0);
+	assert_eq!(convs[0].duration(), Some(75));
+	assert_eq!(conversions(&game, Port::P2), vec![]);
+	assert_eq!(conversions(&game, Port::P3), vec![]);
+
+	Ok(())
+}
+
+#[test]
+fn di() -> Result<(), String> {
+	use stats::di::{di, MAX_DI_ANGLE};
+
+	let mut game = game("items")?;
+	let events = di(&game, Port::P2);
+	let no_di = events.iter().find(|e| e.index == 210).unwrap();
+	assert_eq!((no_di.joystick, no_di.launch_angle, no_di.rotation), (Position { x: 0.0, y: 0.0 }, 136.0, 0.0));
+
+	// P2 held up-left, nearly perpendicular to a 44° launch, turning it steeply up
+	let bad = *events.iter().find(|e| e.index == 472).unwrap();
+	assert!((bad.launch_angle - 44.0).abs() < 0.01, "{:?}", bad);
+	assert!(bad.rotation > 0.95 * MAX_DI_ANGLE && bad.rotation <= MAX_DI_ANGLE, "{:?}", bad);
+	assert!(bad.score.unwrap() < 0.1, "{:?}", bad);
+
+	// had they held down-right instead, the hit would have sent them out at ~26° instead of ~62°
+	let first = game.frames.index(0);
+	let launch = &mut leaders(&mut game, 1)[(472 - first) as usize];
+	let velocities = launch.post.velocities.as_mut().unwrap();
+	let speed = velocities.knockback.x.hypot(velocities.knockback.y);
+	let (sin, cos) = (bad.launch_angle - bad.rotation).to_radians().sin_cos();
+	velocities.knockback.x = speed * cos;
+	velocities.knockback.y = speed * sin;
+	launch.pre.joystick = Position { x: -bad.joystick.x, y: -bad.joystick.y };
+
+	let good = *di(&game, Port::P2).iter().find(|e| e.index == 472).unwrap();
+	assert!((good.launch_angle - bad.launch_angle).abs() < 0.01, "{:?}", good);
+	assert!((good.rotation + bad.rotation).abs() < 0.01, "{:?}", good);
+	// (not quite the best, which heads for the corner of the blast zones at ~37°)
+	assert!(good.score.unwrap() > 0.6, "{:?}", good);
+
+	assert_eq!(di(&game, Port::P3), vec![]);
+	// no knockback velocities before v3.5
+	assert_eq!(di(&self::game("game")?, Port::P1), vec![]);
+
+	Ok(())
+}